@@ -2,6 +2,8 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
 
 #[derive(Debug)]
 pub struct PatternElem {
@@ -10,12 +12,17 @@ pub struct PatternElem {
     pub inv: bool,
     pub chars: Vec<char>, 
     pub ranges: Vec<(char, char)>, 
+    pub group: Option<(char, Vec<Vec<PatternElem>>)>, // ?(a|b) and so on of extglob
 }
 
-pub fn judge(s: &String, pos: usize, pe: &PatternElem) -> Vec<usize> {
+pub fn judge(s: &[char], pos: usize, pe: &PatternElem) -> Vec<usize> {
+    if let Some((op, alts)) = &pe.group {
+        return judge_group(s, pos, *op, alts).into_iter().collect();
+    }
+
     let mut ans = vec![];
     if pe.asterisk {
-        for n in pos..s.chars().count()+1 {
+        for n in pos..s.len()+1 {
             ans.push(n);
         }
 
@@ -23,12 +30,12 @@ pub fn judge(s: &String, pos: usize, pe: &PatternElem) -> Vec<usize> {
     }
 
     if pe.question {
-        return vec!(pos+1);
+        return if pos < s.len() { vec!(pos+1) }else{ vec![] };
     }
 
-    if let Some(c) = s.chars().nth(pos) {
+    if let Some(&c) = s.get(pos) {
         let matched = pe.chars.iter().any(|ch| ch == &c) 
-                      || pe.ranges.iter().any(|r| r.0 < c && c < r.1);
+                      || pe.ranges.iter().any(|r| r.0 <= c && c <= r.1);
 
         if (pe.inv && ! matched) || (!pe.inv && matched) {
              ans.push(pos+1);
@@ -38,6 +45,67 @@ pub fn judge(s: &String, pos: usize, pe: &PatternElem) -> Vec<usize> {
     ans
 }
 
+/* the end positions of the part of s from pos which the pattern elements match */
+fn match_ends(s: &[char], starts: HashSet<usize>, pattern: &[PatternElem]) -> HashSet<usize> {
+    let mut poss = starts;
+    for pat in pattern {
+        let mut poss_new = HashSet::new();
+        for p in poss {
+            for n in judge(s, p, pat) {
+                poss_new.insert(n);
+            }
+        }
+        poss = poss_new;
+        if poss.is_empty() {
+            break;
+        }
+    }
+    poss
+}
+
+fn match_alternatives(s: &[char], pos: usize, alts: &[Vec<PatternElem>]) -> HashSet<usize> {
+    let mut ans = HashSet::new();
+    for alt in alts {
+        ans.extend(match_ends(s, HashSet::from([pos]), alt));
+    }
+    ans
+}
+
+/* repeats the alternatives until no new end position is found */
+fn match_repeatedly(s: &[char], starts: HashSet<usize>, alts: &[Vec<PatternElem>]) -> HashSet<usize> {
+    let mut ans = starts.clone();
+    let mut frontier = starts;
+    while ! frontier.is_empty() {
+        let mut next = HashSet::new();
+        for p in frontier {
+            for n in match_alternatives(s, p, alts) {
+                if ans.insert(n) {
+                    next.insert(n);
+                }
+            }
+        }
+        frontier = next;
+    }
+    ans
+}
+
+fn judge_group(s: &[char], pos: usize, op: char, alts: &[Vec<PatternElem>]) -> HashSet<usize> {
+    match op {
+        '?' => {
+            let mut ans = match_alternatives(s, pos, alts);
+            ans.insert(pos);
+            ans
+        },
+        '*' => match_repeatedly(s, HashSet::from([pos]), alts),
+        '+' => match_repeatedly(s, match_alternatives(s, pos, alts), alts),
+        '!' => {
+            let matched = match_alternatives(s, pos, alts);
+            (pos..s.len()+1).filter(|n| ! matched.contains(n)).collect()
+        },
+        _ => match_alternatives(s, pos, alts), // @
+    }
+}
+
 fn wildcard() -> PatternElem {
     PatternElem {
         asterisk: true,
@@ -45,6 +113,7 @@ fn wildcard() -> PatternElem {
         inv: false,
         chars: vec![],
         ranges: vec![],
+        group: None,
     }
 }
 
@@ -73,6 +142,7 @@ fn bracket(chs: &Vec<char>) -> PatternElem {
         inv: inv,
         chars: chars2,
         ranges: ranges,
+        group: None,
     }
 }
 
@@ -83,6 +153,7 @@ fn anychar() -> PatternElem {
         inv: false,
         chars: vec![],
         ranges: vec![],
+        group: None,
     }
 }
 
@@ -93,26 +164,89 @@ fn simple_char(c: char) -> PatternElem {
         inv: false,
         chars: vec!(c),
         ranges: vec![],
+        group: None,
+    }
+}
+
+/* the position of the ) closing the group which starts at from */
+fn group_end(chars: &[char], from: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, ch) in chars.iter().enumerate().skip(from) {
+        if escaped {
+            escaped = false;
+        }else if *ch == '\\' {
+            escaped = true;
+        }else if *ch == '(' {
+            depth += 1;
+        }else if *ch == ')' {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
     }
+    None
 }
 
-fn set_glob(glob: &String) -> Vec<PatternElem> {
+/* a|b(c|d) -> [a, b(c|d)] */
+fn split_alternatives(chars: &[char]) -> Vec<String> {
+    let mut ans = vec![];
+    let mut depth = 0;
+    let mut escaped = false;
+    let mut alt = String::new();
+    for ch in chars {
+        if escaped {
+            escaped = false;
+        }else if *ch == '\\' {
+            escaped = true;
+        }else if *ch == '(' {
+            depth += 1;
+        }else if *ch == ')' {
+            depth -= 1;
+        }else if *ch == '|' && depth == 0 {
+            ans.push(alt);
+            alt = String::new();
+            continue;
+        }
+        alt.push(*ch);
+    }
+    ans.push(alt);
+    ans
+}
+
+fn extglob_group(op: char, inside: &[char]) -> PatternElem {
+    let alts = split_alternatives(inside).iter()
+               .map(|a| set_glob(a, true)).collect::<Vec<Vec<PatternElem>>>();
+
+    PatternElem {
+        asterisk: false,
+        question: false,
+        inv: false,
+        chars: vec![],
+        ranges: vec![],
+        group: Some((op, alts)),
+    }
+}
+
+fn set_glob(glob: &str, extglob: bool) -> Vec<PatternElem> {
     let mut ans = vec![];
     let mut pos = 0;
     let mut escaped = false;
     let mut in_brace = false;
     let mut bracket_str = vec![];
+    let chars = glob.chars().collect::<Vec<char>>();
 
-    loop {
-        if glob.chars().count() == pos {
-            break;
-        }
+    while pos < chars.len() {
+        let ch = chars[pos];
 
-        let ch = if let Some(c) = glob.chars().nth(pos) {
-            c
-        }else{
-            panic!("Glob parse error");
-        };
+        if extglob && ! escaped && ! in_brace && "?*+@!".contains(ch) && chars.get(pos+1) == Some(&'(') {
+            if let Some(end) = group_end(&chars, pos+1) {
+                ans.push(extglob_group(ch, &chars[pos+2..end]));
+                pos = end + 1;
+                continue;
+            }
+        }
 
         pos += 1;
 
@@ -129,7 +263,8 @@ fn set_glob(glob: &String) -> Vec<PatternElem> {
             ans.push(anychar());
         }else if ch == '[' && ! in_brace {
             in_brace = true;
-        }else if ch == ']' && in_brace {
+        }else if ch == ']' && in_brace && ! bracket_str.is_empty()
+                 && bracket_str != ['!'] && bracket_str != ['^'] { // "]" just after "[" is a member
             ans.push(bracket(&bracket_str));
             in_brace = false;
             bracket_str = vec![];
@@ -142,6 +277,9 @@ fn set_glob(glob: &String) -> Vec<PatternElem> {
         escaped = false;
     }
 
+    if in_brace { // no closing bracket
+        ans.push(simple_char('['));
+    }
     for ch in bracket_str {
         ans.push(simple_char(ch));
     }
@@ -149,23 +287,210 @@ fn set_glob(glob: &String) -> Vec<PatternElem> {
     ans
 }
 
-pub fn glob_match(glob: &String, s: &String) -> bool {
-    let pattern = set_glob(glob);
-    let mut poss = HashSet::new();
-    poss.insert(0);
+/* the matcher shared by case, ${name#pattern} and so on, and the filename expansion */
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    glob_match_ext(pattern, text, false)
+}
 
-    for pat in pattern {
-        let mut poss_new = HashSet::new();
-        for p in poss {
-            for n in judge(s, p, &pat) {
-                poss_new.insert(n);
-            }
+/* ?(..), *(..), +(..), @(..) and !(..) are also handled with extglob */
+pub fn glob_match_ext(pattern: &str, text: &str, extglob: bool) -> bool {
+    let s = text.chars().collect::<Vec<char>>();
+    let pattern = set_glob(pattern, extglob);
+    match_ends(&s, HashSet::from([0]), &pattern).contains(&s.len())
+}
+
+/* escapes the special characters of glob in a quoted string not to expand it.
+   ( is escaped only after the characters that start extglob patterns. */
+pub fn escape_glob(s: &str) -> String {
+    let mut ans = String::new();
+    let mut prev = ' ';
+    for ch in s.chars() {
+        if "*?[".contains(ch) || (ch == '(' && "+@!*?".contains(prev)) {
+            ans.push('\\');
         }
-        poss = poss_new;
-        if poss.len() == 0 {
-            break;
+        ans.push(ch);
+        prev = ch;
+    }
+    ans
+}
+
+pub fn is_glob(s: &str) -> bool {
+    let mut escaped = false;
+
+    for (pos, ch) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        }else if ch == '\\' {
+            escaped = true;
+        }else if ch == '*' || ch == '[' || ch == '?' {
+            return true;
+        }else if "+@!".contains(ch) && s[pos+ch.len_utf8()..].starts_with('(') { // extglob
+            return true;
+        }
+    }
+    false
+}
+
+fn remove_backslash(s: &str) -> String {
+    let mut escaped = false;
+    let mut ans = String::new();
+    for ch in s.chars() {
+        if ! escaped && ch == '\\' {
+            escaped = true;
+            continue;
+        }
+        ans.push(ch);
+        escaped = false;
+    }
+    ans
+}
+
+/* names in the directory matching one component of a path pattern */
+fn match_dir_entries(dir: &str, component: &str, dotglob: bool, extglob: bool) -> Vec<String> {
+    let read_from = if dir.is_empty() { "." }else{ dir };
+    let entries = match fs::read_dir(read_from) {
+        Ok(es) => es,
+        Err(_) => return vec![],
+    };
+
+    let dot_pattern = component.starts_with('.') || component.starts_with("\\.");
+    let mut ans = vec![];
+    for e in entries.flatten() {
+        let name = match e.file_name().to_str() {
+            Some(n) => n.to_string(),
+            None => continue,
+        };
+
+        if name.starts_with('.') && ! dot_pattern && ! dotglob {
+            continue;
+        }
+
+        if glob_match_ext(component, &name, extglob) {
+            ans.push(name);
+        }
+    }
+    ans
+}
+
+/* directories (and files) under the directory for ** of globstar.
+   Symbolic links to directories are not followed so as not to loop,
+   and listed only when the pattern ends with the double asterisk and a slash. */
+fn walk_dir(dir: &str, dotglob: bool, with_files: bool, with_links: bool, ans: &mut Vec<String>) {
+    let read_from = if dir.is_empty() { "." }else{ dir };
+    let entries = match fs::read_dir(read_from) {
+        Ok(es) => es,
+        Err(_) => return,
+    };
+
+    for e in entries.flatten() {
+        let name = match e.file_name().to_str() {
+            Some(n) => n.to_string(),
+            None => continue,
+        };
+        if name.starts_with('.') && ! dotglob {
+            continue;
+        }
+
+        let path = dir.to_string() + &name;
+        let is_dir = match e.file_type() {
+            Ok(t) => t.is_dir(),
+            Err(_) => false,
+        };
+
+        if is_dir {
+            ans.push(if with_files { path.clone() }else{ path.clone() + "/" });
+            walk_dir(&(path + "/"), dotglob, with_files, with_links, ans);
+        }else if with_files {
+            ans.push(path);
+        }else if with_links && Path::new(&path).is_dir() {
+            ans.push(path + "/");
+        }
+    }
+}
+
+/* the order of filename expansion given by GLOBSORT: name, numeric, size,
+   mtime, or nosort. A leading - reverses the order. Ties are sorted by name. */
+pub fn sort_paths(paths: &mut [String], globsort: &str) {
+    let (key, reverse) = match globsort.strip_prefix('-') {
+        Some(k) => (k, true),
+        None    => (globsort.strip_prefix('+').unwrap_or(globsort), false),
+    };
+
+    let file_name = |p: &String| p.trim_end_matches('/').rsplit('/').next().unwrap_or("").to_string();
+    match key {
+        "nosort" => return,
+        "numeric" => paths.sort_by_key(|p| {
+            let name = file_name(p);
+            match name.parse::<u128>() {
+                Ok(n) if name.chars().all(|c| c.is_ascii_digit()) => (0, n, p.clone()),
+                _ => (1, 0, p.clone()),
+            }
+        }),
+        // stat(2) is called once for each path
+        "size" => paths.sort_by_cached_key(|p| (fs::metadata(p).map(|m| m.len()).unwrap_or(0), p.clone())),
+        "mtime" => paths.sort_by_cached_key(|p| (fs::metadata(p).and_then(|m| m.modified()).ok(), p.clone())),
+        _ => paths.sort(),
+    }
+
+    if reverse {
+        paths.reverse();
+    }
+}
+
+/* filename expansion without dotglob and globstar. Nothing is returned when nothing matches. */
+pub fn glob_expand(pattern: &str) -> Vec<String> {
+    expand_paths(pattern, false, false, false)
+}
+
+/* paths matching a pattern like "dir?/[ab]*.txt" in the sorted order */
+pub fn expand_paths(pattern: &str, dotglob: bool, globstar: bool, extglob: bool) -> Vec<String> {
+    let (mut paths, rest) = match pattern.strip_prefix('/') {
+        Some(r) => (vec!["/".to_string()], r),
+        None => (vec!["".to_string()], pattern),
+    };
+
+    let components = rest.split('/').collect::<Vec<&str>>();
+    for (i, comp) in components.iter().enumerate() {
+        let last = i == components.len() - 1;
+        let mut next = vec![];
+
+        for p in &paths {
+            if comp.is_empty() { // a/ or a//b. p ends with / at this point.
+                next.push(p.to_string());
+                continue;
+            }
+
+            if globstar && *comp == "**" { // zero or more directories
+                if ! last || ! p.is_empty() {
+                    next.push(p.to_string());
+                }
+                let dir_only = i + 2 == components.len() && components[i+1].is_empty();
+                walk_dir(p, dotglob, last, dir_only, &mut next);
+                continue;
+            }
+
+            let names = if is_glob(comp) {
+                match_dir_entries(p, comp, dotglob, extglob)
+            }else{
+                vec![remove_backslash(comp)]
+            };
+
+            for name in names {
+                let path = p.to_string() + &name;
+                if last {
+                    if fs::symlink_metadata(&path).is_ok() {
+                        next.push(path);
+                    }
+                }else if Path::new(&path).is_dir() {
+                    next.push(path + "/");
+                }
+            }
         }
+        paths = next;
+        paths.sort();
+        paths.dedup();
     }
 
-    ! poss.insert(s.chars().count())
+    paths.retain(|p| ! p.is_empty());
+    paths
 }
@@ -1,133 +1,424 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::{ShellCore,Feeder};
+use crate::ShellCore;
 
-fn op_order(operator: &String) -> u8 {
-    let op: &str = &operator.clone();
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(i64),
+    Name(String),
+    Op(String),
+}
+
+/* longer operators must come first */
+const OPERATORS: [&str; 39] = [
+    "<<=", ">>=",
+    "**", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "^=", "|=",
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    "+", "-", "*", "/", "%", "=", "<", ">", "!", "~", "&", "^", "|",
+    "?", ":", "(", ")", ",",
+];
+
+const ASSIGNMENT_OPS: [&str; 11] = ["=", "+=", "-=", "*=", "/=", "%=", "<<=", ">>=", "&=", "^=", "|="];
 
-    match op {
-        "**" => 5,
-        "*" | "/" | "%"            => 6, 
-        "+" | "-"                  => 7, 
-        "<<" | ">>"                => 8, 
-        "<=" | ">=" | ">" | "<"    => 9, 
-        "(" | ")"                  => 20, 
-        _ => 255, 
+/* binary operators from the lowest precedence */
+const BINARY_OPS: [&[&str]; 10] = [
+    &["||"], &["&&"], &["|"], &["^"], &["&"], &["==", "!="],
+    &["<=", ">=", "<", ">"], &["<<", ">>"], &["+", "-"], &["*", "/", "%"],
+];
+
+/* digits of the base. A value out of the range wraps around like bash. */
+fn parse_digits(digits: &str, base: u32) -> Option<i64> {
+    if digits.is_empty() {
+        return None;
+    }
+    let mut ans: i64 = 0;
+    for ch in digits.chars() {
+        let d = ch.to_digit(base)?;
+        ans = ans.wrapping_mul(base as i64).wrapping_add(d as i64);
     }
+    Some(ans)
 }
 
-fn get_integer(text: &mut Feeder) -> Option<(String,u8)> {
-    let pos = text.scanner_integer();
+/* 10, 0x1f, 017, 2#101 */
+fn parse_number(word: &str) -> Result<i64, String> {
+    let err = || format!("value too great for base (error token is \"{}\")", word);
 
-    if pos != 0 {
-        Some( (text.consume(pos),0) )
+    if let Some(n) = word.find('#') {
+        let base = word[..n].parse::<u32>().map_err(|_| err())?;
+        if ! (2..=36).contains(&base) {
+            return Err(format!("invalid arithmetic base (error token is \"{}\")", word));
+        }
+        return parse_digits(&word[n+1..], base).ok_or_else(err);
+    }
+
+    if word.starts_with("0x") || word.starts_with("0X") {
+        parse_digits(&word[2..], 16).ok_or_else(err)
+    }else if word.len() > 1 && word.starts_with('0') {
+        parse_digits(&word[1..], 8).ok_or_else(err)
     }else{
-        None
+        parse_digits(word, 10).ok_or_else(err)
     }
 }
 
-fn get_operator(text: &mut Feeder) -> Option<(String,u8)> {
-    if text.len() == 0 {
-        return None;
+/* the limit of nested evaluation of parentheses, unary operators and variables.
+   It is lower than 1024 of bash not to overflow the stack of a debug build. */
+const MAX_DEPTH: usize = 256;
+
+/* each token has its position in the expression for error messages */
+fn tokenize(expression: &str) -> Result<Vec<(Token, usize)>, String> {
+    let mut tokens = vec![];
+    let chars = expression.char_indices().collect::<Vec<(usize, char)>>();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (pos, ch) = chars[i];
+        if ch.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if ch.is_ascii_alphanumeric() || ch == '_' {
+            let mut word = String::new();
+            while i < chars.len() && (chars[i].1.is_ascii_alphanumeric() || chars[i].1 == '_'
+                                      || (chars[i].1 == '#' && ch.is_ascii_digit())) {
+                word.push(chars[i].1);
+                i += 1;
+            }
+
+            let token = if ch.is_ascii_digit() {
+                Token::Num(parse_number(&word)?)
+            }else{
+                Token::Name(word)
+            };
+            tokens.push((token, pos));
+            continue;
+        }
+
+        let rest = &expression[pos..];
+        match OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            Some(op) => {
+                tokens.push((Token::Op(op.to_string()), pos));
+                i += op.len();
+            },
+            None => return Err(format!("syntax error: invalid arithmetic operator (error token is \"{}\")", rest)),
+        }
     }
 
-    if let Some(_) = "+-/%*".find(text.nth(0)) {
-        let op = text.consume(1);
-        Some( (op.clone(), op_order(&op)) )
-    }else{
-        None
+    Ok(tokens)
+}
+
+/* base ** exp which wraps around like bash. exp can be larger than u32. */
+fn wrapping_pow(mut base: i64, mut exp: i64) -> i64 {
+    let mut ans: i64 = 1;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            ans = ans.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exp >>= 1;
     }
+    ans
 }
 
-fn reduce(stack: &mut Vec<i32>, op: String ) {
-    let op: &str = &op.clone();
+struct Calculator {
+    expression: String,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    depth: usize, // the level of the recursive evaluation
+    noeval: usize, // positive in the skipped side of &&, || and ?:
+}
 
-    let right = stack.pop().unwrap();
-    let left = stack.pop().unwrap();
+impl Calculator {
+    /* enters a nested evaluation. The caller decrements depth after it. */
+    fn nest(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format!("expression recursion level exceeded (error token is \"{}\")", self.error_token()));
+        }
+        Ok(())
+    }
 
-    match op {
-        "+" => stack.push(left+right),
-        "-" => stack.push(left-right),
-        "*" => stack.push(left*right),
-        "/" => stack.push(left/right),
-        _ => (), 
+    fn error_token(&self) -> String {
+        match self.tokens.get(self.pos) {
+            Some((_, p)) => self.expression[*p..].trim().to_string(),
+            None => "".to_string(),
+        }
     }
-}
 
-pub fn calculate(expression: String, core: &mut ShellCore) -> String {
-    let tokens = tokenizer(expression, core);
-    let mut num_stack: Vec<i32> = vec![];
-    let mut wait_stack: Vec<(String,u8)> = vec![];
+    fn syntax_error(&self) -> String {
+        match self.tokens.get(self.pos) {
+            Some(_) => format!("syntax error in expression (error token is \"{}\")", self.error_token()),
+            None => format!("syntax error: operand expected (error token is \"{}\")", self.error_token()),
+        }
+    }
 
-    //eprintln!("TOKENS: {:?}", tokens);
+    fn peek_op(&self) -> Option<&str> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Op(op), _)) => Some(op),
+            _ => None,
+        }
+    }
 
-    for t in tokens {
-     //   eprintln!("STACK: {:?}", num_stack);
-      //  eprintln!("WAIT STACK: {:?}", wait_stack);
-        while wait_stack.len() != 0 {
-            let wtop = wait_stack.pop().unwrap();
+    fn next_is_op(&self, ops: &[&str]) -> Option<String> {
+        match self.peek_op() {
+            Some(op) if ops.contains(&op) => Some(op.to_string()),
+            _ => None,
+        }
+    }
 
-            if t.0 == ")" && wtop.0 == "(" {
-            //    wait_stack.push(wtop);
-                break;
-            }
+    fn get_var(&self, name: &str, core: &mut ShellCore) -> Result<i64, String> {
+        let value = core.get_var(name);
+        if value.trim().is_empty() {
+            return Ok(0);
+        }
+        if self.depth > MAX_DEPTH {
+            return Err(format!("expression recursion level exceeded (error token is \"{}\")", name));
+        }
 
-            if wtop.1 <= t.1 {
-                if wtop.1 > 0 {
-                    reduce(&mut num_stack, wtop.0.clone());
-                }else{
-                    num_stack.push(wtop.0.parse::<i32>().unwrap());
-                }
-            }else{
-                wait_stack.push(wtop);
-                break;
-            }
+        let mut sub = Calculator {
+            expression: value.clone(),
+            tokens: tokenize(&value)?,
+            pos: 0,
+            depth: self.depth + 1,
+            noeval: self.noeval,
+        };
+        sub.eval(core)
+    }
+
+    /* an empty error message means that the error has been already reported */
+    fn set_var(&self, name: &str, value: i64, core: &mut ShellCore) -> Result<i64, String> {
+        if self.noeval == 0 && ! core.substitute_var(name, &value.to_string()) {
+            return Err(String::new());
         }
+        Ok(value)
+    }
 
-        if t.0 != ")" {
-            wait_stack.push(t);
+    fn eval(&mut self, core: &mut ShellCore) -> Result<i64, String> {
+        let ans = self.comma(core)?;
+        if self.pos < self.tokens.len() {
+            return Err(self.syntax_error());
         }
+        Ok(ans)
     }
 
-//    eprintln!("-------------------");
-    while wait_stack.len() != 0 {
-        let wtop = wait_stack.pop().unwrap();
-        if wtop.1 > 0 {
-            reduce(&mut num_stack, wtop.0.clone());
-        }else{
-            num_stack.push(wtop.0.parse::<i32>().unwrap());
-        //    stack.push(wtop);
+    fn comma(&mut self, core: &mut ShellCore) -> Result<i64, String> {
+        let mut ans = self.assignment(core)?;
+        while self.next_is_op(&[","]).is_some() {
+            self.pos += 1;
+            ans = self.assignment(core)?;
         }
- //       eprintln!("STACK: {:?}", num_stack);
-  //      eprintln!("WAIT STACK: {:?}", wait_stack);
+        Ok(ans)
     }
 
-    //stack.iter().map(|t| t.0.clone()).collect::<Vec<String>>().join(" ")
-    num_stack.pop().unwrap().to_string()
-}
+    fn assignment(&mut self, core: &mut ShellCore) -> Result<i64, String> {
+        let name = match self.tokens.get(self.pos) {
+            Some((Token::Name(n), _)) => n.clone(),
+            _ => return self.ternary(core),
+        };
 
-fn tokenizer(expression: String, _core: &mut ShellCore) -> Vec<(String,u8)> {
-    //let mut stack = vec![];
-    let mut tokens = vec![];
-    
-    let mut text = Feeder::new_from(expression.clone());
-    while text.len() != 0 {
-        //get value
-        if let Some(n) = get_integer(&mut text) {
-            tokens.push(n);
-        }else{
-            break;
+        self.pos += 1;
+        let op = match self.next_is_op(&ASSIGNMENT_OPS) {
+            Some(op) => op,
+            None => {
+                self.pos -= 1;
+                return self.ternary(core);
+            },
+        };
+        self.pos += 1;
+
+        let right_text = self.error_token();
+        let right = self.assignment(core)?;
+        let value = match op.as_str() {
+            "=" => right,
+            _ => {
+                let left = self.get_var(&name, core)?;
+                self.binary(&op[..op.len()-1], left, right, &right_text)?
+            },
+        };
+        self.set_var(&name, value, core)
+    }
+
+    /* only one of the two sides is evaluated */
+    fn ternary(&mut self, core: &mut ShellCore) -> Result<i64, String> {
+        let cond = self.binary_level(0, core)?;
+        if self.next_is_op(&["?"]).is_none() {
+            return Ok(cond);
         }
+        self.pos += 1;
 
-        //get operator
-        if let Some(op) = get_operator(&mut text) {
-            tokens.push(op);
-        }else{
-            break;
+        let first = self.skip_or_eval(cond == 0, |calc, core| calc.assignment(core), core)?;
+        if self.next_is_op(&[":"]).is_none() {
+            return Err(self.syntax_error());
         }
+        self.pos += 1;
+        let second = self.skip_or_eval(cond != 0, |calc, core| calc.ternary(core), core)?;
+
+        Ok(if cond != 0 { first }else{ second })
     }
 
-    tokens
+    fn skip_or_eval(&mut self, skip: bool, f: fn(&mut Calculator, &mut ShellCore) -> Result<i64, String>,
+                    core: &mut ShellCore) -> Result<i64, String> {
+        if skip {
+            self.noeval += 1;
+        }
+        let ans = f(self, core);
+        if skip {
+            self.noeval -= 1;
+        }
+        ans
+    }
+
+    fn binary_level(&mut self, level: usize, core: &mut ShellCore) -> Result<i64, String> {
+        if level == BINARY_OPS.len() {
+            return self.power(core);
+        }
+
+        let mut left = self.binary_level(level+1, core)?;
+        while let Some(op) = self.next_is_op(BINARY_OPS[level]) {
+            self.pos += 1;
+            let right_text = self.error_token();
+            let right = match op.as_str() {
+                "&&" => self.skip_or_eval(left == 0, |c, core| c.binary_level(2, core), core)?,
+                "||" => self.skip_or_eval(left != 0, |c, core| c.binary_level(1, core), core)?,
+                _    => self.binary_level(level+1, core)?,
+            };
+            left = self.binary(&op, left, right, &right_text)?;
+        }
+        Ok(left)
+    }
+
+    fn binary(&self, op: &str, left: i64, right: i64, right_text: &str) -> Result<i64, String> {
+        let ans = match op {
+            "||" => (left != 0 || right != 0) as i64,
+            "&&" => (left != 0 && right != 0) as i64,
+            "|"  => left | right,
+            "^"  => left ^ right,
+            "&"  => left & right,
+            "==" => (left == right) as i64,
+            "!=" => (left != right) as i64,
+            "<=" => (left <= right) as i64,
+            ">=" => (left >= right) as i64,
+            "<"  => (left < right) as i64,
+            ">"  => (left > right) as i64,
+            "<<" => left.wrapping_shl(right as u32),
+            ">>" => left.wrapping_shr(right as u32),
+            "+"  => left.wrapping_add(right),
+            "-"  => left.wrapping_sub(right),
+            "*"  => left.wrapping_mul(right),
+            "/" | "%" if right == 0 => {
+                if self.noeval > 0 {
+                    return Ok(0);
+                }
+                return Err(format!("division by 0 (error token is \"{}\")", right_text));
+            },
+            "/"  => left.wrapping_div(right),
+            "%"  => left.wrapping_rem(right),
+            _ => return Err(self.syntax_error()),
+        };
+        Ok(ans)
+    }
+
+    /* right associative and stronger than unary operators on the left */
+    fn power(&mut self, core: &mut ShellCore) -> Result<i64, String> {
+        let base = self.unary(core)?;
+        if self.next_is_op(&["**"]).is_none() {
+            return Ok(base);
+        }
+        self.pos += 1;
+
+        let right_text = self.error_token();
+        self.nest()?;
+        let exp = self.power(core)?;
+        self.depth -= 1;
+        if exp < 0 {
+            if self.noeval > 0 {
+                return Ok(0);
+            }
+            return Err(format!("exponent less than 0 (error token is \"{}\")", right_text));
+        }
+        Ok(wrapping_pow(base, exp))
+    }
+
+    fn unary(&mut self, core: &mut ShellCore) -> Result<i64, String> {
+        match self.next_is_op(&["+", "-", "!", "~", "++", "--"]) {
+            Some(op) if op == "++" || op == "--" => {
+                self.pos += 1;
+                let name = match self.tokens.get(self.pos) {
+                    Some((Token::Name(n), _)) => n.clone(),
+                    _ => return Err(self.syntax_error()),
+                };
+                self.pos += 1;
+                let value = self.get_var(&name, core)?;
+                let value = if op == "++" { value.wrapping_add(1) }else{ value.wrapping_sub(1) };
+                self.set_var(&name, value, core)
+            },
+            Some(op) => {
+                self.pos += 1;
+                self.nest()?;
+                let value = self.unary(core)?;
+                self.depth -= 1;
+                Ok(match op.as_str() {
+                    "-" => value.wrapping_neg(),
+                    "!" => (value == 0) as i64,
+                    "~" => !value,
+                    _   => value,
+                })
+            },
+            None => self.postfix(core),
+        }
+    }
+
+    fn postfix(&mut self, core: &mut ShellCore) -> Result<i64, String> {
+        let name = match self.tokens.get(self.pos) {
+            Some((Token::Name(n), _)) => n.clone(),
+            _ => return self.primary(core),
+        };
+
+        self.pos += 1;
+        let value = self.get_var(&name, core)?;
+        if let Some(op) = self.next_is_op(&["++", "--"]) {
+            self.pos += 1;
+            let new_value = if op == "++" { value.wrapping_add(1) }else{ value.wrapping_sub(1) };
+            self.set_var(&name, new_value, core)?;
+        }
+        Ok(value)
+    }
+
+    fn primary(&mut self, core: &mut ShellCore) -> Result<i64, String> {
+        match self.tokens.get(self.pos).cloned() {
+            Some((Token::Num(n), _)) => {
+                self.pos += 1;
+                Ok(n)
+            },
+            Some((Token::Op(op), _)) if op == "(" => {
+                self.pos += 1;
+                self.nest()?;
+                let ans = self.comma(core)?;
+                self.depth -= 1;
+                if self.next_is_op(&[")"]).is_none() {
+                    return Err(self.syntax_error());
+                }
+                self.pos += 1;
+                Ok(ans)
+            },
+            _ => Err(self.syntax_error()),
+        }
+    }
 }
 
+/* evaluates an arithmetic expression. Variables may be referred without $. */
+pub fn calculate(expression: &str, core: &mut ShellCore) -> Result<i64, String> {
+    let mut calc = Calculator {
+        expression: expression.to_string(),
+        tokens: tokenize(expression)?,
+        pos: 0,
+        depth: 0,
+        noeval: 0,
+    };
+
+    if calc.tokens.is_empty() {
+        return Ok(0);
+    }
+    calc.eval(core)
+}
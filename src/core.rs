@@ -11,29 +11,71 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::env;
 use crate::core::shopts::Shopts;
-use nix::sys::wait::{waitpid, WaitStatus, WaitPidFlag};
-use nix::unistd::Pid;
+use nix::sys::wait::{waitpid, WaitStatus};
+use nix::unistd::{Pid, getppid};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
 use crate::core::jobs::Jobs;
+use crate::{Feeder, Script};
+use crate::calculator::calculate;
+use crate::utils::get_fullpath;
+use std::fs;
 
 use nix::unistd::read;
 use std::os::unix::prelude::RawFd;
 
+/* a variable made by local and its value and attributes outside the function */
+#[derive(Debug)]
+pub struct LocalVar {
+    pub name: String,
+    pub outer_value: Option<String>,
+    pub outer_attributes: Option<String>,
+    pub outer_env: Option<String>,
+    pub outer_array: Option<HashMap<String, String>>,
+}
+
+/* a line of the history and the unix time when it was recorded */
+#[derive(Debug)]
+pub struct HistoryEntry {
+    pub line: String,
+    pub time: i64,
+}
+
 pub struct ShellCore {
     pub builtins: HashMap<String, fn(&mut ShellCore, args: &mut Vec<String>) -> i32>,
     pub functions: HashMap<String, String>,
     pub arrays: HashMap<String, Vec<String>>,
+    pub assoc_arrays: HashMap<String, HashMap<String, String>>,
     pub vars: HashMap<String, String>,
+    pub var_attributes: HashMap<String, String>, // i: integer, r: readonly, x: export
+    pub local_scopes: Vec<Vec<LocalVar>>, // one element for each function call
     pub args: Vec<String>,
     pub aliases: HashMap<String, String>,
-    pub history: Vec<String>,
+    pub history: Vec<HistoryEntry>,
+    pub new_history_start: usize, // the entries from here are not in the history file yet
     pub flags: String,
     pub jobs: Jobs, //old
     pub in_double_quot: bool,
+    pub in_condition: bool,
     pub pipeline_end: String,
     pub script_file: Option<File>,
     pub return_enable: bool,
     pub return_flag: bool,
+    pub interrupted: bool, // Ctrl-C, failglob or a syntax error stopped a command. The rest of the line is skipped.
+    pub loop_level: usize, // the depth of the running loops
+    pub function_level: usize, // the depth of the running functions
+    pub in_trap: bool, // true while the command of a trap runs
+    pub break_counter: usize, // the number of loops that break exits
+    pub continue_counter: usize, // the loop that continue resumes is the n-th one
     pub shopts: Shopts, 
+    pub traps: HashMap<i32, String>,
+    pub in_subshell: bool, // true in a forked process
+    pub getopts_pos: (usize, usize), //(OPTIND, position in the word) after the last getopts
+    pub command_hash: HashMap<String, (String, u32)>, // name -> (path, hits)
+    pub hashed_path: String, // PATH when the entries of command_hash are registered
+    pub vi_mode: bool, // set -o vi
+    pub completions: HashMap<String, String>, // command -> word list of complete -W
+    pub dir_stack: Vec<String>, // pushd and popd. The current directory is not included.
 }
 
 impl ShellCore {
@@ -42,21 +84,43 @@ impl ShellCore {
             builtins: HashMap::new(),
             functions: HashMap::new(),
             arrays: HashMap::new(),
+            assoc_arrays: HashMap::new(),
             vars: HashMap::new(),
+            var_attributes: HashMap::new(),
+            local_scopes: vec![],
             args: vec![],
             aliases: HashMap::new(),
             history: Vec::new(),
+            new_history_start: 0,
             flags: String::new(),
             jobs: Jobs::new(),// {backgrounds: vec!(Job::new(&"".to_string(), &vec![], false))},
             in_double_quot: false,
+            in_condition: false,
             pipeline_end: String::new(),
             script_file: None,
             return_flag: false,
+            interrupted: false,
             return_enable: false,
+            loop_level: 0,
+            function_level: 0,
+            in_trap: false,
+            break_counter: 0,
+            continue_counter: 0,
             shopts: Shopts::new(),
+            traps: HashMap::new(),
+            in_subshell: false,
+            getopts_pos: (1, 1),
+            command_hash: HashMap::new(),
+            hashed_path: String::new(),
+            vi_mode: false,
+            completions: HashMap::new(),
+            dir_stack: vec![],
         };
 
         conf.set_var("?", &0.to_string());
+        conf.set_var("$", &process::id().to_string());
+        conf.set_var("PPID", &getppid().to_string());
+        conf.set_var("OPTIND", "1");
         builtins::set_builtins(&mut conf);
 
         conf
@@ -66,6 +130,166 @@ impl ShellCore {
         self.vars.insert(key.to_string(), value.to_string());
     }
 
+    pub fn has_attribute(&self, key: &str, attr: char) -> bool {
+        match self.var_attributes.get(key) {
+            Some(attrs) => attrs.contains(attr),
+            None => false,
+        }
+    }
+
+    /* assignment by the user. This function returns false for a readonly variable. */
+    pub fn substitute_var(&mut self, key: &str, value: &str) -> bool {
+        if self.has_attribute(key, 'r') {
+            eprintln!("bash: {}: readonly variable", key);
+            return false;
+        }
+
+        let value = if self.has_attribute(key, 'i') {
+            match calculate(value, self) {
+                Ok(n) => n.to_string(),
+                Err(msg) => {
+                    if ! msg.is_empty() {
+                        eprintln!("bash: {}: {}", value.trim(), msg);
+                    }
+                    return false;
+                },
+            }
+        }else{
+            value.to_string()
+        };
+
+        if key == "OPTIND" { // getopts starts at the head of the word again
+            self.getopts_pos = (1, 1);
+        }
+        if self.has_attribute(key, 'x') || (! self.vars.contains_key(key) && env::var(key).is_ok()) {
+            env::set_var(key, value);
+        }else{
+            self.set_var(key, &value);
+        }
+        true
+    }
+
+    pub fn push_local_scope(&mut self) {
+        self.local_scopes.push(vec![]);
+    }
+
+    pub fn pop_local_scope(&mut self) {
+        let scope = match self.local_scopes.pop() {
+            Some(s) => s,
+            None => return,
+        };
+
+        for v in scope.into_iter().rev() {
+            match v.outer_value {
+                Some(value) => self.set_var(&v.name, &value),
+                None        => {self.vars.remove(&v.name);},
+            }
+            match v.outer_env {
+                Some(value) => env::set_var(&v.name, value),
+                None        => env::remove_var(&v.name),
+            }
+            match v.outer_array {
+                Some(map) => self.assoc_arrays.insert(v.name.clone(), map),
+                None      => self.assoc_arrays.remove(&v.name),
+            };
+            match v.outer_attributes {
+                Some(attrs) => self.var_attributes.insert(v.name, attrs),
+                None        => self.var_attributes.remove(&v.name),
+            };
+        }
+    }
+
+    /* returns false if the variable is already local in the current function */
+    pub fn make_local(&mut self, name: &str) -> bool {
+        let scope = match self.local_scopes.last_mut() {
+            Some(s) => s,
+            None => return false,
+        };
+
+        if scope.iter().any(|v| v.name == name) {
+            return false;
+        }
+
+        scope.push(LocalVar {
+            name: name.to_string(),
+            outer_value: self.vars.get(name).cloned(),
+            outer_attributes: self.var_attributes.get(name).cloned(),
+            outer_env: env::var(name).ok(),
+            outer_array: self.assoc_arrays.get(name).cloned(),
+        });
+        true
+    }
+
+    pub fn is_assoc(&self, name: &str) -> bool {
+        self.has_attribute(name, 'A')
+    }
+
+    /* keys of an associative array in sorted order; numerically for a non-declared array */
+    pub fn get_array_keys(&self, name: &str) -> Vec<String> {
+        let mut keys = match self.assoc_arrays.get(name) {
+            Some(map) => map.keys().cloned().collect::<Vec<String>>(),
+            None => return vec![],
+        };
+
+        if self.is_assoc(name) {
+            keys.sort();
+        }else{
+            keys.sort_by_key(|k| k.parse::<i64>().unwrap_or(0));
+        }
+        keys
+    }
+
+    pub fn is_array(&self, name: &str) -> bool {
+        self.assoc_arrays.contains_key(name)
+    }
+
+    pub fn get_array_elem(&self, name: &str, key: &str) -> Option<String> {
+        match self.assoc_arrays.get(name) {
+            Some(map) => map.get(key).cloned(),
+            None if key == "0" && self.has_var(name) => Some(self.get_var(name)),
+            None => None,
+        }
+    }
+
+    pub fn set_array_elem(&mut self, name: &str, key: &str, value: &str) -> bool {
+        if self.has_attribute(name, 'r') {
+            eprintln!("bash: {}: readonly variable", name);
+            return false;
+        }
+
+        self.assoc_arrays.entry(name.to_string()).or_default()
+            .insert(key.to_string(), value.to_string());
+        true
+    }
+
+    /* name=(elem ...) and name+=(elem ...). Appended elements follow the largest index
+       and an element without a key follows the previous one. */
+    pub fn set_array(&mut self, name: &str, elems: &[(Option<String>, String)], append: bool) -> bool {
+        if self.has_attribute(name, 'r') {
+            eprintln!("bash: {}: readonly variable", name);
+            return false;
+        }
+
+        let mut start = 0;
+        if ! append {
+            self.vars.remove(name);
+            self.assoc_arrays.remove(name);
+        }else if let Some(map) = self.assoc_arrays.get(name) {
+            start = map.keys().filter_map(|k| k.parse::<usize>().ok()).max().map_or(0, |n| n+1);
+        }else if let Some(value) = self.vars.remove(name) { // the scalar becomes the first element
+            self.assoc_arrays.entry(name.to_string()).or_default().insert("0".to_string(), value);
+            start = 1;
+        }
+
+        let map = self.assoc_arrays.entry(name.to_string()).or_default();
+        for (key, value) in elems {
+            let key = key.clone().unwrap_or(start.to_string());
+            start = key.parse::<usize>().map_or(start, |n| n+1);
+            map.insert(key, value.to_string());
+        }
+        true
+    }
+
     pub fn get_var(&self, key: &str) -> String {
         if let Ok(n) = key.parse::<usize>() {
             if self.args.len() > n {
@@ -114,6 +338,38 @@ impl ShellCore {
         "".to_string()
     }
 
+    pub fn push_history(&mut self, entry: &str, time: Option<i64>) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+        self.history.push(HistoryEntry {
+            line: entry.to_string(),
+            time: time.unwrap_or(now),
+        });
+    }
+
+    /* HISTFILE or ~/.bash_history */
+    pub fn history_file(&self) -> String {
+        if self.has_var("HISTFILE") {
+            return self.get_var("HISTFILE");
+        }
+        self.get_var("HOME") + "/.bash_history"
+    }
+
+    pub fn has_var(&self, key: &str) -> bool {
+        if let Ok(n) = key.parse::<usize>() {
+            return self.args.len() > n;
+        }
+
+        if key == "@" || key == "*" {
+            return self.args.len() > 1;
+        }
+
+        if key == "-" || key == "#" || key == "?" {
+            return true;
+        }
+
+        self.vars.contains_key(key) || env::var(key).is_ok()
+    }
+
     pub fn get_function(&mut self, name: &String) -> Option<String> {
         if self.functions.contains_key(name) {
             if let Some(s) = self.functions.get(name) {
@@ -133,6 +389,36 @@ impl ShellCore {
         }
     }
 
+    /* the hash table is cleared when PATH is changed */
+    pub fn check_command_hash(&mut self) {
+        let path = self.get_var("PATH");
+        if path != self.hashed_path {
+            self.command_hash.clear();
+            self.hashed_path = path;
+        }
+    }
+
+    /* searches PATH only when the command is not in the hash table */
+    pub fn get_command_path(&mut self, name: &str) -> String {
+        if name.contains('/') {
+            return name.to_string();
+        }
+
+        self.check_command_hash();
+        if let Some(entry) = self.command_hash.get_mut(name) {
+            if fs::metadata(&entry.0).is_ok() {
+                entry.1 += 1;
+                return entry.0.clone();
+            }
+        }
+
+        let path = get_fullpath(&name.to_string());
+        if ! path.is_empty() {
+            self.command_hash.insert(name.to_string(), (path.clone(), 1));
+        }
+        path
+    }
+
     pub fn has_flag(&self, flag: char) -> bool {
         if let Some(_) = self.flags.find(flag) {
             return true;
@@ -140,41 +426,44 @@ impl ShellCore {
         false
     }
 
+    /* reads the output of a command substitution until EOF and waits the process */
     pub fn read_pipe(&mut self, pin: RawFd, pid: Pid) -> String {
-        let mut ans = "".to_string();
+        let mut bytes = vec![];
         let mut ch = [0;1000];
-    
         loop {
-            while let Ok(n) = read(pin, &mut ch) {
-                ans += &String::from_utf8(ch[..n].to_vec()).unwrap();
-                match waitpid(pid, Some(WaitPidFlag::WNOHANG)).expect("Faild to wait child process.") {
-                    WaitStatus::StillAlive => {
-                        continue;
-                    },
-                    WaitStatus::Exited(_pid, status) => {
-                        self.set_var("?", &status.to_string());
-                        break;
-                    },
-                    WaitStatus::Signaled(pid, signal, _) => {
-                        self.set_var("?", &(128+signal as i32).to_string());
-                        eprintln!("Pid: {:?}, Signal: {:?}", pid, signal);
-                        break;
-                    },
-                    _ => {
-                        break;
-                    },
-                };
+            match read(pin, &mut ch) {
+                Ok(0) => break,
+                Ok(n) => bytes.extend_from_slice(&ch[..n]),
+                Err(nix::errno::Errno::EINTR) => continue,
+                Err(_) => break,
             }
-            return ans;
         }
+
+        match waitpid(pid, None) {
+            Ok(WaitStatus::Exited(_pid, status)) => {
+                self.set_var("?", &status.to_string());
+            },
+            Ok(WaitStatus::Signaled(_pid, signal, _)) => {
+                self.set_var("?", &(128+signal as i32).to_string());
+            },
+            _ => {},
+        }
+
+        String::from_utf8_lossy(&bytes).to_string()
     }
 
     pub fn wait_job(&mut self) { //only for fg job
-        let pipestatus = self.jobs.wait_fg_job();
+        let (pipestatus, sigint) = self.jobs.wait_fg_job();
         if pipestatus.len() == 0 {
             return;
         }
 
+        /* the prompt comes on the next line of ^C when Ctrl-C interrupts the job */
+        if self.has_flag('i') && sigint {
+            eprintln!();
+            self.interrupted = true;
+        }
+
         self.set_var("?", &pipestatus[pipestatus.len()-1].to_string());
         let s = pipestatus
             .iter()
@@ -186,13 +475,105 @@ impl ShellCore {
         self.jobs.foreground.status = 'D';
     }
 
+    /* run the commands of traps for caught signals. $? is not changed by them. */
+    pub fn run_traps(&mut self) {
+        for sig in proc::take_caught_signals() {
+            if let Some(com) = self.traps.get(&sig) {
+                self.run_trap_command(&com.clone());
+            }
+        }
+    }
+
+    /* trapped signals are reset in a subshell, while ignored ones stay ignored */
+    pub fn enter_subshell(&mut self) {
+        self.in_subshell = true;
+        self.traps.retain(|_, com| com.is_empty());
+    }
+
+    /* the EXIT trap runs once when the shell or a subshell ends */
+    pub fn run_exit_trap(&mut self, status: i32) {
+        if let Some(com) = self.traps.remove(&0) {
+            self.set_var("?", &status.to_string());
+            self.run_trap_command(&com);
+        }
+    }
+
+    /* DEBUG and ERR traps are not inherited by functions, as bash without set -T and -E */
+    fn run_pseudo_signal_trap(&mut self, sig: i32) {
+        if self.in_trap || self.function_level > 0 {
+            return;
+        }
+        if let Some(com) = self.traps.get(&sig) {
+            self.run_trap_command(&com.clone());
+        }
+    }
+
+    /* called before each simple command */
+    pub fn run_debug_trap(&mut self, command: &str) {
+        if ! self.in_trap {
+            self.set_var("BASH_COMMAND", command.trim());
+        }
+        self.run_pseudo_signal_trap(proc::DEBUG_TRAP);
+    }
+
+    /* called when a command fails in the same condition as set -e */
+    pub fn run_err_trap(&mut self) {
+        self.run_pseudo_signal_trap(proc::ERR_TRAP);
+    }
+
+    /* called when a function or a sourced script ends. $? is its exit status. */
+    pub fn run_return_trap(&mut self) {
+        if self.in_trap {
+            return;
+        }
+        if let Some(com) = self.traps.get(&proc::RETURN_TRAP) {
+            let return_flag = self.return_flag; // not to skip the trap command after return
+            self.return_flag = false;
+            self.run_trap_command(&com.clone());
+            self.return_flag = return_flag;
+        }
+    }
+
+    pub fn run_trap_command(&mut self, com: &str) {
+        let status = self.get_var("?");
+        let backup = self.in_trap;
+        self.in_trap = true;
+        let mut feeder = Feeder::new_from(com.to_string());
+        if let Some(mut script) = Script::parse(&mut feeder, self) {
+            script.exec(self);
+        }
+        self.in_trap = backup;
+        self.set_var("?", &status);
+    }
+
+    /* true while return, break, continue, or Ctrl-C skips the rest of the commands */
+    pub fn is_jumping(&self) -> bool {
+        self.return_flag || self.break_counter > 0 || self.continue_counter > 0 || self.interrupted
+    }
+
+    /* called after each iteration of a loop. It returns false if the loop should stop. */
+    pub fn check_loop_jump(&mut self) -> bool {
+        if self.return_flag || self.interrupted {
+            return false;
+        }
+        if self.break_counter > 0 {
+            self.break_counter -= 1;
+            return false;
+        }
+        if self.continue_counter > 0 {
+            self.continue_counter -= 1;
+            return self.continue_counter == 0;
+        }
+        true
+    }
+
     pub fn reverse_exit_status(&mut self) {
         let rev = if self.vars["?"] == "0" {"1"}else{"0"};
         self.set_var("?", rev);
     }
 
     pub fn check_jobs(&mut self) {
-        for j in 1..self.jobs.backgrounds.len() {
+        for j in 0..self.jobs.backgrounds.len() {
             if self.jobs.backgrounds[j].async_pids.len() != 0 {
                 self.jobs.backgrounds[j].check_of_finish();
             }
@@ -203,6 +584,7 @@ impl ShellCore {
         //let mut minus_to_plus = false;
         for j in self.jobs.backgrounds.iter_mut() {
             if j.status == 'D' { //done
+                self.jobs.reaped.extend(j.reaped.iter().cloned());
                 j.print_status(first, second);
                 /*
                 if self.jobs.backgrounds[j].mark == '+' {
@@ -212,5 +594,6 @@ impl ShellCore {
         }
 
         self.jobs.remove_finished_jobs();
+        self.jobs.reap_disowned();
     }
 }
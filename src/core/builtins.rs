@@ -2,15 +2,24 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use std::process;
+use std::collections::HashMap;
 use std::{io,fs,env};
 use std::path::Path;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
 use std::fs::OpenOptions;
-use std::io::{Write, BufReader, BufRead};
+use std::io::Write;
 use crate::bash_glob::glob_match;
 use super::job::Job;
 use nix::sys::signal;
 use nix::sys::signal::Signal;
+use std::convert::TryFrom;
 use nix::unistd;
+use nix::unistd::Pid;
+use nix::sys::stat;
+use nix::sys::stat::Mode;
+use std::ffi::CString;
+use super::proc;
+use crate::utils::{get_fullpath, read_line_fd0, format_time, is_history_timestamp};
 
 use crate::Script;
 use crate::ShellCore;
@@ -19,78 +28,636 @@ use crate::Feeder;
 pub fn set_builtins(core: &mut ShellCore){
     core.builtins.insert(".".to_string(), source);
     core.builtins.insert(":".to_string(), true_);
+    core.builtins.insert("[".to_string(), test);
     core.builtins.insert("alias".to_string(), alias);
     core.builtins.insert("builtin".to_string(), builtin);
     core.builtins.insert("bg".to_string(), bg);
+    core.builtins.insert("break".to_string(), break_);
     core.builtins.insert("cd".to_string(), cd);
+    core.builtins.insert("command".to_string(), command);
+    core.builtins.insert("complete".to_string(), complete);
+    core.builtins.insert("continue".to_string(), continue_);
+    core.builtins.insert("declare".to_string(), declare);
+    core.builtins.insert("dirs".to_string(), dirs);
+    core.builtins.insert("disown".to_string(), disown);
+    core.builtins.insert("echo".to_string(), echo);
     core.builtins.insert("eval".to_string(), eval);
+    core.builtins.insert("exec".to_string(), exec);
     core.builtins.insert("exit".to_string(), exit);
     core.builtins.insert("export".to_string(), export);
     core.builtins.insert("false".to_string(), false_);
     core.builtins.insert("fg".to_string(), fg);
+    core.builtins.insert("hash".to_string(), hash);
     core.builtins.insert("history".to_string(), history);
+    core.builtins.insert("kill".to_string(), kill);
+    core.builtins.insert("local".to_string(), local);
     core.builtins.insert("jobs".to_string(), jobs);
+    core.builtins.insert("popd".to_string(), popd);
+    core.builtins.insert("pushd".to_string(), pushd);
     core.builtins.insert("pwd".to_string(), pwd);
     core.builtins.insert("set".to_string(), set);
     core.builtins.insert("shift".to_string(), shift);
+    core.builtins.insert("test".to_string(), test);
+    core.builtins.insert("trap".to_string(), trap);
     core.builtins.insert("true".to_string(), true_);
+    core.builtins.insert("type".to_string(), type_);
+    core.builtins.insert("typeset".to_string(), declare);
+    core.builtins.insert("umask".to_string(), umask);
+    core.builtins.insert("unalias".to_string(), unalias);
+    core.builtins.insert("unset".to_string(), unset);
     core.builtins.insert("read".to_string(), read);
     core.builtins.insert("return".to_string(), return_);
     core.builtins.insert("shopt".to_string(), shopt);
     core.builtins.insert("source".to_string(), source);
     core.builtins.insert("wait".to_string(), wait);
 
+    core.builtins.insert("getopts".to_string(), getopts);
+
     core.builtins.insert("glob_test".to_string(), glob_test);
 }
 
 pub fn exit(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    let home = env::var("HOME").expect("HOME is not defined");
-    if let Ok(mut hist_file) = OpenOptions::new().write(true)
-                               .append(true).open(home + "/.bash_history") {
-        for h in &core.history {
-            write!(hist_file, "{}\n", h).expect("Cannot write history");
+    let status = if args.len() >= 2 {
+        match args[1].trim().parse::<i64>() {
+            Ok(_) if args.len() > 2 => {
+                eprintln!("bash: exit: too many arguments");
+                if ! core.has_flag('i') {
+                    process::exit(1);
+                }
+                return 1;
+            },
+            Ok(n) => (n & 0xff) as i32,
+            Err(_) => {
+                eprintln!("bash: exit: {}: numeric argument required", args[1]);
+                2
+            },
+        }
+    }else{
+        core.get_var("?").parse::<i32>().unwrap_or(1)
+    };
+
+    core.run_exit_trap(status);
+    if core.in_subshell {
+        process::exit(status);
+    }
+
+    if core.has_flag('i') {
+        eprintln!("exit");
+        history_write(core, None, true);
+    }
+
+    if core.shopts.get("huponexit") {
+        core.jobs.hangup();
+    }
+    process::exit(status);
+}
+
+/* redirections of exec are applied to the shell itself in SimpleCommand */
+pub fn exec(_core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() < 2 {
+        return 0;
+    }
+
+    let path = if args[1].contains('/') { args[1].clone() }else{ get_fullpath(&args[1]) };
+    if path.is_empty() {
+        eprintln!("bash: exec: {}: not found", args[1]);
+        process::exit(127);
+    }
+
+    let cargs: Vec<CString> = args[1..].iter()
+        .map(|a| CString::new(a.to_string()).unwrap())
+        .collect();
+    let envs: Vec<CString> = env::vars()
+        .map(|v| CString::new(format!("{}={}", v.0, v.1)).unwrap())
+        .collect();
+
+    proc::set_signals();
+    let err = unistd::execve(&CString::new(path).unwrap(), &cargs, &envs);
+    eprintln!("bash: exec: {}: {}", args[1], err.unwrap_err().desc());
+    process::exit(126);
+}
+
+pub fn history(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    match args.get(1).map(|s| s.as_str()) {
+        Some("-c") => {
+            core.history.clear();
+            core.new_history_start = 0;
+            return 0;
+        },
+        Some("-d") => return history_delete(core, args.get(2)),
+        Some("-r") => return history_read(core, args.get(2)),
+        Some("-w") => return history_write(core, args.get(2), false),
+        Some("-s") => {
+            core.push_history(&args[2..].join(" "), None);
+            return 0;
+        },
+        Some("--") | None => {},
+        Some(opt) if opt.starts_with('-') && opt.len() > 1 => {
+            eprintln!("bash: history: {}: invalid option", opt);
+            eprintln!("history: usage: history [-c] [-d offset] [n] or history -rw [filename] or history -s arg [arg...]");
+            return 2;
+        },
+        Some(_) => {},
+    }
+
+    let len = core.history.len();
+    let num = match args.iter().skip(1).find(|a| *a != "--") {
+        Some(n) => match n.parse::<usize>() {
+            Ok(n) => std::cmp::min(n, len),
+            Err(_) => {
+                eprintln!("bash: history: {}: numeric argument required", n);
+                return 1;
+            },
+        },
+        None => len,
+    };
+
+    let time_format = core.get_var("HISTTIMEFORMAT");
+    for (i, h) in core.history.iter().enumerate().skip(len - num) {
+        let time = match core.has_var("HISTTIMEFORMAT") {
+            true  => format_time(h.time, &time_format),
+            false => "".to_string(),
         };
-        hist_file.flush().expect("Cannot flush the history file");
+        println!("{:5}  {}{}", i+1, time, h.line);
     }
+    0
+}
 
-    if args.len() >= 2 {
-        if let Ok(status) = args[1].parse::<i32>(){
-            process::exit(status);
-        }else{
-            eprintln!("exit: {}: numeric wordument required", args[1]);
-            process::exit(2);
+/* the file is HISTFILE or ~/.bash_history when it is not given */
+fn history_read(core: &mut ShellCore, file: Option<&String>) -> i32 {
+    let path = file.cloned().unwrap_or(core.history_file());
+    match fs::read_to_string(&path) {
+        Ok(text) => {
+            let mut time = None;
+            for line in text.lines() {
+                if is_history_timestamp(line) {
+                    time = line[1..].parse::<i64>().ok();
+                }else{
+                    core.push_history(line, time.take());
+                }
+            }
+            core.new_history_start = core.history.len();
+            0
+        },
+        Err(_) => 1,
+    }
+}
+
+/* only the entries of this session are appended */
+fn history_write(core: &mut ShellCore, file: Option<&String>, append: bool) -> i32 {
+    let path = file.cloned().unwrap_or(core.history_file());
+    let mut hist_file = match OpenOptions::new().write(true).create(true)
+                              .append(append).truncate(! append).open(&path) {
+        Ok(f) => f,
+        Err(_) => return 1,
+    };
+
+    let with_time = core.has_var("HISTTIMEFORMAT");
+    let start = if append { core.new_history_start }else{ 0 };
+    for h in core.history.iter().skip(start) {
+        if with_time && writeln!(hist_file, "#{}", h.time).is_err() {
+            return 1;
+        }
+        if writeln!(hist_file, "{}", h.line).is_err() {
+            return 1;
         }
     }
+    match hist_file.flush() {
+        Ok(_) => {
+            core.new_history_start = core.history.len();
+            0
+        },
+        Err(_) => 1,
+    }
+}
 
-    if let Ok(status) = core.get_var("?").to_string().parse::<i32>(){
-        process::exit(status);
+/* a negative offset counts back from the end of the history */
+fn history_delete(core: &mut ShellCore, offset: Option<&String>) -> i32 {
+    let offset = match offset {
+        Some(o) => o,
+        None => {
+            eprintln!("bash: history: -d: option requires an argument");
+            eprintln!("history: usage: history [-c] [-d offset] [n] or history -rw [filename] or history -s arg [arg...]");
+            return 2;
+        },
+    };
+
+    let len = core.history.len() as i64;
+    let pos = match offset.parse::<i64>() {
+        Ok(n) if n > 0 && n <= len => n - 1,
+        Ok(n) if n < 0 && -n <= len => len + n,
+        _ => {
+            eprintln!("bash: history: {}: history position out of range", offset);
+            return 1;
+        },
+    };
+    core.history.remove(pos as usize);
+    if (pos as usize) < core.new_history_start {
+        core.new_history_start -= 1;
+    }
+    0
+}
+
+
+pub fn pwd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut physical = false;
+    for a in args[1..].iter() {
+        match a.as_str() {
+            "-P" => physical = true,
+            "-L" => physical = false,
+            _ if a.starts_with('-') => {
+                eprintln!("bash: pwd: {}: invalid option", a);
+                eprintln!("pwd: usage: pwd [-LP]");
+                return 2;
+            },
+            _ => {},
+        }
+    }
+
+    let current = match env::current_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("bash: pwd: {}", e);
+            return 1;
+        },
+    };
+
+    let logical = core.get_var("PWD");
+    let same_dir = match (fs::canonicalize(&logical), fs::canonicalize(&current)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+
+    if ! physical && logical.starts_with('/') && same_dir {
+        println!("{}", logical);
     }else{
-        eprintln!("Shell internal error");
-        process::exit(1);
+        println!("{}", current.display());
     }
+    0
+}
+
+/* returns the interpreted string and whether \c appeared */
+fn echo_escape(arg: &str) -> (String, bool) {
+    let mut ans = String::new();
+    let mut chars = arg.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            ans.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('a') => ans.push('\x07'),
+            Some('b') => ans.push('\x08'),
+            Some('c') => return (ans, true),
+            Some('e') | Some('E') => ans.push('\x1b'),
+            Some('f') => ans.push('\x0c'),
+            Some('n') => ans.push('\n'),
+            Some('r') => ans.push('\r'),
+            Some('t') => ans.push('\t'),
+            Some('v') => ans.push('\x0b'),
+            Some('\\') => ans.push('\\'),
+            Some(d) if d == '0' || d == 'x' => {
+                let (radix, max) = if d == '0' { (8, 3) }else{ (16, 2) };
+                let mut num = String::new();
+                while num.len() < max {
+                    match chars.peek() {
+                        Some(h) if h.is_digit(radix) => num.push(chars.next().unwrap()),
+                        _ => break,
+                    }
+                }
+
+                if d == 'x' && num.is_empty() {
+                    ans += "\\x";
+                }else if let Some(ch) = char::from_u32(u32::from_str_radix(&num, radix).unwrap_or(0)) {
+                    ans.push(ch);
+                }
+            },
+            Some(other) => {
+                ans.push('\\');
+                ans.push(other);
+            },
+            None => ans.push('\\'),
+        }
+    }
+    (ans, false)
 }
 
-pub fn history(_core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
-    let home = env::var("HOME").expect("HOME is not defined");
-    if let Ok(hist_file) = OpenOptions::new().read(true).open(home + "/.bash_history") {
-        let reader = BufReader::new(hist_file);
-        for (i, line) in reader.lines().enumerate() {
-            if let Ok(s) = line {
-                println!("  {}  {}", i, s);
+pub fn echo(_core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut newline = true;
+    let mut escape = false;
+    let mut n = 1;
+    while n < args.len() {
+        let a = &args[n];
+        if a.len() < 2 || ! a.starts_with('-') || ! a[1..].chars().all(|c| "neE".contains(c)) {
+            break;
+        }
+
+        for c in a[1..].chars() {
+            match c {
+                'n' => newline = false,
+                'e' => escape = true,
+                _   => escape = false,
+            }
+        }
+        n += 1;
+    }
+
+    let mut out = String::new();
+    for (i, a) in args[n..].iter().enumerate() {
+        if i != 0 {
+            out.push(' ');
+        }
+
+        if escape {
+            let (s, stop) = echo_escape(a);
+            out += &s;
+            if stop {
+                newline = false;
+                break;
             }
+        }else{
+            out += a;
         }
     }
+
+    if newline {
+        out.push('\n');
+    }
+    let mut stdout = io::stdout();
+    if let Err(e) = write!(stdout, "{}", out).and_then(|_| stdout.flush()) {
+        let msg = match e.raw_os_error() {
+            Some(n) => nix::errno::Errno::from_i32(n).desc().to_string(),
+            None => e.to_string(),
+        };
+        eprintln!("bash: echo: write error: {}", msg);
+        return 1;
+    }
     0
 }
 
+fn test_integer(s: &str) -> Result<i64, String> {
+    match s.trim().parse::<i64>() {
+        Ok(n) => Ok(n),
+        Err(_) => Err(format!("{}: integer expression expected", s)),
+    }
+}
+
+fn test_unary(op: &str, arg: &str) -> Result<bool, String> {
+    let meta = fs::metadata(arg);
+    let is_type = |f: fn(&fs::FileType) -> bool| {
+        meta.as_ref().map(|m| f(&m.file_type())).unwrap_or(false)
+    };
+    let has_mode = |bit: u32| {
+        meta.as_ref().map(|m| m.permissions().mode() & bit != 0).unwrap_or(false)
+    };
+    let access = |mode: unistd::AccessFlags| unistd::access(arg, mode).is_ok();
+
+    let ans = match op {
+        "-z" => arg.is_empty(),
+        "-n" => ! arg.is_empty(),
+        "-e" | "-a" => meta.is_ok(),
+        "-f" => is_type(|t| t.is_file()),
+        "-d" => is_type(|t| t.is_dir()),
+        "-b" => is_type(|t| t.is_block_device()),
+        "-c" => is_type(|t| t.is_char_device()),
+        "-p" => is_type(|t| t.is_fifo()),
+        "-S" => is_type(|t| t.is_socket()),
+        "-h" | "-L" => fs::symlink_metadata(arg).map(|m| m.file_type().is_symlink()).unwrap_or(false),
+        "-s" => meta.as_ref().map(|m| m.len() > 0).unwrap_or(false),
+        "-u" => has_mode(0o4000),
+        "-g" => has_mode(0o2000),
+        "-k" => has_mode(0o1000),
+        "-r" => access(unistd::AccessFlags::R_OK),
+        "-w" => access(unistd::AccessFlags::W_OK),
+        "-x" => access(unistd::AccessFlags::X_OK),
+        "-t" => unistd::isatty(test_integer(arg)? as i32).unwrap_or(false),
+        _ => return Err(format!("{}: unary operator expected", op)),
+    };
+    Ok(ans)
+}
+
+fn is_test_unary(op: &str) -> bool {
+    ["-z", "-n", "-e", "-a", "-f", "-d", "-b", "-c", "-p", "-S", "-h", "-L",
+     "-s", "-u", "-g", "-k", "-r", "-w", "-x", "-t"].contains(&op)
+}
+
+fn is_test_binary(op: &str) -> bool {
+    ["=", "==", "!=", "<", ">", "-eq", "-ne", "-lt", "-le", "-gt", "-ge",
+     "-nt", "-ot", "-ef"].contains(&op)
+}
+
+fn test_binary(left: &str, op: &str, right: &str) -> Result<bool, String> {
+    let modified = |s: &str| fs::metadata(s).and_then(|m| m.modified()).ok();
+
+    let ans = match op {
+        "=" | "==" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        ">" => left > right,
+        "-eq" => test_integer(left)? == test_integer(right)?,
+        "-ne" => test_integer(left)? != test_integer(right)?,
+        "-lt" => test_integer(left)? < test_integer(right)?,
+        "-le" => test_integer(left)? <= test_integer(right)?,
+        "-gt" => test_integer(left)? > test_integer(right)?,
+        "-ge" => test_integer(left)? >= test_integer(right)?,
+        "-nt" => match (modified(left), modified(right)) {
+            (Some(l), Some(r)) => l > r,
+            (Some(_), None) => true,
+            _ => false,
+        },
+        "-ot" => match (modified(left), modified(right)) {
+            (Some(l), Some(r)) => l < r,
+            (None, Some(_)) => true,
+            _ => false,
+        },
+        "-ef" => match (fs::metadata(left), fs::metadata(right)) {
+            (Ok(l), Ok(r)) => l.dev() == r.dev() && l.ino() == r.ino(),
+            _ => false,
+        },
+        _ => return Err(format!("{}: binary operator expected", op)),
+    };
+    Ok(ans)
+}
+
+/* evaluates the arguments by their number as POSIX specifies */
+fn test_args(args: &[String]) -> Result<bool, String> {
+    match args.len() {
+        0 => Ok(false),
+        1 => Ok(! args[0].is_empty()),
+        2 if args[0] == "!" => Ok(! test_args(&args[1..])?),
+        2 => test_unary(&args[0], &args[1]),
+        3 if is_test_binary(&args[1]) => test_binary(&args[0], &args[1], &args[2]),
+        3 if args[1] == "-a" => Ok(test_args(&args[0..1])? && test_args(&args[2..])?),
+        3 if args[1] == "-o" => Ok(test_args(&args[0..1])? || test_args(&args[2..])?),
+        3 if args[0] == "!" => Ok(! test_args(&args[1..])?),
+        3 if args[0] == "(" && args[2] == ")" => test_args(&args[1..2]),
+        3 => Err(format!("{}: binary operator expected", args[1])),
+        4 if args[0] == "!" => Ok(! test_args(&args[1..])?),
+        4 if args[0] == "(" && args[3] == ")" => test_args(&args[1..3]),
+        _ => {
+            let mut pos = 0;
+            let ans = test_or(args, &mut pos)?;
+            if pos < args.len() {
+                return Err(format!("{}: syntax error", args[pos]));
+            }
+            Ok(ans)
+        },
+    }
+}
+
+fn test_or(args: &[String], pos: &mut usize) -> Result<bool, String> {
+    let mut ans = test_and(args, pos)?;
+    while *pos < args.len() && args[*pos] == "-o" {
+        *pos += 1;
+        let right = test_and(args, pos)?;
+        ans = ans || right;
+    }
+    Ok(ans)
+}
+
+fn test_and(args: &[String], pos: &mut usize) -> Result<bool, String> {
+    let mut ans = test_term(args, pos)?;
+    while *pos < args.len() && args[*pos] == "-a" {
+        *pos += 1;
+        let right = test_term(args, pos)?;
+        ans = ans && right;
+    }
+    Ok(ans)
+}
+
+fn test_term(args: &[String], pos: &mut usize) -> Result<bool, String> {
+    let rest = &args[*pos..];
+    if rest.is_empty() {
+        return Err("argument expected".to_string());
+    }
+
+    if rest[0] == "!" {
+        *pos += 1;
+        return Ok(! test_term(args, pos)?);
+    }
+
+    if rest[0] == "(" {
+        *pos += 1;
+        let ans = test_or(args, pos)?;
+        if *pos >= args.len() || args[*pos] != ")" {
+            return Err("`)' expected".to_string());
+        }
+        *pos += 1;
+        return Ok(ans);
+    }
+
+    if rest.len() >= 3 && is_test_binary(&rest[1]) {
+        *pos += 3;
+        return test_binary(&rest[0], &rest[1], &rest[2]);
+    }
+
+    if rest.len() >= 2 && is_test_unary(&rest[0]) {
+        *pos += 2;
+        return test_unary(&rest[0], &rest[1]);
+    }
+
+    *pos += 1;
+    Ok(! rest[0].is_empty())
+}
+
+pub fn test(_core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let name = args[0].clone();
+    let mut words = args[1..].to_vec();
+    if name == "[" {
+        if words.last().map(|s| s.as_str()) != Some("]") {
+            eprintln!("bash: [: missing `]'");
+            return 2;
+        }
+        words.pop();
+    }
+
+    match test_args(&words) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(msg) => {
+            eprintln!("bash: {}: {}", name, msg);
+            2
+        },
+    }
+}
+
+fn print_traps(core: &ShellCore, sigs: &[i32]) {
+    let mut sigs = sigs.to_vec();
+    sigs.sort();
+    for n in sigs {
+        if let Some(com) = core.traps.get(&n) {
+            println!("trap -- '{}' {}", com.replace("'", "'\\''"), proc::signal_name(n));
+        }
+    }
+}
+
+fn print_signal_list() {
+    let sigs = Signal::iterator().collect::<Vec<Signal>>();
+    for (i, sig) in sigs.iter().enumerate() {
+        let sep = if i % 5 == 4 || i == sigs.len()-1 { "\n" }else{ "\t" };
+        print!("{:2}) {}{}", *sig as i32, sig.as_str(), sep);
+    }
+}
+
+pub fn trap(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() == 1 || (args.len() == 2 && args[1] == "-p") {
+        let sigs = core.traps.keys().cloned().collect::<Vec<i32>>();
+        print_traps(core, &sigs);
+        return 0;
+    }
 
-pub fn pwd(_core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
-    if let Some(p) = env::current_dir().expect("Cannot get current dir").to_str() {
-        println!("{}", p.to_string());
+    if args[1] == "-l" {
+        print_signal_list();
         return 0;
+    }
+
+    let mut words = &args[1..];
+    if words[0] == "--" {
+        words = &words[1..];
+    }
+
+    /* the command is None when the signals are reset */
+    let (com, specs) = match words {
+        [] => return 0,
+        [w] | [w, ..] if w == "-p" || w == "-" => (None, &words[1..]),
+        [_] => (None, words),
+        [c, ..] => (Some(c.clone()), &words[1..]),
     };
 
-    panic!("Cannot get current dir");
+    let mut sigs = vec![];
+    let mut status = 0;
+    for spec in specs {
+        match proc::signal_number(spec) {
+            Some(n) => sigs.push(n),
+            None => {
+                eprintln!("bash: trap: {}: invalid signal specification", spec);
+                status = 1;
+            },
+        }
+    }
+
+    if words[0] == "-p" {
+        print_traps(core, &sigs);
+        return status;
+    }
+
+    for n in sigs {
+        let sig = Signal::try_from(n).ok();
+        match (&com, sig) {
+            (None, Some(s)) => proc::reset_signal(s),
+            (Some(c), Some(s)) if c.is_empty() => proc::ignore_signal(s),
+            (Some(_), Some(s)) => proc::set_trap(s),
+            _ => {},
+        }
+
+        match &com {
+            Some(c) => core.traps.insert(n, c.clone()),
+            None    => core.traps.remove(&n),
+        };
+    }
+
+    status
 }
 
 pub fn true_(_core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
@@ -101,346 +668,1730 @@ pub fn false_(_core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
     1
 }
 
+/* %n, %+, %%, %-, %string, %?string -> position in the job table */
+fn job_position(core: &ShellCore, spec: &str, com: &str) -> Option<usize> {
+    let (first, second) = core.jobs.get_top_priority_id();
+    let body = spec.strip_prefix('%').unwrap_or(spec);
+    let alive = |j: &Job| j.status != 'D' && j.status != 'I';
+
+    let found = core.jobs.backgrounds.iter().position(|j| {
+        if ! alive(j) {
+            return false;
+        }
+
+        match body {
+            "" | "+" | "%" => j.id == first,
+            "-"            => j.id == second,
+            _ => match body.parse::<usize>() {
+                Ok(n) => j.id == n,
+                _ => match body.strip_prefix('?') {
+                    Some(s) => j.text.contains(s),
+                    None    => j.text.starts_with(body),
+                },
+            },
+        }
+    });
+
+    if found.is_none() {
+        let name = if body.is_empty() || body == "+" || body == "%" { "current" }else{ spec };
+        eprintln!("bash: {}: {}: no such job", com, name);
+    }
+    found
+}
+
+pub fn disown(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut opts = String::new();
+    let mut specs = vec![];
+    for arg in args[1..].iter() {
+        if arg.starts_with('-') && arg.len() > 1 && specs.is_empty() {
+            for c in arg[1..].chars() {
+                if ! "ahr".contains(c) {
+                    eprintln!("bash: disown: -{}: invalid option", c);
+                    eprintln!("disown: usage: disown [-h] [-ar] [jobspec ... | pid ...]");
+                    return 2;
+                }
+                opts.push(c);
+            }
+        }else{
+            specs.push(arg.clone());
+        }
+    }
+
+    let mut status = 0;
+    let positions = if opts.contains('a') || opts.contains('r') {
+        (0..core.jobs.backgrounds.len()).filter(|i| {
+            let s = core.jobs.backgrounds[*i].status;
+            s != 'I' && s != 'D' && (s == 'R' || ! opts.contains('r'))
+        }).collect::<Vec<usize>>()
+    }else{
+        if specs.is_empty() {
+            specs.push("%+".to_string());
+        }
+        let mut positions = vec![];
+        for spec in &specs {
+            let pos = match spec.parse::<i32>() {
+                Ok(n) if ! spec.starts_with('%') => core.jobs.backgrounds.iter()
+                         .position(|j| j.status != 'I' && j.pids.contains(&Pid::from_raw(n))),
+                _ => job_position(core, spec, "disown"),
+            };
+            match pos {
+                Some(p) => positions.push(p),
+                None => {
+                    if ! spec.starts_with('%') {
+                        eprintln!("bash: disown: {}: no such job", spec);
+                    }
+                    status = 1;
+                },
+            }
+        }
+        positions
+    };
+
+    for pos in positions {
+        match opts.contains('h') {
+            true  => core.jobs.backgrounds[pos].no_hup = true,
+            false => core.jobs.disown(pos),
+        }
+    }
+    status
+}
+
 pub fn bg(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     let (first, second) = core.jobs.get_top_priority_id();
+    let specs = if args.len() < 2 { vec!["%+".to_string()] }else{ args[1..].to_vec() };
+
+    let mut status = 0;
+    for spec in specs {
+        let job = match job_position(core, &spec, "bg") {
+            Some(pos) => &mut core.jobs.backgrounds[pos],
+            None => {
+                status = 1;
+                continue;
+            },
+        };
+
+        if job.status == 'R' {
+            eprintln!("bash: bg: job {} already in background", job.id);
+            continue;
+        }
 
-    fn bg_core (job: &mut Job, first: usize, second: usize) {
         job.status = 'R';
         println!("{}", &job.status_string(first, second));
         for p in &job.async_pids {
             signal::kill(*p, Signal::SIGCONT).unwrap();
         }
     }
+    status
+}
+
+pub fn fg(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let spec = if args.len() < 2 { "%+" }else{ &args[1] };
+    let j = match job_position(core, spec, "fg") {
+        Some(pos) => pos,
+        None => return 1,
+    };
+
+    let terminal = core.has_flag('i') && ! core.jobs.backgrounds[j].signaled_bg;
+    core.jobs.backgrounds[j].status = 'F';
+    for p in &core.jobs.backgrounds[j].async_pids {
+        if terminal {
+            let _ = unistd::tcsetpgrp(0, p.clone());
+            let _ = unistd::tcsetpgrp(1, p.clone());
+        }
+        signal::kill(*p, Signal::SIGCONT).unwrap();
+    }
+    core.jobs.foreground = core.jobs.backgrounds[j].clone();
+    let pipestatus = core.jobs.wait_bg_job_at_foreground(core.jobs.backgrounds[j].id);
+
+    if terminal {
+        let _ = unistd::tcsetpgrp(0, unistd::getpid());
+        let _ = unistd::tcsetpgrp(1, unistd::getpid());
+    }
+
+    match pipestatus.last() {
+        Some(n) => *n,
+        None => 0,
+    }
+}
+
+fn kill_list(args: &[String]) -> i32 {
+    if args.is_empty() {
+        print_signal_list();
+        return 0;
+    }
+
+    let mut status = 0;
+    for arg in args {
+        match arg.parse::<i32>() {
+            Ok(n) => match Signal::try_from(if n > 128 { n - 128 }else{ n }) {
+                Ok(sig) => println!("{}", &sig.as_str()[3..]),
+                _ => {
+                    eprintln!("bash: kill: {}: invalid signal specification", arg);
+                    status = 1;
+                },
+            },
+            _ => match proc::signal_number(arg) {
+                Some(n) if n > 0 => println!("{}", n),
+                _ => {
+                    eprintln!("bash: kill: {}: invalid signal specification", arg);
+                    status = 1;
+                },
+            },
+        }
+    }
+    status
+}
+
+pub fn kill(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() > 1 && args[1] == "-l" {
+        return kill_list(&args[2..]);
+    }
+
+    let mut sig = Some(Signal::SIGTERM);
+    let mut pos = 1;
+    if args.len() > 2 && (args[1] == "-s" || args[1] == "-n") {
+        pos = 2;
+    }
+    if pos == 2 || (args.len() > 1 && args[1].starts_with('-') && args[1] != "--") {
+        let spec = if pos == 2 { &args[2] }else{ &args[1][1..] };
+        sig = match proc::signal_number(spec) {
+            Some(0) => None,
+            Some(n) => Signal::try_from(n).ok(),
+            None => {
+                eprintln!("bash: kill: {}: invalid signal specification", spec);
+                return 1;
+            },
+        };
+        pos += 1;
+    }
+    if args.len() > pos && args[pos] == "--" {
+        pos += 1;
+    }
+
+    if args.len() <= pos {
+        eprintln!("kill: usage: kill [-s sigspec | -n signum | -sigspec] pid | jobspec ... or kill -l [sigspec]");
+        return 2;
+    }
+
+    let mut status = 0;
+    for arg in args[pos..].iter() {
+        let pids = if arg.starts_with('%') {
+            match job_position(core, arg, "kill") {
+                Some(j) => core.jobs.backgrounds[j].pids.clone(),
+                None => {
+                    status = 1;
+                    continue;
+                },
+            }
+        }else{
+            match arg.parse::<i32>() {
+                Ok(n) => vec![Pid::from_raw(n)],
+                _ => {
+                    eprintln!("bash: kill: {}: arguments must be process or job IDs", arg);
+                    status = 1;
+                    continue;
+                },
+            }
+        };
+
+        for pid in pids {
+            if signal::kill(pid, sig).is_err() {
+                eprintln!("bash: kill: ({}) - No such process", pid);
+                status = 1;
+            }
+        }
+    }
+    status
+}
+
+pub fn shift(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let num = if args.len() == 2 {
+        match args[1].parse::<i64>() {
+            Ok(n) if n >= 0 => n as usize,
+            Ok(_) => {
+                eprintln!("bash: shift: {}: shift count out of range", args[1]);
+                return 1;
+            },
+            _ => {
+                eprintln!("bash: shift: {}: numeric argument required", args[1]);
+                return 1;
+            },
+        }
+    }else if args.len() == 1 {
+        1
+    }else{
+        eprintln!("bash: shift: too many arguments");
+        return 1;
+    };
+
+    if core.args.len() < num+1 {
+        return 1;
+    }
+
+    core.args.drain(1..num+1);
+    0
+}
+
+pub fn builtin(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() > 0 {
+        args.remove(0);
+    }
+    if args.len() < 1 {
+        return 0;
+    }
+
+    if let Some(func) = core.get_builtin(&args[0]) {
+        func(core, args)
+    }else{
+        eprintln!("bash: builtin: {}: not a shell builtin", args[0]);
+        1
+    }
+}
+
+/* keeps exported variables such as PWD in the environment */
+fn set_shell_or_env_var(core: &mut ShellCore, key: &str, value: &str) {
+    if ! core.vars.contains_key(key) && env::var(key).is_ok() {
+        env::set_var(key, value);
+    }else{
+        core.set_var(key, value);
+    }
+}
+
+/* resolves . and .. in a path without following symbolic links */
+fn logical_path(base: &str, dir: &str) -> String {
+    let path = if dir.starts_with('/') {
+        dir.to_string()
+    }else{
+        base.to_string() + "/" + dir
+    };
+
+    let mut elems: Vec<&str> = vec![];
+    for e in path.split('/') {
+        match e {
+            "" | "." => {},
+            ".." => {elems.pop();},
+            _ => elems.push(e),
+        }
+    }
+
+    "/".to_string() + &elems.join("/")
+}
+
+pub fn cd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() == 0 {
+        eprintln!("Bug of this shell");
+        return 1;
+    }
+    if args.len() > 2 {
+        eprintln!("{}", "bash: cd: too many arguments");
+        return 1;
+    }
+
+    let mut print_dir = false;
+    if args.len() == 1 { //only "cd"
+        if ! core.has_var("HOME") {
+            eprintln!("bash: cd: HOME not set");
+            return 1;
+        }
+        args.push(core.get_var("HOME"));
+    }else if args[1] == "-" { // cd -
+        if ! core.has_var("OLDPWD") {
+            eprintln!("bash: cd: OLDPWD not set");
+            return 1;
+        }
+        args[1] = core.get_var("OLDPWD");
+        print_dir = true;
+    }else if let Some(dir) = search_cdpath(core, &args[1]) {
+        args[1] = dir;
+        print_dir = true;
+    };
+
+    match change_dir(core, &args[1], "cd") {
+        Some(pwd) => {
+            if print_dir {
+                println!("{}", pwd);
+            }
+            0
+        },
+        None => 1,
+    }
+}
+
+/* a directory in CDPATH for a relative path that isn't in the current directory */
+fn search_cdpath(core: &ShellCore, dir: &str) -> Option<String> {
+    let is_relative = ! dir.starts_with('/') && dir != "." && dir != ".."
+                      && ! dir.starts_with("./") && ! dir.starts_with("../");
+    if ! is_relative || dir.is_empty() || Path::new(dir).is_dir() {
+        return None;
+    }
+
+    core.get_var("CDPATH").split(':').filter(|p| ! p.is_empty())
+        .map(|p| format!("{}/{}", p.trim_end_matches('/'), dir))
+        .find(|p| Path::new(p).is_dir())
+}
+
+/* the logical path of the current directory */
+fn current_dir(core: &ShellCore) -> String {
+    match env::current_dir() {
+        Ok(_) if core.get_var("PWD").starts_with('/') => core.get_var("PWD"),
+        Ok(dir) => dir.display().to_string(),
+        Err(_) => core.get_var("PWD"),
+    }
+}
+
+/* changes the directory and sets PWD and OLDPWD. The new PWD is returned. */
+fn change_dir(core: &mut ShellCore, dir: &str, builtin_name: &str) -> Option<String> {
+    let old = current_dir(core);
+    let newdir = logical_path(&old, dir);
+    let target = if Path::new(&newdir).is_dir() { newdir.clone() }else{ dir.to_string() };
+
+    if let Err(e) = env::set_current_dir(&target) {
+        let msg = match e.kind() {
+            io::ErrorKind::NotFound => "No such file or directory".to_string(),
+            io::ErrorKind::PermissionDenied => "Permission denied".to_string(),
+            _ if Path::new(&target).exists() => "Not a directory".to_string(),
+            _ => e.to_string(),
+        };
+        eprintln!("bash: {}: {}: {}", builtin_name, dir, msg);
+        return None;
+    }
+
+    let pwd = if target == newdir {
+        newdir
+    }else{
+        match env::current_dir() {
+            Ok(dir) => dir.display().to_string(),
+            Err(_) => newdir,
+        }
+    };
+
+    set_shell_or_env_var(core, "OLDPWD", &old);
+    set_shell_or_env_var(core, "PWD", &pwd);
+    Some(pwd)
+}
+
+/* the current directory and the directory stack. The current one is the top. */
+fn full_dir_stack(core: &ShellCore) -> Vec<String> {
+    let mut stack = vec![current_dir(core)];
+    stack.extend(core.dir_stack.iter().cloned());
+    stack
+}
+
+/* +N counts from the top of the stack, -N from the bottom */
+fn dir_stack_index(arg: &str, len: usize) -> Option<Option<usize>> {
+    let n = match arg[1..].parse::<usize>() {
+        Ok(n) if arg.starts_with('+') || arg.starts_with('-') => n,
+        _ => return None,
+    };
+
+    match (arg.starts_with('+'), n < len) {
+        (_, false)    => Some(None),
+        (true, true)  => Some(Some(n)),
+        (false, true) => Some(Some(len - 1 - n)),
+    }
+}
+
+fn print_dir_stack(core: &ShellCore, opts: &str, entries: &[String]) {
+    let home = core.get_var("HOME");
+    let dirs = entries.iter().map(|d| {
+        if opts.contains('l') || home.is_empty() || ! (d == &home || d.starts_with(&(home.clone() + "/"))) {
+            d.clone()
+        }else{
+            d.replacen(&home, "~", 1)
+        }
+    }).collect::<Vec<String>>();
+
+    if opts.contains('v') {
+        for (i, d) in dirs.iter().enumerate() {
+            println!("{:2}  {}", i, d);
+        }
+    }else if opts.contains('p') {
+        for d in dirs {
+            println!("{}", d);
+        }
+    }else{
+        println!("{}", dirs.join(" "));
+    }
+}
+
+pub fn dirs(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut opts = String::new();
+    let mut entry = None;
+    let stack = full_dir_stack(core);
+    for arg in args[1..].iter() {
+        if arg.starts_with('-') && arg[1..].chars().all(|c| "clpv".contains(c)) && arg.len() > 1 {
+            opts += &arg[1..];
+            continue;
+        }
+
+        match dir_stack_index(arg, stack.len()) {
+            Some(Some(n)) => entry = Some(n),
+            Some(None) => {
+                eprintln!("bash: dirs: {}: directory stack index out of range", arg);
+                return 1;
+            },
+            None => {
+                eprintln!("bash: dirs: {}: invalid number", arg);
+                eprintln!("dirs: usage: dirs [-clpv] [+N] [-N]");
+                return 2;
+            },
+        }
+    }
+
+    if opts.contains('c') {
+        core.dir_stack.clear();
+        return 0;
+    }
+
+    match entry {
+        Some(n) => print_dir_stack(core, &opts, &stack[n..n+1]),
+        None    => print_dir_stack(core, &opts, &stack),
+    }
+    0
+}
+
+pub fn pushd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() > 2 {
+        eprintln!("bash: pushd: too many arguments");
+        return 1;
+    }
+
+    let mut stack = full_dir_stack(core);
+    match args.get(1) {
+        None => {
+            if stack.len() < 2 {
+                eprintln!("bash: pushd: no other directory");
+                return 1;
+            }
+            stack.swap(0, 1);
+        },
+        Some(arg) if (arg.starts_with('+') || arg.starts_with('-')) && arg.len() > 1 => {
+            match dir_stack_index(arg, stack.len()) {
+                _ if stack.len() < 2 => {
+                    eprintln!("bash: pushd: directory stack empty");
+                    return 1;
+                },
+                Some(Some(n)) => stack.rotate_left(n),
+                Some(None) => {
+                    eprintln!("bash: pushd: {}: directory stack index out of range", arg);
+                    return 1;
+                },
+                None => {
+                    eprintln!("bash: pushd: {}: invalid number", arg);
+                    eprintln!("pushd: usage: pushd [dir | +N | -N]");
+                    return 2;
+                },
+            }
+        },
+        Some(dir) => stack.insert(0, dir.clone()),
+    }
+
+    match change_dir(core, &stack[0], "pushd") {
+        Some(pwd) => stack[0] = pwd,
+        None => return 1,
+    }
+    core.dir_stack = stack[1..].to_vec();
+    print_dir_stack(core, "", &stack);
+    0
+}
+
+pub fn popd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() > 2 {
+        eprintln!("bash: popd: too many arguments");
+        return 1;
+    }
+    if core.dir_stack.is_empty() {
+        eprintln!("bash: popd: directory stack empty");
+        return 1;
+    }
+
+    let mut stack = full_dir_stack(core);
+    let pos = match args.get(1) {
+        None => 0,
+        Some(arg) => match dir_stack_index(arg, stack.len()) {
+            Some(Some(n)) => n,
+            Some(None) => {
+                eprintln!("bash: popd: {}: directory stack index out of range", arg);
+                return 1;
+            },
+            None => {
+                eprintln!("bash: popd: {}: invalid number", arg);
+                eprintln!("popd: usage: popd [+N | -N]");
+                return 2;
+            },
+        },
+    };
+
+    if pos == 0 && change_dir(core, &stack[1], "popd").is_none() {
+        return 1;
+    }
+    stack.remove(pos);
+    core.dir_stack = stack[1..].to_vec();
+    print_dir_stack(core, "", &full_dir_stack(core));
+    0
+}
+
+pub fn alias(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() <= 1 || (args.len() == 2 && args[1] == "-p") {
+        let mut keys = core.aliases.keys().collect::<Vec<&String>>();
+        keys.sort();
+        for k in keys {
+            println!("alias {}='{}'", k, core.aliases[k].replace("'", "'\\''"));
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    for arg in args[1..].iter() {
+        if let Some(n) = arg.find('=') {
+            core.aliases.insert(arg[..n].to_string(), arg[n+1..].to_string());
+        }else if let Some(com) = core.aliases.get(arg) {
+            println!("alias {}='{}'", arg, com.replace("'", "'\\''"));
+        }else{
+            eprintln!("bash: alias: {}: not found", arg);
+            status = 1;
+        }
+    }
+    status
+}
+
+pub fn unalias(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() <= 1 {
+        eprintln!("unalias: usage: unalias [-a] name [name ...]");
+        return 2;
+    }
+
+    if args[1] == "-a" {
+        core.aliases.clear();
+        return 0;
+    }
+
+    let mut status = 0;
+    for arg in args[1..].iter() {
+        if core.aliases.remove(arg).is_none() {
+            eprintln!("bash: unalias: {}: not found", arg);
+            status = 1;
+        }
+    }
+    status
+}
+
+fn complete_usage() -> i32 {
+    eprintln!("complete: usage: complete [-pr] [-W wordlist] [name ...]");
+    2
+}
+
+fn print_completion(core: &ShellCore, name: &str) -> i32 {
+    match core.completions.get(name) {
+        Some(words) => {
+            println!("complete -W '{}' {}", words.replace("'", "'\\''"), name);
+            0
+        },
+        None => {
+            eprintln!("bash: complete: {}: no completion specification", name);
+            1
+        },
+    }
+}
+
+/* only the -W wordlist form of the completion specifications */
+pub fn complete(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut names = args[1..].to_vec();
+    let mut opts = String::new();
+    let mut wordlist = None;
+    while ! names.is_empty() && names[0].starts_with('-') && names[0].len() > 1 {
+        let opt = names.remove(0);
+        if opt == "--" {
+            break;
+        }
+        for c in opt[1..].chars() {
+            match c {
+                'p' | 'r' => opts.push(c),
+                'W' => {
+                    if names.is_empty() {
+                        eprintln!("bash: complete: -W: option requires an argument");
+                        return complete_usage();
+                    }
+                    wordlist = Some(names.remove(0));
+                },
+                _ => {
+                    eprintln!("bash: complete: -{}: invalid option", c);
+                    return complete_usage();
+                },
+            }
+        }
+    }
+
+    if opts.contains('r') {
+        if names.is_empty() {
+            core.completions.clear();
+            return 0;
+        }
+        let mut status = 0;
+        for name in names {
+            if core.completions.remove(&name).is_none() {
+                eprintln!("bash: complete: {}: no completion specification", name);
+                status = 1;
+            }
+        }
+        return status;
+    }
+
+    if let Some(words) = wordlist.filter(|_| ! opts.contains('p')) {
+        for name in names {
+            core.completions.insert(name, words.clone());
+        }
+        return 0;
+    }
+
+    if names.is_empty() {
+        let mut keys = core.completions.keys().cloned().collect::<Vec<String>>();
+        keys.sort();
+        names = keys;
+    }
+    let mut status = 0;
+    for name in names {
+        status = std::cmp::max(status, print_completion(core, &name));
+    }
+    status
+}
+
+fn set_option_flag(name: &str) -> Option<char> {
+    match name {
+        "errexit" => Some('e'),
+        "histexpand" => Some('H'),
+        "noclobber" => Some('C'),
+        "nounset" => Some('u'),
+        "verbose" => Some('v'),
+        "xtrace" => Some('x'),
+        _ => None,
+    }
+}
+
+pub fn set(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() == 1 {
+       for k in core.vars.keys() {
+           println!("{}={}", k, core.vars[k]);
+       }
+       return 0;
+    }
+
+    let mut n = 1;
+    let mut positional = false;
+    while n < args.len() {
+        let arg = args[n].clone();
+        if arg == "--" {
+            positional = true;
+            n += 1;
+            break;
+        }
+        if arg == "-" {
+            core.flags.retain(|c| c != 'x' && c != 'v');
+            positional = true;
+            n += 1;
+            break;
+        }
+        if arg.len() < 2 || ! (arg.starts_with('-') || arg.starts_with('+')) {
+            break;
+        }
+
+        let on = arg.starts_with('-');
+        let mut flags = vec![];
+        if &arg[1..] == "o" {
+            n += 1;
+            let name = args.get(n).cloned().unwrap_or_default();
+            if name == "vi" || name == "emacs" { // editing modes are not flags
+                core.vi_mode = (name == "vi") == on;
+                n += 1;
+                continue;
+            }
+            match set_option_flag(&name) {
+                Some(f) => flags.push(f),
+                None => {
+                    eprintln!("bash: set: {}: invalid option name", name);
+                    return 1;
+                },
+            }
+        }else{
+            for f in arg[1..].chars() {
+                if ! "euvxCH".contains(f) {
+                    eprintln!("bash: set: {}{}: invalid option", &arg[..1], f);
+                    return 2;
+                }
+                flags.push(f);
+            }
+        }
+
+        for f in flags {
+            core.flags.retain(|c| c != f);
+            if on {
+                core.flags.push(f);
+            }
+        }
+        n += 1;
+    }
+
+    if positional || n < args.len() {
+        core.args.truncate(1);
+        core.args.extend_from_slice(&args[n..]);
+    }
+
+    0
+}
+
+/* splits a line of read into the fields for the variables; escaped characters are not delimiters */
+fn read_split(chars: &[(char, bool)], ifs: &str, num: usize) -> Vec<String> {
+    let is_ws = |c: &(char, bool)| ! c.1 && " \t\n".contains(c.0) && ifs.contains(c.0);
+    let is_delim = |c: &(char, bool)| ! c.1 && ifs.contains(c.0);
+    let to_string = |cs: &[(char, bool)]| cs.iter().map(|c| c.0).collect::<String>();
+
+    let mut ans = vec![];
+    let mut pos = 0;
+    while pos < chars.len() && is_ws(&chars[pos]) {
+        pos += 1;
+    }
+
+    while ans.len() + 1 < num {
+        let start = pos;
+        while pos < chars.len() && ! is_delim(&chars[pos]) {
+            pos += 1;
+        }
+        ans.push(to_string(&chars[start..pos]));
+
+        while pos < chars.len() && is_ws(&chars[pos]) {
+            pos += 1;
+        }
+        if pos < chars.len() && is_delim(&chars[pos]) {
+            pos += 1;
+            while pos < chars.len() && is_ws(&chars[pos]) {
+                pos += 1;
+            }
+        }
+    }
+
+    let mut end = chars.len();
+    while end > pos && is_ws(&chars[end-1]) {
+        end -= 1;
+    }
+    if end > pos && is_delim(&chars[end-1]) && ! chars[pos..end-1].iter().any(is_delim) {
+        end -= 1;
+        while end > pos && is_ws(&chars[end-1]) {
+            end -= 1;
+        }
+    }
+    ans.push(to_string(&chars[pos..end]));
+    ans
+}
+
+pub fn read(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut raw = false;
+    let mut names = vec![];
+    let mut n = 1;
+    while n < args.len() {
+        match args[n].as_str() {
+            "-r" => raw = true,
+            "-p" if n + 1 < args.len() => {
+                n += 1;
+                if unistd::isatty(0).unwrap_or(false) {
+                    eprint!("{}", args[n]);
+                }
+            },
+            a if a.starts_with('-') && names.is_empty() => {
+                eprintln!("bash: read: {}: invalid option", a);
+                return 2;
+            },
+            a => names.push(a.to_string()),
+        }
+        n += 1;
+    }
+
+    let mut chars: Vec<(char, bool)> = vec![];
+    let mut status = 0;
+    loop {
+        let mut line = match read_line_fd0() {
+            Some(line) => line,
+            None => {
+                status = 1;
+                break;
+            },
+        };
+
+        let newline = line.ends_with('\n');
+        if newline {
+            line.pop();
+        }
+
+        let mut escaped = false;
+        for ch in line.chars() {
+            if escaped {
+                chars.push((ch, true));
+                escaped = false;
+            }else if ch == '\\' && ! raw {
+                escaped = true;
+            }else{
+                chars.push((ch, false));
+            }
+        }
+
+        if ! escaped {
+            if ! newline {
+                status = 1;
+            }
+            break;
+        }
+    }
+
+    if names.is_empty() {
+        let line = chars.iter().map(|c| c.0).collect::<String>();
+        if ! core.substitute_var("REPLY", &line) {
+            return 1;
+        }
+        return status;
+    }
+
+    let ifs = if core.has_var("IFS") { core.get_var("IFS") }else{ " \t\n".to_string() };
+    let fields = read_split(&chars, &ifs, names.len());
+    for (name, value) in names.iter().zip(fields.iter()) {
+        if ! core.substitute_var(name, value) {
+            status = 1;
+        }
+    }
+
+    status
+}
+
+pub fn source(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() < 2 {
+        eprintln!("bash: {}: filename argument required", args[0]);
+        eprintln!("{}: usage: {} filename [arguments]", args[0], args[0]);
+        return 2;
+    }
+
+    let source = match fs::read_to_string(&args[1]) {
+        Ok(source) => source,
+        Err(e) => {
+            let msg = match e.kind() {
+                io::ErrorKind::NotFound => "No such file or directory".to_string(),
+                io::ErrorKind::PermissionDenied => "Permission denied".to_string(),
+                _ => e.to_string(),
+            };
+            eprintln!("bash: {}: {}", &args[1], msg);
+            return 1;
+        },
+    };
+
+    let backup_args = if args.len() > 2 {
+        let backup = core.args.clone();
+        core.args = vec!(core.args[0].clone());
+        core.args.extend_from_slice(&args[2..]);
+        Some(backup)
+    }else{
+        None
+    };
+    let backup_return = core.return_enable;
+
+    core.set_var("?", "0");
+    let mut feeder = Feeder::new_from(source);
+    if let Some(mut script) = Script::parse(&mut feeder, core) {
+        core.return_enable = true;
+        script.exec(core);
+    }
+    core.run_return_trap();
+
+    core.return_enable = backup_return;
+    core.return_flag = false;
+    if let Some(backup) = backup_args {
+        core.args = backup;
+    }
+    core.get_var("?").parse::<i32>().unwrap_or(1)
+}
+
+/* the number of the loops for break and continue. Err has the exit status. */
+fn loop_count(core: &mut ShellCore, args: &[String]) -> Result<usize, i32> {
+    if core.loop_level == 0 {
+        eprintln!("bash: {}: only meaningful in a `for', `while', or `until' loop", args[0]);
+        return Err(0);
+    }
+    if args.len() > 2 {
+        eprintln!("bash: {}: too many arguments", args[0]);
+        if ! core.has_flag('i') {
+            process::exit(1);
+        }
+        return Err(1);
+    }
+
+    let n = match args.get(1) {
+        None => return Ok(1),
+        Some(n) => n,
+    };
+    match n.parse::<i64>() {
+        Ok(n) if n > 0 => Ok(std::cmp::min(n as usize, core.loop_level)),
+        Ok(_) => {
+            eprintln!("bash: {}: {}: loop count out of range", args[0], n);
+            core.break_counter = core.loop_level;
+            Err(1)
+        },
+        Err(_) => {
+            eprintln!("bash: {}: {}: numeric argument required", args[0], n);
+            if ! core.has_flag('i') {
+                process::exit(128);
+            }
+            core.break_counter = core.loop_level;
+            Err(128)
+        },
+    }
+}
+
+pub fn break_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    match loop_count(core, args) {
+        Ok(n) => {
+            core.break_counter = n;
+            0
+        },
+        Err(status) => status,
+    }
+}
+
+pub fn continue_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    match loop_count(core, args) {
+        Ok(n) => {
+            core.continue_counter = n;
+            0
+        },
+        Err(status) => status,
+    }
+}
+
+pub fn return_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if core.return_enable {
+        core.return_flag = true;
+        match args.get(1) {
+            Some(n) => n.parse::<i32>().map(|n| n & 0xff).unwrap_or(2),
+            None => core.get_var("?").parse::<i32>().unwrap_or(0),
+        }
+    }else{
+        eprintln!("Builtin return is only enabled in a function or source");
+        1
+    }
+}
+
+pub fn jobs(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let (first, second) = core.jobs.get_top_priority_id();
+
+    for j in core.jobs.backgrounds.iter_mut() {
+        if j.async_pids.len() != 0 {
+            j.check_of_finish();
+        }
+    }
+
+    let mut long = false;
+    let mut pid_only = false;
+    let mut specs = vec![];
+    for arg in args[1..].iter() {
+        match arg.as_str() {
+            "-l" => long = true,
+            "-p" => pid_only = true,
+            "-lp" | "-pl" => { long = true; pid_only = true; },
+            a if a.starts_with('-') && a.len() > 1 => {
+                eprintln!("bash: jobs: {}: invalid option", a);
+                eprintln!("jobs: usage: jobs [-lp] [jobspec ...]");
+                return 2;
+            },
+            _ => specs.push(arg.clone()),
+        }
+    }
+
+    let mut status = 0;
+    let mut positions = vec![];
+    if specs.is_empty() {
+        positions = (0..core.jobs.backgrounds.len()).collect();
+    }
+    for spec in specs {
+        match job_position(core, &spec, "jobs") {
+            Some(pos) => positions.push(pos),
+            None => status = 1,
+        }
+    }
+
+    for pos in positions {
+        let j = &mut core.jobs.backgrounds[pos];
+        if pid_only {
+            if j.status != 'I' {
+                if let Some(pid) = j.leader_pid() {
+                    println!("{}", pid);
+                }
+            }
+            if j.status == 'D' {
+                j.status = 'I';
+            }
+        }else if long {
+            j.print_status_with_pid(first, second);
+        }else{
+            j.print_status(first, second);
+        }
+    }
+
+    status
+}
+
+pub fn shopt(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut flags = String::new();
+    let mut n = 1;
+    while n < args.len() && args[n].starts_with('-') && args[n].len() > 1 {
+        for ch in args[n][1..].chars() {
+            if ! "supq".contains(ch) {
+                eprintln!("bash: shopt: -{}: invalid option", ch);
+                eprintln!("shopt: usage: shopt [-pqsu] [-o] [optname ...]");
+                return 2;
+            }
+            flags.push(ch);
+        }
+        n += 1;
+    }
+
+    let (set, unset) = (flags.contains('s'), flags.contains('u'));
+    let (quiet, reusable) = (flags.contains('q'), flags.contains('p'));
+    if set && unset {
+        eprintln!("bash: shopt: cannot set and unset shell options simultaneously");
+        return 1;
+    }
+
+    if n == args.len() {
+        if ! quiet {
+            core.shopts.print(! unset, ! set, reusable);
+        }
+        return 0;
+    }
 
-    if args.len() < 2 {
-        for j in 0..core.jobs.backgrounds.len() {
-            if core.jobs.backgrounds[j].id == first {
-                bg_core(&mut core.jobs.backgrounds[j], first, second);
+    let mut status = 0;
+    for opt in &args[n..] {
+        if ! core.shopts.exists(opt) {
+            eprintln!("bash: shopt: {}: invalid shell option name", opt);
+            status = 1;
+        }else if set || unset {
+            core.shopts.set(opt, set);
+        }else{
+            if ! quiet {
+                core.shopts.print_opt(opt, reusable);
+            }
+            if ! core.shopts.get(opt) {
+                status = 1;
             }
         }
-        return 0;
     }
+    status
+}
 
-    args[1] = args[1].trim_start_matches("%").to_string();
-    let job_pos = if let Ok(n) = args[1].parse::<usize>() {
-        n - 1
-    }else{
-        eprintln!("bash: bg: {}: no such job", args[1]);
-        return 1;
-    };
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {},
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
 
-    let status = core.jobs.backgrounds[job_pos].status;
-    let id = core.jobs.backgrounds[job_pos].id;
-    if job_pos >= core.jobs.backgrounds.len() || status == 'D' || status == 'I' {
-        eprintln!("bash: bg: {}: no such job", id);
+pub fn getopts(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() < 3 {
+        eprintln!("getopts: usage: getopts optstring name [arg ...]");
+        return 2;
+    }
+    if ! is_name(&args[2]) {
+        eprintln!("bash: getopts: `{}': not a valid identifier", args[2]);
         return 1;
-    }else if status == 'R' {
-        eprintln!("bash: bg: job {} already in background", id);
-        return 0;
     }
 
-    bg_core(&mut core.jobs.backgrounds[job_pos], first, second);
-    0
-}
+    let optstring = args[1].clone();
+    let name = args[2].clone();
+    let words = if args.len() > 3 { args[3..].to_vec() }else{ core.args[1..].to_vec() };
+    let silent = optstring.starts_with(':');
 
-pub fn fg(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    let (first, _second) = core.jobs.get_top_priority_id();
+    let mut optind = core.get_var("OPTIND").parse::<usize>().unwrap_or(1).max(1);
+    let mut charpos = if core.getopts_pos.0 == optind { core.getopts_pos.1 }else{ 1 };
+    core.vars.remove("OPTARG");
 
-    if args.len() < 2 {
-        for j in 0..core.jobs.backgrounds.len() {
-            if core.jobs.backgrounds[j].status != 'S' && core.jobs.backgrounds[j].status != 'R' {
-                continue;
-            }
+    let word = words.get(optind-1).map(|w| w.chars().collect::<Vec<char>>());
+    if word.as_ref().is_some_and(|w| charpos >= w.len()) { // the word has been changed
+        charpos = 1;
+    }
+    let word = match word {
+        Some(w) if charpos > 1 || (w.len() > 1 && w[0] == '-' && w != ['-', '-']) => w,
+        Some(w) if w == ['-', '-'] => {
+            optind += 1;
+            core.set_var("OPTIND", &optind.to_string());
+            core.substitute_var(&name, "?");
+            return 1;
+        },
+        _ => {
+            core.set_var("OPTIND", &optind.to_string());
+            core.substitute_var(&name, "?");
+            return 1;
+        },
+    };
 
-            if core.jobs.backgrounds[j].id != first {
-                continue;
-            }
+    let opt = word[charpos];
+    charpos += 1;
+    if charpos >= word.len() {
+        optind += 1;
+        charpos = 1;
+    }
 
-            core.jobs.backgrounds[j].status = 'F';
-            for p in &core.jobs.backgrounds[j].async_pids {
-                if ! core.jobs.backgrounds[j].signaled_bg {
-                    unistd::tcsetpgrp(0, p.clone()).expect("Bash internal error (tcsetpgrp)");
-                    unistd::tcsetpgrp(1, p.clone()).expect("Bash internal error (tcsetpgrp)");
+    let mut value = opt.to_string();
+    match optstring.find(opt) {
+        Some(n) if opt != ':' => {
+            if optstring[n+1..].starts_with(':') {
+                if charpos > 1 {
+                    core.set_var("OPTARG", &word[charpos..].iter().collect::<String>());
+                    optind += 1;
+                    charpos = 1;
+                }else if let Some(arg) = words.get(optind-1) {
+                    core.set_var("OPTARG", arg);
+                    optind += 1;
+                }else if silent {
+                    value = ":".to_string();
+                    core.set_var("OPTARG", &opt.to_string());
+                }else{
+                    eprintln!("bash: option requires an argument -- {}", opt);
+                    value = "?".to_string();
                 }
-                signal::kill(*p, Signal::SIGCONT).unwrap();
             }
-            core.jobs.foreground = core.jobs.backgrounds[j].clone();
-            core.jobs.wait_bg_job_at_foreground(core.jobs.backgrounds[j].id);
-
-            if ! core.jobs.backgrounds[j].signaled_bg {
-                unistd::tcsetpgrp(0, unistd::getpid()).expect("Bash internal error (tcsetpgrp)");
-                unistd::tcsetpgrp(1, unistd::getpid()).expect("Bash internal error (tcsetpgrp)");
+        },
+        _ => {
+            value = "?".to_string();
+            if silent {
+                core.set_var("OPTARG", &opt.to_string());
+            }else{
+                eprintln!("bash: illegal option -- {}", opt);
             }
-        }
-        return 0;
+        },
     }
-    0
-}
-
-pub fn shift(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    let num = if args.len() == 2 {
-        if let Ok(n) = args[1].parse::<usize>() {
-            n
-        }else{
-            eprintln!("bash: shift: {}: numeric wordument required", args[1]);
-            return 1;
-        }
-    }else if args.len() == 1 {
-        1
-    }else{
-        eprintln!("bash: shift: too many worduments");
-        return 1;
-    };
 
-    if core.args.len() < num+1 {
+    core.getopts_pos = (optind, charpos);
+    core.set_var("OPTIND", &optind.to_string());
+    if ! core.substitute_var(&name, &value) {
         return 1;
     }
-
-    for _ in 0..num {
-        core.args.remove(1);
-    }
     0
 }
 
-pub fn builtin(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() > 0 {
-        args.remove(0);
-    }
-    if args.len() < 1 {
+pub fn export(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() <= 1 || (args.len() == 2 && args[1] == "-p") {
+        let mut vars = env::vars().collect::<Vec<(String, String)>>();
+        vars.sort();
+        for (k, v) in vars {
+            println!("declare -x {}=\"{}\"", k, v.replace("\\", "\\\\").replace("\"", "\\\""));
+        }
         return 0;
     }
 
-    if let Some(func) = core.get_builtin(&args[0]) {
-        func(core, args)
-    }else{
-        eprintln!("bash: builtin: {}: not a shell builtin", args[0]);
-        1
+    let mut status = 0;
+    for arg in args[1..].iter() {
+        let (key, value) = match arg.find('=') {
+            Some(n) => (&arg[..n], Some(arg[n+1..].to_string())),
+            None => (&arg[..], None),
+        };
+
+        if ! is_name(key) {
+            eprintln!("bash: export: `{}': not a valid identifier", arg);
+            status = 1;
+            continue;
+        }
+
+        if let Some(v) = value {
+            if ! core.substitute_var(key, &v) {
+                status = 1;
+                continue;
+            }
+        }
+
+        if let Some(v) = core.vars.remove(key) {
+            env::set_var(key, v);
+        }
     }
+
+    status
 }
 
-pub fn cd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() == 0 {
-        eprintln!("Bug of this shell");
-        return 1;
-    }
-    if args.len() > 2 {
-        eprintln!("{}", "bash: cd: too many arguments");
-        return 1;
+const KEYWORDS: [&str; 19] = ["!", "case", "do", "done", "elif", "else", "esac", "fi", "for",
+                              "function", "if", "in", "select", "then", "time", "until", "while",
+                              "{", "}"];
+
+fn executable_path(name: &str) -> Option<String> {
+    let path = if name.contains('/') { name.to_string() }else{ get_fullpath(&name.to_string()) };
+    match fs::metadata(&path) {
+        Ok(m) if m.is_file() && m.permissions().mode() & 0o111 != 0 => Some(path),
+        _ => None,
     }
+}
 
+pub fn hash(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    core.check_command_hash();
 
-    if args.len() == 1 { //only "cd"
-        let var = env::var("HOME").expect("HOME is not defined");
-        args.push(var);
-    }else if args.len() == 2 && args[1] == "-" { // cd -
-        if let Some(old) = core.vars.get("OLDPWD") {
-            args[1] = old.to_string();
+    let mut names = args[1..].to_vec();
+    let mut opts = String::new();
+    while ! names.is_empty() && names[0].starts_with('-') && names[0].len() > 1 {
+        let opt = names.remove(0);
+        if opt == "--" {
+            break;
         }
-    };
+        for c in opt[1..].chars() {
+            if ! "dlrt".contains(c) {
+                eprintln!("bash: hash: -{}: invalid option", c);
+                eprintln!("hash: usage: hash [-lr] [-p pathname] [-dt] [name ...]");
+                return 2;
+            }
+            opts.push(c);
+        }
+    }
 
-    if let Ok(old) = env::current_dir() {
-        core.set_var("OLDPWD", &old.display().to_string());
-    };
+    if opts.contains('r') {
+        core.command_hash.clear();
+    }
 
-    let path = Path::new(&args[1]);
-    if env::set_current_dir(&path).is_ok() {
-        if let Ok(full) = fs::canonicalize(path) {
-            core.set_var("PWD", &full.display().to_string());
+    if names.is_empty() {
+        if opts.contains('r') {
+            return 0;
+        }
+        if core.command_hash.is_empty() && ! opts.contains('l') {
+            println!("hash: hash table empty");
+            return 0;
         }
-        0
-    }else{
-        eprintln!("Not exist directory");
-        1
-    }
-}
 
-pub fn alias(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() <= 1 {
-        for (k, v) in core.aliases.iter() {
-            println!("alias {}='{}'", k, v);
+        let mut entries = core.command_hash.iter().collect::<Vec<(&String, &(String, u32))>>();
+        entries.sort();
+        if ! opts.contains('l') {
+            println!("hits\tcommand");
+        }
+        for (name, (path, hits)) in entries {
+            match opts.contains('l') {
+                true  => println!("builtin hash -p {} {}", path, name),
+                false => println!("{:4}\t{}", hits, path),
+            }
         }
         return 0;
     }
 
-    if let Some(com) = core.aliases.get(&args[1]) {
-        println!("alias {}='{}'", &args[1], com);
-        return 0;
-    }
+    let mut status = 0;
+    for name in names {
+        if opts.contains('d') {
+            if core.command_hash.remove(&name).is_none() {
+                eprintln!("bash: hash: {}: not found", name);
+                status = 1;
+            }
+            continue;
+        }
 
-    let elems = args[1].split('=').collect::<Vec<&str>>();
-    if elems.len() < 2 {
-        eprintln!("bash: alias: {} not found", &args[1]);
-        return 1;
-    }
+        if ! opts.contains('t') && ! core.command_hash.contains_key(&name)
+        && ! core.builtins.contains_key(&name) {
+            let path = get_fullpath(&name);
+            if path.is_empty() {
+                eprintln!("bash: hash: {}: not found", name);
+                status = 1;
+                continue;
+            }
+            core.command_hash.insert(name.clone(), (path, 0));
+        }
 
-    core.aliases.insert(elems[0].to_string(), elems[1..].join("="));
-    0
+        if opts.contains('t') {
+            match core.command_hash.get(&name) {
+                Some((path, _)) => println!("{}", path),
+                None => {
+                    eprintln!("bash: hash: {}: not found", name);
+                    status = 1;
+                },
+            }
+        }
+    }
+    status
 }
 
-pub fn set(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() == 1 {
-       for k in core.vars.keys() {
-           println!("{}={}", k, core.vars[k]);
-       }
-       return 0;
+pub fn umask(_core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut opts = String::new();
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with('-') && args[pos].len() > 1 {
+        pos += 1;
+        if args[pos-1] == "--" {
+            break;
+        }
+        for c in args[pos-1][1..].chars() {
+            if ! "pS".contains(c) {
+                eprintln!("bash: umask: -{}: invalid option", c);
+                eprintln!("umask: usage: umask [-p] [-S] [mode]");
+                return 2;
+            }
+            opts.push(c);
+        }
+    }
+    let symbolic = opts.contains('S');
+    let reusable = opts.contains('p');
+
+    if pos < args.len() {
+        let mask = match u32::from_str_radix(&args[pos], 8) {
+            Ok(n) if n <= 0o777 => n,
+            _ => {
+                eprintln!("bash: umask: {}: octal number out of range", args[pos]);
+                return 1;
+            },
+        };
+        stat::umask(Mode::from_bits_truncate(mask));
+        if ! symbolic {
+            return 0;
+        }
     }
 
-    core.args.clear();
+    /* the mask can be read only by setting it */
+    let mask = stat::umask(Mode::empty());
+    stat::umask(mask);
+    let mask = mask.bits() as u32;
 
-    for a in args {
-        core.args.push(a.to_string());
+    let prefix = if reusable { "umask " }else{ "" };
+    if ! symbolic {
+        println!("{}{:04o}", prefix, mask);
+        return 0;
     }
 
+    let perms = ["u", "g", "o"].iter().enumerate().map(|(i, who)| {
+        let bits = ! (mask >> (6 - i*3)) & 0o7;
+        let rwx = "rwx".chars().enumerate()
+                  .filter(|(j, _)| bits & (0o4 >> j) != 0)
+                  .map(|(_, c)| c).collect::<String>();
+        format!("{}={}", who, rwx)
+    }).collect::<Vec<String>>();
+    let prefix = if reusable { "umask -S " }else{ "" };
+    println!("{}{}", prefix, perms.join(","));
     0
 }
 
-pub fn read(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    let mut line = String::new();
-    if io::stdin().read_line(&mut line).expect("Failed to read line") == 0 {
-        return 1;
+/* command name args... is handled in SimpleCommand. This function works for -v and -V. */
+pub fn command(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut names = args[1..].to_vec();
+    let mut opts = String::new();
+    while ! names.is_empty() && names[0].starts_with('-') && names[0].len() > 1 {
+        let opt = names.remove(0);
+        if opt == "--" {
+            break;
+        }
+        for c in opt[1..].chars() {
+            if ! "pvV".contains(c) {
+                eprintln!("bash: command: -{}: invalid option", c);
+                eprintln!("command: usage: command [-pVv] command [arg ...]");
+                return 2;
+            }
+            opts.push(c);
+        }
     }
 
-    let wordnum = args.len() - 1;
-    if wordnum < 1 {
+    if names.is_empty() {
         return 0;
     }
 
-    let mut token = line.trim_end().split(" ").map(|s| s.to_string()).collect::<Vec<String>>();
-
-    let last = if wordnum < token.len() {
-        token[wordnum-1..].join(" ")
-    }else if wordnum == token.len() {
-        token[wordnum-1].clone()
-    }else{
-        "".to_string()
-    };
+    if opts.contains('V') {
+        let mut type_args = vec!("type".to_string());
+        type_args.extend(names);
+        return type_(core, &mut type_args);
+    }
 
-    if token.len() >= wordnum-1 {
-        token.insert(wordnum-1, last.clone());
-    }else{
-        while token.len() < wordnum {
-            token.push(last.clone());
-        }
+    if ! opts.contains('v') {
+        return match core.get_builtin(&names[0]) {
+            Some(func) => func(core, &mut names),
+            None => {
+                eprintln!("bash: command: {}: not a shell builtin", names[0]);
+                1
+            },
+        };
     }
 
-    for (i, a) in args.iter().enumerate() {
-        if i == 0 {
+    let mut found = false; // the status is 0 if any one of the names is found
+    for name in names {
+        if let Some(com) = core.aliases.get(&name) {
+            println!("alias {}='{}'", name, com);
+        }else if KEYWORDS.contains(&name.as_str()) || core.functions.contains_key(&name)
+        || core.builtins.contains_key(&name) {
+            println!("{}", name);
+        }else if let Some(path) = executable_path(&name) {
+            println!("{}", path);
+        }else{
             continue;
         }
-
-        core.set_var(a, &token[i-1]);
+        found = true;
     }
-
-    0
+    if found {0}else{1}
 }
 
-pub fn source(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() < 2 {
-        eprintln!("usage: source filename");
-        return 1;
+pub fn type_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut names = args[1..].to_vec();
+    let category_only = ! names.is_empty() && names[0] == "-t";
+    if category_only {
+        names.remove(0);
     }
 
-    if args.len() > 1 {
-        match fs::read_to_string(&args[1]) {
-            Ok(source) => {
-                let mut feeder = Feeder::new_from(source);
-                if let Some(mut script) = Script::parse(&mut feeder, core) {
-                    core.return_enable = true;
-                    script.exec(core);
-                    core.return_enable = false;
-                }else{
-                    return 1;
-                };
-            },
-            _ => eprintln!("Cannot read the source file: {}", &args[1]),
+    let mut status = 0;
+    for name in names {
+        if let Some(com) = core.aliases.get(&name) {
+            match category_only {
+                true  => println!("alias"),
+                false => println!("{} is aliased to `{}'", name, com),
+            }
+        }else if KEYWORDS.contains(&name.as_str()) {
+            match category_only {
+                true  => println!("keyword"),
+                false => println!("{} is a shell keyword", name),
+            }
+        }else if let Some(body) = core.functions.get(&name) {
+            match category_only {
+                true  => println!("function"),
+                false => println!("{} is a function\n{} () \n{}", name, name, body),
+            }
+        }else if core.builtins.contains_key(&name) {
+            match category_only {
+                true  => println!("builtin"),
+                false => println!("{} is a shell builtin", name),
+            }
+        }else if let Some(path) = executable_path(&name) {
+            match category_only {
+                true  => println!("file"),
+                false => println!("{} is {}", name, path),
+            }
+        }else{
+            if ! category_only {
+                eprintln!("bash: type: {}: not found", name);
+            }
+            status = 1;
         }
     }
-    0
+    status
 }
 
-pub fn return_(core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
-    if core.return_enable {
-        core.return_flag = true;
-        0
+fn declare_print(core: &ShellCore, name: &str) -> bool {
+    let mut attrs = core.var_attributes.get(name).cloned().unwrap_or_default();
+    if ! core.vars.contains_key(name) && env::var(name).is_ok() && ! attrs.contains('x') {
+        attrs.push('x');
+    }
+    if core.assoc_arrays.contains_key(name) && ! attrs.contains('A') {
+        attrs.push('a');
+    }
+    let attrs = "aAirx".chars().filter(|c| attrs.contains(*c)).collect::<String>();
+    let attrs = if attrs.is_empty() { "-".to_string() }else{ attrs };
+
+    if core.assoc_arrays.contains_key(name) {
+        let elems = core.get_array_keys(name).iter().map(|k| {
+            let v = core.get_array_elem(name, k).unwrap_or_default();
+            format!("[{}]=\"{}\"", k, v.replace("\\", "\\\\").replace("\"", "\\\""))
+        }).collect::<Vec<String>>().join(" ");
+        let tail = if attrs.contains('A') { " " }else{ "" };
+        println!("declare -{} {}=({}{})", attrs, name, elems, tail);
+    }else if core.has_var(name) {
+        let value = core.get_var(name).replace("\\", "\\\\").replace("\"", "\\\"");
+        println!("declare -{} {}=\"{}\"", attrs, name, value);
+    }else if core.var_attributes.contains_key(name) {
+        println!("declare -{} {}", attrs, name);
     }else{
-        eprintln!("Builtin return is only enabled in a function or source");
-        1
+        return false;
     }
+    true
 }
 
-pub fn jobs(core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
-    let (first, second) = core.jobs.get_top_priority_id();
+fn all_var_names(core: &ShellCore) -> Vec<String> {
+    let mut names = core.vars.keys().filter(|k| is_name(k)).cloned().collect::<Vec<String>>();
+    names.extend(env::vars().map(|(k, _)| k));
+    names.extend(core.var_attributes.keys().cloned());
+    names.extend(core.assoc_arrays.keys().cloned());
+    names.sort();
+    names.dedup();
+    names
+}
 
-    for j in core.jobs.backgrounds.iter_mut() {
-        if j.async_pids.len() != 0 {
-            j.check_of_finish();
+pub fn declare(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut add = String::new();
+    let mut remove = String::new();
+    let mut print = false;
+    let mut n = 1;
+    while n < args.len() && (args[n].starts_with('-') || args[n].starts_with('+')) && args[n].len() > 1 {
+        let plus = args[n].starts_with('+');
+        for c in args[n][1..].chars() {
+            match (c, plus) {
+                ('p', false) => print = true,
+                ('i', _) | ('r', false) | ('x', _) | ('a', false) | ('A', false)
+                    => if plus { remove.push(c) }else{ add.push(c) },
+                _ => {
+                    eprintln!("bash: declare: {}{}: invalid option", if plus {'+'}else{'-'}, c);
+                    eprintln!("declare: usage: declare [-aAirxp] [name[=value] ...]");
+                    return 2;
+                },
+            }
         }
+        n += 1;
     }
 
-    for j in core.jobs.backgrounds.iter_mut() {
-        j.print_status(first, second);
+    if n == args.len() && (print || (add.is_empty() && remove.is_empty())) {
+        for name in all_var_names(core) {
+            if print {
+                declare_print(core, &name);
+            }else if core.has_var(&name) {
+                let value = core.get_var(&name);
+                if value.chars().all(|c| c.is_ascii_alphanumeric() || "_./:=@%+,-".contains(c)) {
+                    println!("{}={}", name, value);
+                }else{
+                    println!("{}='{}'", name, value.replace("'", "'\\''"));
+                }
+            }
+        }
+        return 0;
     }
 
-    0
-}
+    let mut status = 0;
+    for arg in args[n..].iter() {
+        if print {
+            if ! declare_print(core, arg) {
+                eprintln!("bash: declare: {}: not found", arg);
+                status = 1;
+            }
+            continue;
+        }
 
-pub fn shopt(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() == 1 {
-        core.shopts.print(true, true);
-        return 0;
-    }
+        let (name, value) = match arg.find('=') {
+            Some(p) => (&arg[..p], Some(&arg[p+1..])),
+            None    => (&arg[..], None),
+        };
 
-    if args.len() == 2 && args[1] == "-s" {
-        core.shopts.print(true, false);
-        return 0;
+        if ! is_name(name) {
+            eprintln!("bash: declare: `{}': not a valid identifier", arg);
+            status = 1;
+            continue;
+        }
+        if core.has_attribute(name, 'r') && (value.is_some() || ! remove.is_empty()) {
+            eprintln!("bash: declare: {}: readonly variable", name);
+            status = 1;
+            continue;
+        }
+
+        let mut attrs = core.var_attributes.get(name).cloned().unwrap_or_default();
+        attrs.retain(|c| ! remove.contains(c));
+        for c in add.chars().filter(|c| *c != 'r' && *c != 'a') { // -a is known from assoc_arrays
+            if ! attrs.contains(c) {
+                attrs.push(c);
+            }
+        }
+
+        if remove.contains('x') && env::var(name).is_ok() {
+            let v = core.get_var(name);
+            env::remove_var(name);
+            core.set_var(name, &v);
+        }
+        if add.contains('x') && core.vars.contains_key(name) {
+            let v = core.get_var(name);
+            core.vars.remove(name);
+            env::set_var(name, v);
+        }
+
+        if (add.contains('a') || add.contains('A')) && ! core.assoc_arrays.contains_key(name) {
+            let mut map = HashMap::new();
+            if let Some(v) = core.vars.remove(name) {
+                map.insert("0".to_string(), v);
+            }
+            core.assoc_arrays.insert(name.to_string(), map);
+        }
+
+        core.var_attributes.insert(name.to_string(), attrs.clone());
+        if let Some(v) = value {
+            core.substitute_var(name, v);
+        }
+
+        if add.contains('r') {
+            attrs.push('r');
+            core.var_attributes.insert(name.to_string(), attrs);
+        }
     }
-    if args.len() == 2 && args[1] == "-u" {
-        core.shopts.print(false, true);
-        return 0;
+
+    status
+}
+
+pub fn local(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if core.local_scopes.is_empty() {
+        eprintln!("bash: local: can only be used in a function");
+        return 1;
     }
 
-    if args.len() > 2 && args[1] == "-s" {
-        for opt in &mut args[2..] {
-            core.shopts.set(opt, true);
+    let names = args[1..].iter().filter(|a| ! a.starts_with('-') && ! a.starts_with('+'))
+                .map(|a| a.split('=').next().unwrap().to_string())
+                .collect::<Vec<String>>();
+
+    if names.is_empty() {
+        let locals = core.local_scopes.last().unwrap().iter()
+                     .map(|v| v.name.clone()).collect::<Vec<String>>();
+        for name in locals {
+            declare_print(core, &name);
         }
         return 0;
     }
 
-    if args.len() > 2 && args[1] == "-u" {
-        for opt in &mut args[2..] {
-            core.shopts.set(opt, false);
+    for name in names.iter() {
+        if is_name(name) && ! core.has_attribute(name, 'r') && core.make_local(name) {
+            let exported = core.vars.remove(name).is_none() && env::var(name).is_ok();
+            env::remove_var(name);
+            core.assoc_arrays.remove(name);
+            match exported {
+                true  => core.var_attributes.insert(name.to_string(), "x".to_string()),
+                false => core.var_attributes.remove(name),
+            };
         }
-        return 0;
     }
 
-    0
+    let mut declare_args = args.clone();
+    declare_args[0] = "declare".to_string();
+    declare(core, &mut declare_args)
 }
 
-pub fn export(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() <= 1 { // TODO: it should output all env vars. 
-        return 1;
+pub fn unset(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut function = false;
+    let mut variable = false;
+    let mut n = 1;
+    while n < args.len() && args[n].starts_with('-') && args[n].len() > 1 {
+        for c in args[n][1..].chars() {
+            match c {
+                'f' => function = true,
+                'v' => variable = true,
+                _ => {
+                    eprintln!("bash: unset: -{}: invalid option", c);
+                    return 2;
+                },
+            }
+        }
+        n += 1;
     }
 
-    let key_value = args[1].split("=").map(|s| s.to_string()).collect::<Vec<String>>();
+    let mut status = 0;
+    for name in args[n..].iter() {
+        if function && ! variable {
+            core.functions.remove(name);
+            continue;
+        }
 
-    let (key, value) = if key_value.len() == 0 {
-        return 1;
-    }else if key_value.len() == 1 {
-        (&key_value[0], "".to_string())
-    }else{
-        (&key_value[0], key_value[1..].join("="))
-    };
+        if core.has_attribute(name, 'r') {
+            eprintln!("bash: unset: {}: cannot unset: readonly variable", name);
+            status = 1;
+            continue;
+        }
+        core.var_attributes.remove(name);
 
-    if ! core.vars.contains_key(key) {
-        env::set_var(key, value);
-        return 0;
-    }
+        if let (Some(n), true) = (name.find('['), name.ends_with(']')) {
+            if let Some(map) = core.assoc_arrays.get_mut(&name[..n]) {
+                map.remove(&name[n+1..name.len()-1]);
+            }
+            continue;
+        }
+        if core.assoc_arrays.remove(name).is_some() {
+            continue;
+        }
 
-    let value = core.get_var(&key);
-    env::set_var(key, value);
-    core.vars.remove(key);
+        if core.vars.remove(name).is_some() || env::var(name).is_ok() {
+            env::remove_var(name);
+        }else if ! variable {
+            core.functions.remove(name);
+        }
+    }
 
-    0
+    status
 }
 
 pub fn eval(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
@@ -452,6 +2403,10 @@ pub fn eval(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     let mut feeder = Feeder::new_from(text);
         eprintln!("{:?}", feeder._text());
     if let Some(mut script) = Script::parse(&mut feeder, core) {
+        if core.interrupted { // a syntax error in the text
+            core.interrupted = false;
+            return 2;
+        }
         script.exec(core);
     }
 
@@ -459,7 +2414,7 @@ pub fn eval(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
 }
 
 pub fn glob_test(_core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if glob_match(&args[1].to_string(), &args[2].to_string()){
+    if glob_match(&args[1], &args[2]){
         eprintln!("MATCH!");
         0
     }else{
@@ -468,20 +2423,63 @@ pub fn glob_test(_core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     }
 }
 
-pub fn wait(core: &mut ShellCore, _args: &mut Vec<String>) -> i32 {
+fn wait_job(core: &mut ShellCore, pos: usize, first: usize, second: usize) -> i32 {
+    let job = &mut core.jobs.backgrounds[pos];
+    if job.status == 'R' || job.status == 'F' {
+        job.status = 'F';
+        let id = job.id;
+        core.jobs.wait_bg_job_at_foreground(id);
+    }
+
+    let job = &mut core.jobs.backgrounds[pos];
+    if job.status != 'I' {
+        job.status = 'D';
+        eprintln!("{}", &job.status_string(first, second));
+        job.status = 'I';
+    }
+    job.exit_status
+}
+
+pub fn wait(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     let (first, second) = core.jobs.get_top_priority_id();
 
-    for i in 0..core.jobs.backgrounds.len() {
-        if core.jobs.backgrounds[i].status != 'R' && core.jobs.backgrounds[i].status != 'F' { 
-            continue;
+    if args.len() < 2 {
+        for i in 0..core.jobs.backgrounds.len() {
+            if core.jobs.backgrounds[i].status == 'R' || core.jobs.backgrounds[i].status == 'F' { 
+                wait_job(core, i, first, second);
+            }
         }
-        core.jobs.backgrounds[i].status = 'F';
-        let id = core.jobs.backgrounds[i].id;
-        core.jobs.wait_bg_job_at_foreground(id);
-        core.jobs.backgrounds[i].status = 'D';
-        eprintln!("{}", &core.jobs.backgrounds[i].status_string(first, second));
-        core.jobs.backgrounds[i].status = 'I';
+        core.jobs.reaped.clear(); // bash also discards the statuses here
+        return 0;
     }
 
-    0
+    let mut status = 0;
+    for arg in args[1..].iter() {
+        let pos = if arg.starts_with('%') {
+            job_position(core, arg, "wait")
+        }else if let Ok(n) = arg.parse::<i32>() {
+            let pid = Pid::from_raw(n);
+            let found = core.jobs.backgrounds.iter()
+                        .position(|j| j.status != 'I' && j.pids.contains(&pid));
+            if let Some(n) = core.jobs.reaped.iter().position(|r| r.0 == pid).filter(|_| found.is_none()) {
+                status = core.jobs.reaped.remove(n).1;
+                continue;
+            }
+            if found.is_none() {
+                eprintln!("bash: wait: pid {} is not a child of this shell", n);
+            }
+            found
+        }else{
+            eprintln!("bash: wait: `{}': not a pid or valid job spec", arg);
+            status = 1;
+            continue;
+        };
+
+        status = match pos {
+            Some(pos) => wait_job(core, pos, first, second),
+            None      => 127,
+        };
+    }
+
+    status
 }
@@ -15,6 +15,9 @@ pub struct Job {
     pub status: char, // S: stopped, R: running, D: done, I: invalid, F: fg
     pub id: usize,
     pub priority: u32,
+    pub exit_status: i32,
+    pub reaped: Vec<(Pid, i32)>, // processes already waited with WNOHANG and their statuses
+    pub no_hup: bool, // disown -h: SIGHUP is not sent at exit
 }
 
 impl Job {
@@ -34,6 +37,9 @@ impl Job {
             status: if is_bg {'R'}else{'F'},
             id: 0,
             priority: 0, 
+            exit_status: 0,
+            reaped: vec![],
+            no_hup: false,
         }
     }
 
@@ -42,12 +48,25 @@ impl Job {
             return true; 
         }
 
+        for p in self.pids.clone() {
+            if self.async_pids.contains(&p) || self.is_reaped(p) {
+                continue;
+            }
+            if let Some(status) = proc::check_async_process(p) {
+                self.reaped.push((p, status));
+            }
+        }
+
         let mut remain = vec![];
 
         while self.async_pids.len() > 0 {
             let p = self.async_pids.pop().unwrap();
-            if ! proc::check_async_process(p){
-                remain.push(p);
+            match proc::check_async_process(p) {
+                Some(status) => {
+                    self.exit_status = status;
+                    self.reaped.push((p, status));
+                },
+                None         => remain.push(p),
             }
         }
 
@@ -60,6 +79,18 @@ impl Job {
         self.async_pids.len() == 0 // true if finished
     }
 
+    /* the processes which haven't been waited yet */
+    pub fn unreaped_pids(&self) -> Vec<Pid> {
+        let mut pids = self.pids.clone();
+        pids.extend(self.async_pids.iter().filter(|p| ! self.pids.contains(p)));
+        pids.retain(|p| ! self.is_reaped(*p));
+        pids
+    }
+
+    pub fn is_reaped(&self, pid: Pid) -> bool {
+        self.reaped.iter().any(|r| r.0 == pid)
+    }
+
     pub fn status_string(&self, first: usize, second: usize) -> String {
         let mark = if self.id == first {
             '+'
@@ -76,7 +107,30 @@ impl Job {
             _   => "ERROR",
         };
 
-        format!("[{}]{} {}\t\t{}", &self.id, mark, status, &self.text.trim_end())
+        format!("[{}]{} {}\t\t{}", &self.id, mark, status, &self.text.trim())
+    }
+
+    pub fn leader_pid(&self) -> Option<Pid> {
+        match self.pids.first() {
+            Some(pid) => Some(*pid),
+            None      => self.async_pids.first().cloned(),
+        }
+    }
+
+    pub fn print_status_with_pid(&mut self, first: usize, second: usize) {
+        if self.status == 'I' {
+            return;
+        }
+
+        self.change_status_with_file();
+        let s = self.status_string(first, second);
+        match (self.leader_pid(), s.find(' ')) {
+            (Some(pid), Some(n)) => println!("{} {}{}", &s[..n], pid, &s[n..]),
+            _ => println!("{}", s),
+        }
+        if self.status == 'D' {
+            self.status = 'I';
+        }
     }
 
     pub fn print_status(&mut self, first: usize, second: usize) {
@@ -5,6 +5,8 @@ use nix::unistd::Pid;
 use super::job::Job;
 use crate::elements::command::Command;
 use super::proc;
+use nix::sys::signal;
+use nix::sys::signal::Signal;
 //use nix::unistd;
 
 //[1]+  Running                 sleep 5 &
@@ -12,6 +14,8 @@ use super::proc;
 pub struct Jobs {
     pub foreground: Job,
     pub backgrounds: Vec<Job>, //0: current job, 1~: background jobs
+    pub reaped: Vec<(Pid, i32)>, // processes of the reported jobs for wait
+    pub disowned: Vec<Pid>, // processes of the jobs removed by disown. They are reaped silently.
 }
 
 impl Jobs {
@@ -19,6 +23,8 @@ impl Jobs {
         Jobs {
             foreground: Job::new(&"".to_string(), &vec![], false),
             backgrounds: vec![],
+            reaped: vec![],
+            disowned: vec![],
         }
     }
 
@@ -67,35 +73,45 @@ impl Jobs {
 
     pub fn set_fg_job(&mut self, text: &String, commands: &Vec<Box<dyn Command>>) {
         self.foreground = Job::new(text, commands, false);
+        self.forget_reaped(&self.foreground.pids.clone());
     }
 
-    pub fn add_bg_job(&mut self, text: &String, commands: &Vec<Box<dyn Command>>) {
+    /* the statuses of old processes are dropped when their pids are reused */
+    fn forget_reaped(&mut self, pids: &[Pid]) {
+        self.reaped.retain(|r| ! pids.contains(&r.0));
+    }
+
+    pub fn add_bg_job(&mut self, text: &String, commands: &Vec<Box<dyn Command>>) -> Pid {
         self.reduce_priority();
 
         let mut bgjob = Job::new(text, commands, true);
         bgjob.id = self.backgrounds.len() + 1;
         bgjob.priority = 10000;
 
-        if let Some(pid) = commands.last().unwrap().get_pid() {
-            eprintln!("[{}] {}", bgjob.id, pid);
-            bgjob.async_pids.push(pid);
-        }else{
-            panic!("Bash internal error (before running background process)");
-        }
+        let pid = match commands.last().unwrap().get_pid() {
+            Some(pid) => pid,
+            None => panic!("Bash internal error (before running background process)"),
+        };
+        eprintln!("[{}] {}", bgjob.id, pid);
+        bgjob.async_pids.push(pid);
 
+        self.forget_reaped(&bgjob.pids);
         self.add_job(bgjob);
-        return;
+        pid
     }
 
-    pub fn wait_fg_job(&mut self) -> Vec<i32> {
+    /* the exit statuses and whether one of the processes was killed by SIGINT */
+    pub fn wait_fg_job(&mut self) -> (Vec<i32>, bool) {
         let mut pipestatus = vec![];
+        let mut sigint = false;
         for p in self.foreground.pids.clone() {
-            let exit_status = self.wait_process(p);
+            let (exit_status, killed) = self.wait_process(p);
             pipestatus.push(exit_status);
+            sigint |= killed;
         }
 
         self.foreground.status = 'D';
-        pipestatus
+        (pipestatus, sigint)
     }
 
     pub fn wait_bg_job_at_foreground(&mut self, job_no: usize) -> Vec<i32> {
@@ -110,26 +126,59 @@ impl Jobs {
 
         let mut pipestatus = vec![];
         for p in self.backgrounds[pos].pids.clone() {
-            let exit_status = self.wait_process(p);
+            let exit_status = match self.backgrounds[pos].reaped.iter().find(|r| r.0 == p) {
+                Some(r) => r.1,
+                None    => self.wait_process(p).0,
+            };
             pipestatus.push(exit_status);
         }
 
         self.backgrounds[pos].status = 'D';
+        if let Some(status) = pipestatus.last() {
+            self.backgrounds[pos].exit_status = *status;
+        }
         pipestatus
     }
 
-    pub fn wait_process(&mut self, child: Pid) -> i32 {
-        let exit_status = proc::wait_process(child);
+    pub fn wait_process(&mut self, child: Pid) -> (i32, bool) {
+        let (exit_status, sigint) = proc::wait_process(child);
         if exit_status == 147 || exit_status == 148 { //SIGTSTP or SIGSTOP
             self.to_background(child);
         }
-        exit_status
+        (exit_status, sigint)
     } 
 
     pub fn add_job(&mut self, added: Job) {
         self.backgrounds.push(added);
     }
 
+    /* the job is invalidated so that it is never listed or reported */
+    pub fn disown(&mut self, pos: usize) {
+        let job = &mut self.backgrounds[pos];
+        self.disowned.extend(job.unreaped_pids());
+        job.status = 'I';
+    }
+
+    /* SIGHUP to the jobs at exit except the ones of disown -h.
+       Stopped jobs are also continued so that they receive it. */
+    pub fn hangup(&self) {
+        for job in self.backgrounds.iter().filter(|j| j.status == 'R' || j.status == 'S') {
+            if job.no_hup {
+                continue;
+            }
+            for pid in job.unreaped_pids() {
+                let _ = signal::kill(pid, Signal::SIGHUP);
+                if job.status == 'S' {
+                    let _ = signal::kill(pid, Signal::SIGCONT);
+                }
+            }
+        }
+    }
+
+    pub fn reap_disowned(&mut self) {
+        self.disowned.retain(|p| proc::check_async_process(*p).is_none());
+    }
+
     pub fn remove_finished_jobs(&mut self) {
         while self.backgrounds.len() > 0 {
             let job = self.backgrounds.pop().unwrap();
@@ -7,15 +7,22 @@ use std::fs;
 
 use nix::sys::signal;
 use nix::sys::signal::{Signal, SigHandler};
+use nix::libc::c_int;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-pub fn wait_process(child: Pid) -> i32 {
+/* bit n is set when the signal n is caught by a trap */
+static CAUGHT_SIGNALS: AtomicU64 = AtomicU64::new(0);
+
+/* the exit status and whether the process was killed by SIGINT */
+pub fn wait_process(child: Pid) -> (i32, bool) {
     let exit_status = match waitpid(child, Some(WaitPidFlag::WUNTRACED)) {
         Ok(WaitStatus::Exited(_pid, status)) => {
             status
         },
-        Ok(WaitStatus::Signaled(pid, signal, _coredump)) => {
-            eprintln!("Pid: {:?}, Signal: {:?}", pid, signal);
-            128+signal as i32
+        Ok(WaitStatus::Signaled(_pid, signal, _coredump)) => {
+            return (128+signal as i32, signal == Signal::SIGINT);
         },
         Ok(WaitStatus::Stopped(_pid, signal)) => {
             128+signal as i32
@@ -29,14 +36,17 @@ pub fn wait_process(child: Pid) -> i32 {
         },
     };
 
-    exit_status
+    (exit_status, false)
 } 
 
-pub fn check_async_process(pid: Pid) -> bool {
+/* None if the process is still alive, the exit status otherwise */
+pub fn check_async_process(pid: Pid) -> Option<i32> {
     match waitpid(pid, Some(WaitPidFlag::WNOHANG)) {
-        Ok(WaitStatus::StillAlive) => false,
-        Ok(_)                      => true, 
-        _                          => {eprintln!("ERROR");true},
+        Ok(WaitStatus::StillAlive)            => None,
+        Ok(WaitStatus::Exited(_, status))     => Some(status),
+        Ok(WaitStatus::Signaled(_, sig, _))   => Some(128+sig as i32),
+        Ok(_)                                 => Some(0), 
+        _                                     => {eprintln!("ERROR");Some(1)},
     }
 }
 
@@ -73,3 +83,71 @@ pub fn ignore_signals() {
         signal::signal(Signal::SIGTSTP, SigHandler::SigIgn).unwrap();
     }
 }
+
+/* the shell ignores these signals when no trap is set */
+fn is_ignored_by_shell(sig: Signal) -> bool {
+    [Signal::SIGINT, Signal::SIGQUIT, Signal::SIGTTIN,
+     Signal::SIGTTOU, Signal::SIGTSTP].contains(&sig)
+}
+
+extern "C" fn catch_signal(sig: c_int) {
+    CAUGHT_SIGNALS.fetch_or(1 << sig, Ordering::SeqCst);
+}
+
+pub fn set_trap(sig: Signal) {
+    unsafe { let _ = signal::signal(sig, SigHandler::Handler(catch_signal)); }
+}
+
+pub fn ignore_signal(sig: Signal) {
+    unsafe { let _ = signal::signal(sig, SigHandler::SigIgn); }
+}
+
+pub fn reset_signal(sig: Signal) {
+    if is_ignored_by_shell(sig) {
+        ignore_signal(sig);
+    }else{
+        unsafe { let _ = signal::signal(sig, SigHandler::SigDfl); }
+    }
+}
+
+pub fn take_caught_signals() -> Vec<i32> {
+    let bits = CAUGHT_SIGNALS.swap(0, Ordering::SeqCst);
+    (1..64).filter(|n| bits & (1 << n) != 0).collect()
+}
+
+/* the pseudo signals of trap. They are numbered after the real signals like bash. */
+pub const DEBUG_TRAP: i32 = 65;
+pub const ERR_TRAP: i32 = 66;
+pub const RETURN_TRAP: i32 = 67;
+
+/* EXIT, 0, INT, SIGINT, 2 -> signal number (0 for EXIT) */
+pub fn signal_number(spec: &str) -> Option<i32> {
+    let upper = spec.to_uppercase();
+    match upper.as_str() {
+        "EXIT" => return Some(0),
+        "DEBUG" => return Some(DEBUG_TRAP),
+        "ERR" => return Some(ERR_TRAP),
+        "RETURN" => return Some(RETURN_TRAP),
+        _ => {},
+    }
+
+    if let Ok(n) = spec.parse::<i32>() {
+        return match n {
+            0 => Some(0),
+            _ => Signal::try_from(n).ok().map(|s| s as i32),
+        };
+    }
+
+    let name = if upper.starts_with("SIG") { upper }else{ "SIG".to_string() + &upper };
+    Signal::from_str(&name).ok().map(|s| s as i32)
+}
+
+pub fn signal_name(n: i32) -> String {
+    match Signal::try_from(n) {
+        Ok(sig) => sig.as_str().to_string(),
+        _ if n == DEBUG_TRAP => "DEBUG".to_string(),
+        _ if n == ERR_TRAP => "ERR".to_string(),
+        _ if n == RETURN_TRAP => "RETURN".to_string(),
+        _       => "EXIT".to_string(),
+    }
+}
@@ -6,10 +6,10 @@ use std::collections::HashMap;
 pub struct Shopts(HashMap<String, bool>);
 
 impl Shopts {
-    pub fn _get(self, key: &str) -> Option<bool> {
+    pub fn get(&self, key: &str) -> bool {
         match self.0.get(key) {
-            Some(ans) => return Some(*ans),
-            _ => return None,
+            Some(ans) => *ans,
+            _ => false,
         }
     }
 
@@ -26,7 +26,21 @@ impl Shopts {
         }
     }
 
-    pub fn print(&self, on_print: bool, off_print: bool) {
+    pub fn exists(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /* "name\ton" or "shopt -s name" (reusable) */
+    pub fn print_opt(&self, key: &str, reusable: bool) {
+        let on = self.get(key);
+        if reusable {
+            println!("shopt {} {}", if on { "-s" }else{ "-u" }, key);
+        }else{
+            println!("{:15}\t{}", key, if on { "on" }else{ "off" });
+        }
+    }
+
+    pub fn print(&self, on_print: bool, off_print: bool, reusable: bool) {
         let mut keys = vec![];
         for k in self.0.keys() {
             keys.push(k);
@@ -34,17 +48,8 @@ impl Shopts {
 
         keys.sort();
         for k in keys { 
-            let output;
-            let onoff = if self.0[k] {
-                output = on_print;
-                "on"
-            }else{
-                output = off_print;
-                "off"
-            };
-
-            if output {
-                println!("{}\t{}", k, onoff);
+            if (self.0[k] && on_print) || (! self.0[k] && off_print) {
+                self.print_opt(k, reusable);
             }
         }
     }
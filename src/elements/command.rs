@@ -8,6 +8,7 @@ pub mod double_paren;
 pub mod if_command;
 pub mod case_command;
 pub mod while_command;
+pub mod select_command;
 pub mod function_definition;
 
 use nix::unistd::Pid;
@@ -20,6 +21,7 @@ use crate::core::proc;
 use self::double_paren::CommandDoubleParen;
 use self::if_command::CommandIf;
 use self::while_command::CommandWhile;
+use self::select_command::CommandSelect;
 use self::paren::CommandParen;
 use self::brace::CommandBrace;
 use self::case_command::CommandCase;
@@ -66,6 +68,7 @@ pub trait Command {
         match unsafe{fork()} {
             Ok(ForkResult::Child) => {
                 proc::set_signals();
+                conf.enter_subshell();
                 self.set_group();
                 /*
                 if self.is_group_leader() { //TODO: implement this function
@@ -76,8 +79,10 @@ pub trait Command {
                     exit(1);
                 }
                 self.exec_elems(conf);
+                let status = conf.vars["?"].parse::<i32>().unwrap();
+                conf.run_exit_trap(status);
                 close(1).expect("Can't close a pipe end");
-                exit(conf.vars["?"].parse::<i32>().unwrap());
+                exit(status);
             },
             Ok(ForkResult::Parent { child } ) => {
                 self.set_pid(child);
@@ -103,6 +108,7 @@ pub trait Command {
 pub fn parse(text: &mut Feeder, conf: &mut ShellCore) -> Option<Box<dyn Command>> {
     if let Some(a) =      CommandIf::parse(text,conf)                  {Some(Box::new(a))}
     else if let Some(a) = CommandWhile::parse(text, conf)              {Some(Box::new(a))}
+    else if let Some(a) = CommandSelect::parse(text, conf)             {Some(Box::new(a))}
     else if let Some(a) = CommandCase::parse(text, conf)               {Some(Box::new(a))}
     else if let Some(a) = CommandParen::parse(text, conf, false)       {Some(Box::new(a))}
     else if let Some(a) = CommandDoubleParen::parse(text, conf, false) {Some(Box::new(a))}
@@ -8,7 +8,8 @@ use std::os::unix::prelude::RawFd;
 use crate::elements::script::Script;
 use crate::elements::redirect::Redirect;
 use crate::file_descs::*;
-use std::process::exit;
+use std::io;
+use std::io::Write;
 use nix::unistd;
 
 fn tail_check(s: &String) -> bool{
@@ -35,11 +36,23 @@ pub struct CommandBrace {
 }
 
 impl Command for CommandBrace {
+    /* runs on this shell unless it is in a pipeline. The redirects are applied temporarily. */
     fn exec_elems(&mut self, conf: &mut ShellCore) {
-             self.script.exec(conf);
-             if ! self.fds.no_connection() {
-                 exit(conf.vars["?"].parse::<i32>().unwrap());
-             }
+        if ! self.fds.no_pipe() { // in the forked process
+            self.script.exec(conf);
+            return;
+        }
+
+        let backup = self.fds.backup_fds();
+        match self.fds.set_child_io(conf) {
+            Ok(()) => self.script.exec(conf),
+            Err(s) => {
+                eprintln!("{}", s);
+                conf.set_var("?", "1");
+            },
+        }
+        let _ = io::stdout().flush();
+        FileDescs::restore_fds(backup);
     }
 
     fn set_pid(&mut self, pid: Pid) { self.pid = Some(pid); }
@@ -50,7 +63,7 @@ impl Command for CommandBrace {
         }
     }
     fn set_group_leader(&mut self) { self.group_leader = true; }
-    fn no_connection(&self) -> bool { self.fds.no_connection() }
+    fn no_connection(&self) -> bool { self.fds.no_pipe() }
 
     fn set_child_io(&mut self, conf: &mut ShellCore) -> Result<(), String> {
         self.fds.set_child_io(conf)
@@ -12,13 +12,13 @@ use nix::unistd;
 use crate::file_descs::*;
 //use crate::feeder::scanner::*;
 use crate::elements::word::Word;
-use crate::bash_glob::glob_match;
+use crate::bash_glob::glob_match_ext;
 // use crate::elements::CommandElem;
 
 #[derive(Debug)]
 pub struct CommandCase {
     pub word: Word,
-    pub conddo: Vec<(Vec<String>, Option<Script>)>,
+    pub conddo: Vec<(Vec<Word>, Option<Script>, String)>, // (patterns, commands, ;; or ;& or ;;&)
     text: String,
     pid: Option<Pid>,
     fds: FileDescs,
@@ -52,21 +52,22 @@ impl Command for CommandCase {
     fn get_text(&self) -> String { self.text.clone() }
 
     fn exec_elems(&mut self, conf: &mut ShellCore) {
-        let word_str = self.word.eval(conf).join(" ");
-
-        for (cond, doing) in &mut self.conddo {
-            let mut flag = false;
-            for c in cond {
-                if glob_match(c, &word_str) {
-                    if let Some(d) = doing {
-                        d.exec(conf);
-                    }
-                    flag = true;
-                    break;
-                }
+        let word_str = Word::remove_escape(&self.word.eval(conf).join(" "));
+
+        let mut fall_through = false;
+        for (cond, doing, end) in &mut self.conddo {
+            if ! fall_through && ! cond.iter_mut().any(|c| glob_match_ext(&c.eval(conf).join(" "), &word_str, conf.shopts.get("extglob"))) {
+                continue;
             }
-            if flag {
-                break;
+
+            if let Some(d) = doing {
+                d.exec(conf);
+            }
+
+            match end.as_str() {
+                ";&"  => fall_through = true,
+                ";;&" => fall_through = false,
+                _     => break,
             }
         }
     }
@@ -88,27 +89,34 @@ impl CommandCase {
     fn parse_cond_do_pair(text: &mut Feeder, conf: &mut ShellCore, ans: &mut CommandCase) -> bool {
         let mut conds = vec![];
         ans.text += &text.request_next_line(conf);
+        if text.starts_with("(") {
+            ans.text += &text.consume(1);
+        }
 
-        loop {
-            let pos = text.scanner_until_escape("|)");
-            if pos == 0 || pos == text.len() {
-                return false;
+        loop { // a|b|c)
+            ans.text += &text.consume_blank();
+            match Word::parse(text, conf, false) {
+                Some(w) => {
+                    ans.text += &w.text;
+                    conds.push(w);
+                },
+                None => return false,
             }
-            conds.push(text.consume(pos));
-            ans.text += &conds.last().unwrap().clone();
+            ans.text += &text.consume_blank();
 
             if text.starts_with(")") {
-           // if text.nth(0) == ')' {
                 break;
-            }else{
+            }else if text.starts_with("|") {
                 ans.text += &text.consume(1);
+            }else{
+                return false;
             }
         }
 
         ans.text += &text.consume(1);
         ans.text += &text.request_next_line(conf);
 
-        let doing = if text.len() >= 2 && text.starts_with( ";;") {
+        let doing = if text.starts_with(";;") || text.starts_with(";&") {
             None
         }else if let Some(s) = Script::parse(text, conf) {
             ans.text += &s.text;
@@ -119,11 +127,16 @@ impl CommandCase {
 
         ans.text += &text.request_next_line(conf);
 
-        if text.len() >= 2 && text.starts_with( ";;") {
-            ans.text += &text.consume(2);
-        }
+        let end = if text.starts_with(";;&") {
+            text.consume(3)
+        }else if text.starts_with(";;") || text.starts_with(";&") {
+            text.consume(2)
+        }else{
+            ";;".to_string()
+        };
+        ans.text += &end;
 
-        ans.conddo.push( (conds, doing) );
+        ans.conddo.push( (conds, doing, end) );
         true
     }
 
@@ -142,6 +155,7 @@ impl CommandCase {
             return None;
         };
 
+        let ans_text = ans_text + &word.text;
         let mut ans = CommandCase::new(word);
         ans.text = ans_text;
 
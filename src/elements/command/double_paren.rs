@@ -9,6 +9,8 @@ use crate::elements::redirect::Redirect;
 use crate::file_descs::*;
 //use crate::feeder::scanner::*;
 use crate::calculator::calculate;
+use crate::elements::subword::variable::eval_word;
+use crate::elements::word::Word;
 use nix::unistd;
 
 #[derive(Debug)]
@@ -24,15 +26,23 @@ pub struct CommandDoubleParen {
 
 impl Command for CommandDoubleParen {
     fn exec(&mut self, conf: &mut ShellCore) {
-        self.substitution_text = calculate(self.expression.clone(), conf);
-
-        let status = if self.substitution_text == "0" {
-            "1"
-        }else{
-            "0"
-        }.to_string();
+        let expression = Word::remove_escape(&eval_word(&self.expression, conf));
+        let status = match calculate(&expression, conf) {
+            Ok(n) => {
+                self.substitution_text = n.to_string();
+                if n == 0 { "1" }else{ "0" }
+            },
+            Err(msg) => {
+                if ! msg.is_empty() {
+                    let com = if self.substitution { "" }else{ "((: " };
+                    eprintln!("bash: {}{}: {}", com, expression.trim(), msg);
+                }
+                self.substitution_text = String::new();
+                "1"
+            },
+        };
 
-        conf.set_var("?", &status);
+        conf.set_var("?", status);
     }
 
     fn get_pid(&self) -> Option<Pid> { self.pid }
@@ -82,7 +92,7 @@ impl CommandDoubleParen {
         loop{
             ans.text = text.consume(2);
 
-            let pos = text.scanner_until(0, ")");
+            let pos = text.scanner_until_closing_paren();
 
             if pos != text.len() {
                 ans.expression = text.consume(pos);
@@ -107,7 +117,7 @@ impl CommandDoubleParen {
             }
         }
 
-        text.consume(2);
+        ans.text += &text.consume(2);
         if substitution {
             return Some(ans);
         }
@@ -24,6 +24,7 @@ pub struct FunctionDefinition {
 impl Command for FunctionDefinition {
     fn exec_elems(&mut self, conf: &mut ShellCore) {
         conf.functions.insert(self.name.clone(), self.body.get_text());
+        conf.set_var("?", "0");
     }
     fn set_pid(&mut self, pid: Pid) { self.pid = Some(pid); }
     fn set_group(&mut self){
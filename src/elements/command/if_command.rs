@@ -23,7 +23,10 @@ pub struct CommandIf {
 impl Command for CommandIf {
     fn exec_elems(&mut self, conf: &mut ShellCore) {
         for pair in self.ifthen.iter_mut() {
+             let backup = conf.in_condition;
+             conf.in_condition = true;
              pair.0.exec(conf);
+             conf.in_condition = backup;
              if conf.vars["?"] != "0" {
                 continue;
              }
@@ -33,6 +36,8 @@ impl Command for CommandIf {
 
         if let Some(s) = &mut self.else_do {
             s.exec(conf);
+        }else{
+            conf.set_var("?", "0");
         }
     }
 
@@ -77,67 +82,33 @@ impl CommandIf {
 
 
     fn parse_if_then_pair(text: &mut Feeder, conf: &mut ShellCore, ans: &mut CommandIf) -> bool {
-        text.feed_additional_line(conf);
-
-        let cond = if let Some(s) = Script::parse(text, conf) {
-            ans.text += &s.text;
-            s
-        }else{
-            return false;
+        let cond = match Script::parse_until(text, conf, &["then"]) {
+            Some(s) => s,
+            None    => return false,
         };
+        ans.text += &cond.text;
+        ans.text += &text.consume(4);
 
-        text.feed_additional_line(conf);
-
-        if text.starts_with( "then"){
-            ans.text += &text.consume(4);
-        }
-
-        loop {
-            text.feed_additional_line(conf);
-
-            let backup = text.clone();
-            let doing = if let Some(s) = Script::parse(text, conf) {
-                ans.text += &s.text;
-                s
-            }else{
-                text.rewind(backup);
-                eprintln!("A");
-                continue;
-            };
-
-            eprintln!("'{}'", text._text());
-            if text.starts_with( "fi") || text.starts_with("else") || text.starts_with("elif") {
+        match Script::parse_until(text, conf, &["fi", "else", "elif"]) {
+            Some(doing) => {
+                ans.text += &doing.text;
                 ans.ifthen.push( (cond, doing) );
-                break;
-            }else{
-                text.rewind(backup);
-                eprintln!("B");
-                continue;
-            }
+            },
+            None        => return false,
         }
         true
     }
 
-    fn parse_else_fi(text: &mut Feeder, conf: &mut ShellCore, ans: &mut CommandIf) {
-        loop {
-            text.feed_additional_line(conf);
-        
-            let backup = text.clone();
-            ans.else_do = if let Some(s) = Script::parse(text, conf) {
+    fn parse_else_fi(text: &mut Feeder, conf: &mut ShellCore, ans: &mut CommandIf) -> bool {
+        ans.else_do = match Script::parse_until(text, conf, &["fi"]) {
+            Some(s) => {
                 ans.text += &s.text;
                 Some(s)
-            }else{
-                continue;
-            };
-    
-            if text.starts_with( "fi"){
-                 ans.text += &text.consume(2);
-                 break;
-            }else{
-                text.rewind(backup);
-                continue;
-            }
-        }
+            },
+            None => return false,
+        };
+        ans.text += &text.consume(2);
+        true
     }
 
     pub fn parse(text: &mut Feeder, conf: &mut ShellCore) -> Option<CommandIf> {
@@ -163,7 +134,10 @@ impl CommandIf {
                 continue;
             }else if text.starts_with( "else"){
                 ans.text += &text.consume(4);
-                CommandIf::parse_else_fi(text, conf, &mut ans);
+                if ! CommandIf::parse_else_fi(text, conf, &mut ans) {
+                    text.rewind(backup);
+                    return None;
+                }
                 break;
             }
 
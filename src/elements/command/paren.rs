@@ -33,6 +33,7 @@ impl Command for CommandParen {
         match unsafe{fork()} {
             Ok(ForkResult::Child) => {
                 proc::set_signals();
+                conf.enter_subshell();
                 self.set_group();
                 if let Err(s) = self.fds.set_child_io(conf){
                     eprintln!("{}", s);
@@ -44,8 +45,10 @@ impl Command for CommandParen {
                         FileDescs::dup_and_close(p.1, 1);
                     }
                     s.exec(conf);
+                    let status = conf.vars["?"].parse::<i32>().unwrap();
+                    conf.run_exit_trap(status);
                     close(1).expect("Can't close a pipe end");
-                    exit(conf.vars["?"].parse::<i32>().unwrap());
+                    exit(status);
                 };
             },
             Ok(ForkResult::Parent { child } ) => {
@@ -53,6 +56,7 @@ impl Command for CommandParen {
                     close(p.1).expect("Can't close a pipe end");
                     self.substitution_text  = conf.read_pipe(p.0, child)
                         .trim_end_matches('\n').to_string();
+                    close(p.0).expect("Can't close a pipe end");
                 }
                 self.pid = Some(child);
                 return;
@@ -0,0 +1,221 @@
+//SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, Feeder};
+use crate::elements::command::Command;
+use std::os::unix::prelude::RawFd;
+use crate::elements::script::Script;
+use crate::elements::redirect::Redirect;
+use crate::elements::word::Word;
+use nix::unistd::Pid;
+use nix::unistd;
+use crate::file_descs::*;
+use crate::utils::read_line_fd0;
+
+#[derive(Debug)]
+pub struct CommandSelect {
+    pub name: String,
+    pub words: Option<Vec<Word>>, // None: select name; do ... done
+    pub doing: Option<Script>,
+    text: String,
+    pid: Option<Pid>,
+    fds: FileDescs,
+    group_leader: bool,
+}
+
+impl Command for CommandSelect {
+    fn get_pid(&self) -> Option<Pid> { self.pid }
+    fn set_pid(&mut self, pid: Pid) { self.pid = Some(pid); }
+    fn set_group(&mut self){
+        if self.group_leader {
+            let pid = nix::unistd::getpid();
+            let _ = unistd::setpgid(pid, pid);
+        }
+    }
+    fn set_group_leader(&mut self) { self.group_leader = true; }
+    fn no_connection(&self) -> bool { self.fds.no_connection() }
+
+    fn set_pipe(&mut self, pin: RawFd, pout: RawFd, pprev: RawFd) {
+        self.fds.pipein = pin;
+        self.fds.pipeout = pout;
+        self.fds.prevpipein = pprev;
+    }
+
+    fn set_child_io(&mut self, conf: &mut ShellCore) -> Result<(), String> {
+        self.fds.set_child_io(conf)
+    }
+
+    fn get_pipe_end(&mut self) -> RawFd { self.fds.pipein }
+    fn get_pipe_out(&mut self) -> RawFd { self.fds.pipeout }
+    fn get_text(&self) -> String { self.text.clone() }
+
+    fn exec_elems(&mut self, conf: &mut ShellCore) {
+        let items = match &mut self.words {
+            Some(words) => words.iter_mut()
+                           .flat_map(|w| w.eval_fields(conf).into_iter().flatten())
+                           .map(|s| Word::remove_escape(&s)).collect::<Vec<String>>(),
+            None => conf.args[1..].to_vec(),
+        };
+
+        conf.set_var("?", "0");
+        if items.is_empty() {
+            return;
+        }
+
+        let mut show_menu = true;
+        conf.loop_level += 1;
+        loop {
+            if show_menu {
+                CommandSelect::print_menu(&items);
+            }
+
+            let ps3 = if conf.has_var("PS3") { conf.get_var("PS3") }else{ "#? ".to_string() };
+            eprint!("{}", ps3);
+
+            let reply = match read_line_fd0() {
+                Some(line) => line.trim_end_matches('\n').to_string(),
+                None => {
+                    eprintln!();
+                    conf.set_var("?", "1");
+                    break;
+                },
+            };
+
+            show_menu = reply.is_empty();
+            if show_menu {
+                continue;
+            }
+
+            let value = match reply.trim().parse::<usize>() {
+                Ok(n) if n >= 1 && n <= items.len() => items[n-1].clone(),
+                _ => "".to_string(),
+            };
+            conf.set_var("REPLY", &reply);
+            conf.substitute_var(&self.name, &value);
+
+            if let Some(d) = &mut self.doing {
+                d.exec(conf);
+            }
+            if ! conf.check_loop_jump() {
+                break;
+            }
+        }
+        conf.loop_level -= 1;
+    }
+}
+
+impl CommandSelect {
+    pub fn new() -> CommandSelect{
+        CommandSelect {
+            name: "".to_string(),
+            words: None,
+            doing: None,
+            text: "".to_string(),
+            fds: FileDescs::new(),
+            pid: None,
+            group_leader: false,
+        }
+    }
+
+    /* items are printed to stderr like bash */
+    fn print_menu(items: &[String]) {
+        for (i, item) in items.iter().enumerate() {
+            eprintln!("{}) {}", i+1, item);
+        }
+    }
+
+    fn parse_words(text: &mut Feeder, conf: &mut ShellCore, ans: &mut CommandSelect) -> bool {
+        ans.text += &text.consume_blank();
+        if ! text.starts_with("in") || text.scanner_name(0) != 2 {
+            return true;
+        }
+        ans.text += &text.consume(2);
+
+        let mut words = vec![];
+        loop {
+            ans.text += &text.consume_blank();
+            if text.len() == 0 || text.starts_with(";") || text.starts_with("\n") {
+                break;
+            }
+
+            match Word::parse(text, conf, false) {
+                Some(w) => {
+                    ans.text += &w.text;
+                    words.push(w);
+                },
+                None => return false,
+            }
+        }
+
+        ans.words = Some(words);
+        true
+    }
+
+    fn parse_do(text: &mut Feeder, conf: &mut ShellCore, ans: &mut CommandSelect) -> bool {
+        if text.starts_with(";") {
+            ans.text += &text.consume(1);
+        }
+        ans.text += &text.request_next_line(conf);
+
+        if text.starts_with("do") {
+            ans.text += &text.consume(2);
+        }else{
+            return false;
+        }
+
+        ans.doing = match Script::parse_until(text, conf, &["done"]) {
+            Some(s) => {
+                ans.text += &s.text;
+                Some(s)
+            },
+            None => return false,
+        };
+        true
+    }
+
+    pub fn parse(text: &mut Feeder, conf: &mut ShellCore) -> Option<CommandSelect> {
+        if text.len() < 6 || ! text.starts_with("select") || text.scanner_name(0) != 6 {
+            return None;
+        }
+
+        let backup = text.clone();
+
+        let mut ans = CommandSelect::new();
+        ans.text += &text.consume(6);
+        ans.text += &text.consume_blank();
+
+        let pos = text.scanner_name(0);
+        if pos == 0 {
+            text.rewind(backup);
+            return None;
+        }
+        ans.name = text.consume(pos);
+        ans.text += &ans.name.clone();
+
+        if ! CommandSelect::parse_words(text, conf, &mut ans)
+        || ! CommandSelect::parse_do(text, conf, &mut ans) {
+            text.rewind(backup);
+            return None;
+        }
+
+        if text.starts_with("done"){
+            ans.text += &text.consume(4);
+        }else{
+            text.rewind(backup);
+            return None;
+        }
+
+        loop {
+            ans.text += &text.consume_blank();
+
+            if let Some(r) = Redirect::parse(text, conf){
+                    ans.text += &r.text;
+                    ans.fds.redirects.push(Box::new(r));
+            }else{
+                break;
+            }
+        }
+
+        Some(ans)
+    }
+}
@@ -2,10 +2,14 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use std::env;
+use std::io;
+use std::io::Write;
 
 use nix::unistd::{execvpe, fork, ForkResult, Pid}; 
 use nix::unistd;
 use std::ffi::CString;
+use std::path::Path;
+use nix::errno::Errno;
 use std::process::exit;
 use std::os::unix::prelude::RawFd;
 
@@ -20,11 +24,13 @@ use crate::elements::redirect::Redirect;
 use crate::elements::substitution::Substitution;
 //use crate::feeder::scanner::*;
 use crate::file_descs::*;
+use crate::feeder::words::split_words;
 
 #[derive(Debug)]
 pub struct SimpleCommand {
     vars: Vec<Substitution>,
     pub args: Vec<Word>,
+    array_args: Vec<Substitution>, // name=(elem ...) given to declare, local or typeset
     pub text: String,
     pub pid: Option<Pid>,
     fds: FileDescs,
@@ -38,6 +44,7 @@ fn is_reserve(s: &String) -> bool {
 
 impl Command for SimpleCommand {
     fn exec(&mut self, core: &mut ShellCore) {
+        core.run_debug_trap(&self.text);
         if self.args.len() == 0 {
             self.set_vars(core);
             return;
@@ -47,40 +54,32 @@ impl Command for SimpleCommand {
             eprintln!("{}", self.text.trim_end());
         }
 
-        let mut args = self.eval(core);
-        //eprintln!("NUM:{} {:?}", args.len(), &args); 
+        let mut args = match self.eval(core) {
+            Some(a) => a,
+            None => { // failglob. The rest of the line is skipped like bash.
+                core.set_var("?", "1");
+                core.interrupted = true;
+                return;
+            },
+        };
+        if args.is_empty() {
+            self.set_vars(core);
+            return;
+        }
+        //eprintln!("NUM:{} {:?}", args.len(), &args);
         core.set_var("_", &args[args.len()-1]);
 
         if core.has_flag('x') {
-            eprintln!("+{}", args.join(" "));
+            SimpleCommand::xtrace(&args, core);
         }
 
-        // This sentence avoids an unnecessary fork for an internal command.
-        if self.fds.no_connection() {
-            if core.functions.contains_key(&args[0]) {
-                self.exec_function(&mut args, core);
-                return;
-            }
-            if self.run_on_this_process(&mut args, core) {
-                return;
-            }
+        let temporary = ! self.vars.is_empty();
+        if temporary {
+            self.set_temporary_vars(core);
         }
-
-        match unsafe{fork()} {
-            Ok(ForkResult::Child) => {
-                proc::set_signals();
-                self.set_group();
-                if let Err(s) = self.fds.set_child_io(core){
-                    eprintln!("{}", s);
-                    exit(1);
-                }
-                self.exec_external_command(&mut args, core)
-            },
-            Ok(ForkResult::Parent { child } ) => {
-                self.pid = Some(child);
-                return;
-            },
-            Err(err) => panic!("Failed to fork. {}", err),
+        self.exec_command(&mut args, core);
+        if temporary {
+            core.pop_local_scope();
         }
     }
 
@@ -105,10 +104,52 @@ impl Command for SimpleCommand {
 }
 
 impl SimpleCommand {
+    fn exec_command(&mut self, args: &mut Vec<String>, core: &mut ShellCore) {
+        let no_function = SimpleCommand::remove_command_builtin(args);
+        let is_function = ! no_function && core.functions.contains_key(&args[0]);
+
+        // This sentence avoids an unnecessary fork for an internal command.
+        // A background command (the group leader) is always forked.
+        if self.fds.no_connection() && is_function && ! self.group_leader {
+            self.exec_function(args, core);
+            return;
+        }
+        if self.fds.no_pipe() && ! is_function && ! self.group_leader
+        && self.run_on_this_process(args, core) {
+            return;
+        }
+
+        if ! is_function && core.get_builtin(&args[0]).is_none() {
+            let path = core.get_command_path(&args[0]);
+            if ! path.is_empty() {
+                args[0] = path;
+            }
+        }
+
+        match unsafe{fork()} {
+            Ok(ForkResult::Child) => {
+                proc::set_signals();
+                core.enter_subshell();
+                self.set_group();
+                if let Err(s) = self.fds.set_child_io(core){
+                    eprintln!("{}", s);
+                    exit(1);
+                }
+                self.exec_external_command(args, core, is_function)
+            },
+            Ok(ForkResult::Parent { child } ) => {
+                self.pid = Some(child);
+                return;
+            },
+            Err(err) => panic!("Failed to fork. {}", err),
+        }
+    }
+
     pub fn new() -> SimpleCommand{
         SimpleCommand {
             vars: vec![],
             args: vec![],
+            array_args: vec![],
             //eoc: None,
             text: "".to_string(),
             pid: None,
@@ -117,28 +158,78 @@ impl SimpleCommand {
         }
     }
 
+    /* removes "command" (and -p) from the head of the args. The command
+       is not searched from functions if true is returned. -v and -V are
+       left for the builtin command. */
+    fn remove_command_builtin(args: &mut Vec<String>) -> bool {
+        let mut removed = false;
+        while args.len() > 1 && args[0] == "command" {
+            if args[1].starts_with('-') && args[1] != "-p" && args[1] != "--" {
+                break;
+            }
+            args.remove(0);
+            if args[0] == "-p" || args[0] == "--" {
+                args.remove(0);
+            }
+            removed = true;
+            if args.is_empty() {
+                args.push(":".to_string());
+            }
+        }
+        removed
+    }
+
     fn run_on_this_process(&mut self, args: &mut Vec<String>, core: &mut ShellCore) -> bool {
-        if let Some(func) = core.get_builtin(&args[0]) {
-            let status = func(core, args);
-            core.set_var("?", &status.to_string());
-            true
-        }else{
-            false
+        let func = match core.get_builtin(&args[0]) {
+            Some(f) => f,
+            None => return false,
+        };
+
+        let arrays = self.array_args.iter_mut()
+                     .map(|e| e.eval_elems(core).unwrap_or_default())
+                     .collect::<Vec<Vec<(Option<String>, String)>>>();
+
+        // the redirects of exec are applied to the shell permanently
+        let backup = if args[0] == "exec" { vec![] }else{ self.fds.backup_fds() };
+        let status = match self.fds.set_child_io(core) {
+            Ok(()) => func(core, args),
+            Err(s) => {
+                eprintln!("{}", s);
+                1
+            },
+        };
+        if status == 0 { // the builtin has made the variables (local)
+            for (e, elems) in self.array_args.iter().zip(arrays) {
+                e.set_elems(&elems, core);
+            }
         }
+        let _ = io::stdout().flush();
+        FileDescs::restore_fds(backup);
+
+        core.set_var("?", &status.to_string());
+        true
     }
 
-    fn eval(&mut self, core: &mut ShellCore) -> Vec<String> {
+    /* None is returned when a glob does not match with failglob */
+    fn eval(&mut self, core: &mut ShellCore) -> Option<Vec<String>> {
         let mut args = vec![];
 
         for word in &mut self.args {
-            for s in &word.eval(core) {
-                args.append(&mut eval_glob(&s.clone()));
+            // None is e.g. an empty variable or the empty alternative of {a,}
+            for s in word.eval_fields(core).iter().flatten() {
+                match expand_glob(s, core) {
+                    Ok(mut paths) => args.append(&mut paths),
+                    Err(msg) => {
+                        eprintln!("{}", Word::remove_escape(&msg));
+                        return None;
+                    },
+                }
             }
         };
 
-        args.iter()
+        Some(args.iter()
             .map(|a| Word::remove_escape(&a))
-            .collect()
+            .collect())
     }
 
     pub fn push_vars(&mut self, s: Substitution){
@@ -163,64 +254,120 @@ impl SimpleCommand {
         if let Some(mut f) = command::parse(&mut feeder, core) {
          //   eprintln!("FUNCTION '{:?}'", f);
             let backup = core.args.clone();
+            let backup_return = core.return_enable;
+            let backup_loop_level = core.loop_level; // break in a function doesn't exit the caller's loop
             core.args = args.to_vec();
             core.return_enable = true;
+            core.loop_level = 0;
+            core.push_local_scope();
+            core.function_level += 1;
+            /* only a RETURN trap set in the function runs at its end. It remains after the function. */
+            let return_trap = core.traps.remove(&proc::RETURN_TRAP);
             f.exec(core);
+            core.run_return_trap();
+            if let Some(com) = return_trap {
+                core.traps.entry(proc::RETURN_TRAP).or_insert(com);
+            }
+            core.function_level -= 1;
+            core.pop_local_scope();
             self.pid = f.get_pid();
             core.args = backup;
-            core.return_enable = false;
+            core.return_enable = backup_return;
+            core.return_flag = false;
+            core.loop_level = backup_loop_level;
         }else{
             panic!("Shell internal error on function");
         };
         //eprintln!("OUT '{}'", feeder._text());
     }
 
-    fn exec_external_command(&mut self, args: &mut Vec<String>, core: &mut ShellCore) {
-        if core.functions.contains_key(&args[0]) {
-            self.exec_function(args, core);
-            exit(0);
-        }
-
-        if let Some(func) = core.get_builtin(&args[0]) {
-            exit(func(core, args));
+    fn exec_external_command(&mut self, args: &mut Vec<String>, core: &mut ShellCore, is_function: bool) {
+        let builtin = core.get_builtin(&args[0]);
+        if is_function || builtin.is_some() {
+            let status = match builtin {
+                Some(func) if ! is_function => func(core, args),
+                _ => {
+                    self.exec_function(args, core);
+                    core.vars["?"].parse::<i32>().unwrap()
+                },
+            };
+            core.run_exit_trap(status);
+            exit(status);
         }
 
-        //let fullpath = get_fullpath(&args[0]);
-        //args[0] = fullpath;
-        args[0] = get_fullpath(&args[0]);
 
-        let cargs: Vec<CString> = args
-            .iter()
-            .map(|a| CString::new(a.to_string()).unwrap())
-            .collect();
+        let cargs: Vec<CString> = match args.iter().map(|a| CString::new(a.to_string())).collect() {
+            Ok(cargs) => cargs,
+            Err(_) => {
+                eprintln!("bash: {}: an argument contains a null character", &args[0]);
+                exit(126);
+            },
+        };
 
         if core.has_flag('d') {
             eprintln!("{}", self.parse_info().join("\n"));
         };
 
-        for v in &mut self.vars {
-            let key = (*v).name.clone();
-            let value =  (*v).value.eval(core).join(" ");
-            env::set_var(key, value);
-        }
         env::set_var("_".to_string(), args[0].clone());
 
-        let envs: Vec<CString> = std::env::vars()
-            .map(|v| format!("{}={}", v.0, v.1))
-            .map(|a| CString::new(a.to_string()).unwrap())
-            .collect();
+        let envs: Vec<CString> = match std::env::vars()
+            .map(|v| CString::new(format!("{}={}", v.0, v.1))).collect() {
+            Ok(envs) => envs,
+            Err(_) => {
+                eprintln!("bash: {}: an environment variable contains a null character", &args[0]);
+                exit(126);
+            },
+        };
 
-        let _ = execvpe(&cargs[0], &cargs, &envs);
+        if ! args[0].contains('/') { // not found in PATH
+            if core.functions.contains_key("command_not_found_handle") {
+                args.insert(0, "command_not_found_handle".to_string());
+                self.exec_function(args, core);
+                let status = core.get_var("?").parse::<i32>().unwrap_or(1);
+                core.run_exit_trap(status);
+                exit(status);
+            }
+            eprintln!("Command not found: {:?}", &cargs[0]);
+            exit(127);
+        }
+
+        if Path::new(&args[0]).is_dir() {
+            eprintln!("bash: {}: Is a directory", &args[0]);
+            exit(126);
+        }
 
-        eprintln!("Command not found: {:?}", &cargs[0]);
+        match execvpe(&cargs[0], &cargs, &envs) {
+            Err(Errno::EACCES) => {
+                eprintln!("bash: {}: Permission denied", &args[0]);
+                exit(126);
+            },
+            Err(Errno::ENOENT) => eprintln!("bash: {}: No such file or directory", &args[0]),
+            Err(e) => eprintln!("bash: {}: {}", &args[0], e.desc()),
+            Ok(_) => {},
+        }
         exit(127);
     }
 
     fn replace_alias(text: &mut Feeder, core: &mut ShellCore) {
-        let compos = text.scanner_until_escape(" \n");
-        let com = text.from_to(0, compos);
-        if let Some(alias) = core.aliases.get(&com){
-            text.replace(&com, alias);
+        let mut expanded = vec![]; // not to expand an alias recursively
+        loop {
+            let line_end = text.scanner_until_escape("\n");
+            let com = match split_words(&text.from_to(0, line_end)).first() {
+                Some(w) if ! w.is_operator => w.text.clone(),
+                _ => return,
+            };
+            if expanded.contains(&com) {
+                return;
+            }
+
+            match core.aliases.get(&com) {
+                Some(alias) => {
+                    let alias = alias.clone();
+                    text.replace(&com, &alias);
+                    expanded.push(com);
+                },
+                None => return,
+            }
         }
     }
 
@@ -258,6 +405,8 @@ impl SimpleCommand {
             if let Some(r) = Redirect::parse(text, core){
                 ans.text += &r.text;
                 ans.fds.redirects.push(Box::new(r));
+            }else if Self::parse_array_arg(text, core, ans) {
+                ok = true;
             }else if let Some(a) = Word::parse(text, core, false) {
                 if ! SimpleCommand::ng_check(&a.text, ans.args.len() == 0){
                     text.rewind(backup);
@@ -295,6 +444,30 @@ impl SimpleCommand {
         ok
     }
 
+    /* name=(elem ...) after declare, local or typeset. The name is passed
+       to the builtin and the elements are set after the builtin runs. */
+    fn parse_array_arg(text: &mut Feeder, core: &mut ShellCore, ans: &mut SimpleCommand) -> bool {
+        match ans.args.first() {
+            Some(w) if ["declare", "local", "typeset"].contains(&w.text.as_str()) => {},
+            _ => return false,
+        }
+
+        let backup = text.clone();
+        match Substitution::parse(text, core) {
+            Some(s) if s.elems.is_some() => {
+                let name = Word::parse(&mut Feeder::new_from(s.name.clone()), core, false).unwrap();
+                ans.text += &s.get_text();
+                ans.args.push(name);
+                ans.array_args.push(s);
+                true
+            },
+            _ => {
+                text.rewind(backup);
+                false
+            },
+        }
+    }
+
     pub fn parse(text: &mut Feeder, core: &mut ShellCore) -> Option<SimpleCommand> {
         let backup = text.clone();
         let mut ans = SimpleCommand::new();
@@ -304,7 +477,7 @@ impl SimpleCommand {
         };
 
         SimpleCommand::substitutions_and_redirects(text, core, &mut ans);
-        if core.has_flag('i') {
+        if core.has_flag('i') || core.shopts.get("expand_aliases") {
             Self::replace_alias(text, core);
         }
 
@@ -316,15 +489,78 @@ impl SimpleCommand {
         }
     }
 
+    /* set -x */
+    fn xtrace(args: &[String], core: &mut ShellCore) {
+        let quote = |a: &String| {
+            if a.is_empty() || a.chars().any(|c| " \t\n'\"\\$`*?[]{}()<>|&;#~!".contains(c)) {
+                format!("'{}'", a.replace("'", "'\\''"))
+            }else{
+                a.to_string()
+            }
+        };
+
+        SimpleCommand::print_xtrace(&args.iter().map(quote).collect::<Vec<String>>().join(" "), core);
+    }
+
+    /* a line of set -x after PS4 */
+    fn print_xtrace(line: &str, core: &ShellCore) {
+        let ps4 = if core.has_var("PS4") { core.get_var("PS4") }else{ "+ ".to_string() };
+        eprintln!("{}{}", ps4, line);
+    }
+
+    /* the assignments before a command are effective only during the command.
+       They are exported as local variables of a temporary scope. The command
+       runs even if an assignment fails like bash. */
+    fn set_temporary_vars(&mut self, core: &mut ShellCore) {
+        core.push_local_scope();
+        for e in &mut self.vars {
+            let sub = e.eval(core);
+            let (key, value) = (sub[0].clone(), sub[1].clone());
+            core.make_local(&key);
+            if core.substitute_var(&key, &value) {
+                env::set_var(&key, core.get_var(&key));
+            }
+        }
+    }
+
     fn set_vars(&mut self, core: &mut ShellCore){
         for e in &mut self.vars {
+            if let Some(elems) = e.eval_elems(core) {
+                if core.has_flag('x') {
+                    let elems = elems.iter().map(|(key, value)| match key {
+                        Some(k) => format!("[{}]={}", k, value),
+                        None    => value.clone(),
+                    }).collect::<Vec<String>>();
+                    let op = if e.append {"+="}else{"="};
+                    SimpleCommand::print_xtrace(&format!("{}{}({})", e.name, op, elems.join(" ")), core);
+                }
+                if ! e.set_elems(&elems, core) {
+                    core.set_var("?", "1");
+                    if ! core.has_flag('i') {
+                        exit(1);
+                    }
+                    return;
+                }
+                continue;
+            }
+
             let sub = e.eval(core);
             let (key, value) = (sub[0].clone(), sub[1].clone());
-            if let Ok(_) = env::var(&key) {
-                env::set_var(key, value);
-            }else{
-                core.set_var(&key, &value);
+            if core.has_flag('x') {
+                SimpleCommand::print_xtrace(&format!("{}={}", key, value), core);
+            }
+            let ok = match e.eval_subscript(core) {
+                Some(sub) => core.set_array_elem(&key, &sub, &value),
+                None if core.is_array(&key) => core.set_array_elem(&key, "0", &value),
+                None      => core.substitute_var(&key, &value),
             };
+            if ! ok {
+                core.set_var("?", "1");
+                if ! core.has_flag('i') {
+                    exit(1);
+                }
+                return;
+            }
         };
     }
 }
@@ -46,16 +46,30 @@ impl Command for CommandWhile {
     fn get_text(&self) -> String { self.text.clone() }
 
     fn exec_elems(&mut self, conf: &mut ShellCore) {
+        conf.loop_level += 1;
         loop {
             if let Some((cond, doing)) = &mut self.conddo {
+                let backup = conf.in_condition;
+                conf.in_condition = true;
                 cond.exec(conf);
+                conf.in_condition = backup;
+                if conf.is_jumping() {
+                    if conf.check_loop_jump() {
+                        continue;
+                    }
+                    break;
+                }
                 if conf.vars["?"] != "0" {
                     conf.set_var("?", "0");
                     break;
                 }
                 doing.exec(conf);
+                if ! conf.check_loop_jump() {
+                    break;
+                }
             }
         }
+        conf.loop_level -= 1;
     }
 }
 
@@ -72,31 +86,18 @@ impl CommandWhile {
 
 
     fn parse_cond_do_pair(text: &mut Feeder, conf: &mut ShellCore, ans: &mut CommandWhile) -> bool {
-        ans.text += &text.request_next_line(conf);
-
-        let cond = if let Some(s) = Script::parse(text, conf) {
-            ans.text += &s.text;
-            s
-        }else{
-            return false;
+        let cond = match Script::parse_until(text, conf, &["do"]) {
+            Some(s) => s,
+            None    => return false,
         };
+        ans.text += &cond.text;
+        ans.text += &text.consume(2);
 
-        ans.text += &text.request_next_line(conf);
-
-        if text.starts_with( "do"){
-            ans.text += &text.consume(2);
-        }
-
-        ans.text += &text.request_next_line(conf);
-
-        let doing = if let Some(s) = Script::parse(text, conf) {
-            ans.text += &s.text;
-            s
-        }else{
-            return false;
+        let doing = match Script::parse_until(text, conf, &["done"]) {
+            Some(s) => s,
+            None    => return false,
         };
-
-        ans.text += &text.request_next_line(conf);
+        ans.text += &doing.text;
 
         ans.conddo = Some( (cond, doing) );
         true
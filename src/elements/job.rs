@@ -50,7 +50,20 @@ impl Job {
                 continue;
             }
             p.exec(conf);
+            conf.run_traps();
+            if conf.is_jumping() {
+                return;
+            }
             eop = self.pipeline_ends[i].clone();
+
+            if conf.get_var("?") != "0" && ! conf.in_condition && ! p.not_flag
+            && eop != ControlOperator::And && eop != ControlOperator::Or {
+                conf.run_err_trap();
+                if conf.has_flag('e') {
+                    let f = conf.builtins["exit"];
+                    f(conf, &mut vec!("exit".to_string()));
+                }
+            }
         }
     }
 
@@ -58,20 +71,22 @@ impl Job {
         match unsafe{unistd::fork()} {
             Ok(ForkResult::Child) => {
                 proc::set_signals();
+                conf.enter_subshell();
                 let pid = nix::unistd::getpid();
                 let _ = unistd::setpgid(pid, pid);
 
                 self.exec_job(conf);
 
-
-                exit(conf.vars["?"].parse::<i32>().unwrap());
+                let status = conf.vars["?"].parse::<i32>().unwrap();
+                conf.run_exit_trap(status);
+                exit(status);
             },
             Ok(ForkResult::Parent { child } ) => {
                 let mut com = SimpleCommand::new();
                 com.group_leader = true;
                 com.pid = Some(child);
                 conf.jobs.add_bg_job(&self.text, &vec!(Box::new(com)));
-                return;
+                conf.set_var("!", &child.to_string());
             },
             Err(err) => panic!("Failed to fork. {}", err),
         }
@@ -5,6 +5,9 @@ use crate::{ShellCore, Feeder};
 use crate::elements::command::Command;
 use crate::operators::ControlOperator;
 use nix::unistd::pipe;
+use nix::sys::resource::{getrusage, UsageWho};
+use nix::sys::time::TimeVal;
+use std::time::Instant;
 use crate::file_descs::FileDescs;
 use crate::elements::command;
 
@@ -14,11 +17,55 @@ pub struct Pipeline {
     pub text: String,
     pub is_bg: bool,
     pub job_no: u32,
-    not_flag: bool,
+    pub not_flag: bool,
+    pub time_flag: bool,
+    pub posix_time: bool, // time -p
 }
 
 impl Pipeline {
     pub fn exec(&mut self, core: &mut ShellCore) {
+        if ! self.time_flag {
+            return self.exec_commands(core);
+        }
+
+        let start = Instant::now();
+        let before = Pipeline::cpu_times();
+        self.exec_commands(core);
+        let after = Pipeline::cpu_times();
+        let real = start.elapsed().as_secs_f64();
+
+        let (user, sys) = (after.0 - before.0, after.1 - before.1);
+        if self.posix_time {
+            eprintln!("real {:.2}\nuser {:.2}\nsys {:.2}", real, user, sys);
+        }else{
+            eprintln!("\nreal\t{}\nuser\t{}\nsys\t{}", Pipeline::min_sec(real),
+                      Pipeline::min_sec(user), Pipeline::min_sec(sys));
+        }
+    }
+
+    /* user and system times of the shell and the waited children */
+    fn cpu_times() -> (f64, f64) {
+        let to_secs = |t: TimeVal| t.tv_sec() as f64 + t.tv_usec() as f64 / 1_000_000.0;
+        let mut ans = (0.0, 0.0);
+        for who in [UsageWho::RUSAGE_SELF, UsageWho::RUSAGE_CHILDREN] {
+            if let Ok(u) = getrusage(who) {
+                ans.0 += to_secs(u.user_time());
+                ans.1 += to_secs(u.system_time());
+            }
+        }
+        ans
+    }
+
+    fn min_sec(secs: f64) -> String {
+        let min = (secs / 60.0).floor();
+        format!("{}m{:.3}s", min, secs - min * 60.0)
+    }
+
+    fn exec_commands(&mut self, core: &mut ShellCore) {
+        if self.commands.is_empty() { // only "time"
+            return;
+        }
+
         let len = self.commands.len();
         let mut prevfd = -1;
         for (i, c) in self.commands.iter_mut().enumerate() {
@@ -40,7 +87,8 @@ impl Pipeline {
 
     fn set_job_and_wait(&mut self, core: &mut ShellCore) {
         if self.is_bg {
-            core.jobs.add_bg_job(&self.text, &self.commands);
+            let pid = core.jobs.add_bg_job(&self.text, &self.commands);
+            core.set_var("!", &pid.to_string());
         }else{
             core.jobs.set_fg_job(&self.text, &self.commands);
             core.wait_job();
@@ -57,6 +105,8 @@ impl Pipeline {
             commands: vec![],
             text: "".to_string(),
             not_flag: false,
+            time_flag: false,
+            posix_time: false,
             is_bg: false,
             job_no: 0,
         }
@@ -74,9 +124,21 @@ impl Pipeline {
     pub fn parse(text: &mut Feeder, core: &mut ShellCore) -> Option<Pipeline> {
         let mut ans = Pipeline::new();
         ans.text += &text.consume_blank();
-        if text.starts_with( "!") {
-            ans.not_flag = true;
+        if text.starts_with("time") && (text.len() == 4 || " \t\n;".contains(text.nth(4))) {
+            ans.time_flag = true;
+            ans.text += &text.consume(4);
+            ans.text += &text.consume_blank();
+            if text.starts_with("-p") && (text.len() == 2 || " \t\n;".contains(text.nth(2))) {
+                ans.posix_time = true;
+                ans.text += &text.consume(2);
+                ans.text += &text.consume_blank();
+            }
+        }
+
+        while text.starts_with("!") && text.len() > 1 && " \t".contains(text.nth(1)) {
+            ans.not_flag = ! ans.not_flag;
             ans.text += &text.consume(1);
+            ans.text += &text.consume_blank();
         }
 
         loop {
@@ -117,7 +179,7 @@ impl Pipeline {
         }
 
         ans.text += &text.consume_blank_return();
-        if ans.commands.len() > 0 {
+        if ans.commands.len() > 0 || ans.time_flag {
             Some(ans)
         }else{
             None
@@ -16,6 +16,8 @@ pub struct Redirect {
     pub redirect_type: RedirectOp,
     pub path: String,
     pub right_word: Option<Word>,
+    pub here_doc: String,
+    pub expand_here_doc: bool,
 }
 
 impl Redirect {
@@ -28,6 +30,8 @@ impl Redirect {
             redirect_type: RedirectOp::NoRedirect,
             path: String::new(),
             right_word: None,
+            here_doc: String::new(),
+            expand_here_doc: true,
         }
     }
 
@@ -44,6 +48,68 @@ impl Redirect {
         String::new()
     }
 
+    /* the input given by << or <<< */
+    pub fn eval_here(&mut self, conf: &mut ShellCore) -> String {
+        if self.redirect_type == RedirectOp::HereStr {
+            return match &mut self.right_word {
+                Some(w) => Word::remove_escape(&w.eval(conf).join(" ")) + "\n",
+                None => "\n".to_string(),
+            };
+        }
+
+        if ! self.expand_here_doc {
+            return self.here_doc.clone();
+        }
+
+        /* the body is expanded as a double quoted word, where " is not special */
+        let mut quoted = "\"".to_string();
+        let mut chars = self.here_doc.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                '"' => quoted += "\\\"",
+                '\\' => match chars.next() {
+                    Some('"') => quoted += "\\\\\\\"",
+                    Some(c) => { quoted.push(ch); quoted.push(c); },
+                    None => quoted += "\\\\",
+                },
+                _ => quoted.push(ch),
+            }
+        }
+        quoted.push('"');
+
+        let mut feeder = Feeder::new_from(quoted);
+        match Word::parse(&mut feeder, conf, false) {
+            Some(mut w) => Word::remove_escape(&w.eval(conf).join("")),
+            None => self.here_doc.clone(),
+        }
+    }
+
+    fn parse_here_doc(text: &mut Feeder, conf: &mut ShellCore, ans: &mut Redirect, strip_tabs: bool) {
+        let delim_text = match &ans.right_word {
+            Some(w) => w.text.clone(),
+            None => return,
+        };
+        ans.expand_here_doc = ! delim_text.contains(|c| "'\"\\".contains(c));
+        let delim = delim_text.replace(|c| "'\"\\".contains(c), "");
+        ans.here_doc = text.cut_here_document(&delim, strip_tabs, conf);
+    }
+
+    /* no word after a redirection operator. The whole line is not executed. */
+    fn syntax_error(text: &mut Feeder, conf: &mut ShellCore) {
+        let (n, _) = text.scanner_control_op();
+        let token = if text.len() == 0 || text.starts_with("\n") {
+            "newline".to_string()
+        }else if n > 0 {
+            text.from_to(0, n)
+        }else{
+            text.nth(0).to_string()
+        };
+        eprintln!("bash: syntax error near unexpected token `{}'", token);
+        conf.set_var("?", "2");
+        conf.interrupted = true;
+        text.consume(text.len());
+    }
+
     pub fn parse(text: &mut Feeder, conf: &mut ShellCore) -> Option<Redirect> {
         let mut ans = Redirect::new(text);
         let backup = text.clone();
@@ -65,12 +131,19 @@ impl Redirect {
 
         ans.redirect_type = red.unwrap();
         ans.text += &text.consume(pos);
+        let strip_tabs = ans.redirect_type == RedirectOp::HereDoc && text.starts_with("-");
+        if strip_tabs {
+            ans.text += &text.consume(1);
+        }
         ans.text += &text.consume_blank();
 
         if ans.left_fd == -1 {
-            if ans.redirect_type == RedirectOp::Input {
+            if ans.redirect_type == RedirectOp::Input || ans.redirect_type == RedirectOp::InOut
+            || ans.redirect_type == RedirectOp::InputAnd || ans.redirect_type == RedirectOp::HereDoc
+            || ans.redirect_type == RedirectOp::HereStr {
                 ans.left_fd = 0;
-            }else if ans.redirect_type == RedirectOp::Output {
+            }else if ans.redirect_type == RedirectOp::Output || ans.redirect_type == RedirectOp::Clobber
+            || ans.redirect_type == RedirectOp::Append || ans.redirect_type == RedirectOp::OutputAnd {
                 ans.left_fd = 1;
             }
         }
@@ -80,10 +153,13 @@ impl Redirect {
             ans.text += &a.text.clone();
             ans.right_word = Some(a);
         }else{
-            text.rewind(backup);
+            Redirect::syntax_error(text, conf);
             return None;
         };
 
+        if ans.redirect_type == RedirectOp::HereDoc {
+            Redirect::parse_here_doc(text, conf, &mut ans, strip_tabs);
+        }
         Some(ans)
     }
 }
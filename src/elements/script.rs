@@ -15,8 +15,7 @@ impl Script {
         for j in self.list.iter_mut() {
             j.exec(conf);
 
-            if conf.return_flag {
-                conf.return_flag = false;
+            if conf.is_jumping() {
                 return;
             }
         }
@@ -35,6 +34,28 @@ impl Script {
         ( parent == &CommandType::Case && op == &ControlOperator::DoubleSemicolon )
     }*/
 
+    /* parses the commands until one of the ends. Lines are added while the
+       commands are incomplete. None is returned at the end of the input. */
+    pub fn parse_until(text: &mut Feeder, conf: &mut ShellCore, ends: &[&str]) -> Option<Script> {
+        let mut blank = "".to_string();
+        loop {
+            blank += &text.request_next_line(conf);
+
+            let backup = text.clone();
+            if let Some(mut s) = Script::parse(text, conf) {
+                if ends.iter().any(|e| text.starts_with(e)) {
+                    s.text = blank + &s.text;
+                    return Some(s);
+                }
+            }
+
+            text.rewind(backup);
+            if ! text.feed_additional_line(conf) {
+                return None;
+            }
+        }
+    }
+
     pub fn parse(text: &mut Feeder, conf: &mut ShellCore) -> Option<Script> {
         if text.len() == 0 {
             return None;
@@ -5,12 +5,25 @@ use crate::ShellCore;
 use crate::debuginfo::DebugInfo;
 use crate::Feeder;
 use crate::elements::value::Value;
+use crate::elements::word::Word;
+use crate::elements::subword::variable::eval_subscript;
+use crate::utils::expand_glob;
+
+/* an element of name=(elem ...) */
+#[derive(Debug)]
+pub enum ArrayElem {
+    Word(Word),
+    Keyed(String, Value), // [key]=value. The value is not split like name=value.
+}
 
 #[derive(Debug)]
 pub struct Substitution {
     pub text: String,
     pub name: String,
+    pub subscript: Option<String>,
     pub value: Value,
+    pub append: bool, // name+=value
+    pub elems: Option<Vec<ArrayElem>>, // name=(elem ...)
     pub debug: DebugInfo,
 }
 
@@ -23,38 +36,176 @@ impl Substitution {
         for s in self.value.eval(conf){
             v += &s;
         }
+        let mut v = Word::remove_escape(&v);
+        if self.append {
+            v = self.append_to_old_value(v, conf);
+        }
         ans.push(v);
 
         ans
     }
 
+    /* the elements of name=(elem ...) with the subscripts of [key]=value
+       not evaluated yet. None for a scalar value. */
+    pub fn eval_elems(&mut self, conf: &mut ShellCore) -> Option<Vec<(Option<String>, String)>> {
+        let elems = self.elems.as_mut()?;
+        let mut ans = vec![];
+        for e in elems.iter_mut() {
+            match e {
+                ArrayElem::Word(w) => {
+                    for s in w.eval_fields(conf).iter().flatten() {
+                        let paths = expand_glob(s, conf).unwrap_or(vec!(s.clone()));
+                        ans.extend(paths.iter().map(|p| (None, Word::remove_escape(p))));
+                    }
+                },
+                ArrayElem::Keyed(key, v) => {
+                    ans.push((Some(key.clone()), Word::remove_escape(&v.eval(conf).join(""))));
+                },
+            }
+        }
+        Some(ans)
+    }
+
+    /* The subscripts are evaluated here since a key of an associative
+       array is not an arithmetic expression */
+    pub fn set_elems(&self, elems: &[(Option<String>, String)], conf: &mut ShellCore) -> bool {
+        let elems = elems.iter().map(|(key, value)| {
+            (key.as_ref().map(|k| eval_subscript(&self.name, k, conf)), value.clone())
+        }).collect::<Vec<(Option<String>, String)>>();
+        conf.set_array(&self.name, &elems, self.append)
+    }
+
+    /* an integer variable is added arithmetically, others are concatenated */
+    fn append_to_old_value(&self, value: String, conf: &mut ShellCore) -> String {
+        let key = self.eval_subscript(conf).unwrap_or("0".to_string());
+        let old = conf.get_array_elem(&self.name, &key).unwrap_or_default();
+
+        if self.subscript.is_none() && conf.has_attribute(&self.name, 'i') {
+            let old = if old.is_empty() { "0".to_string() }else{ old };
+            return format!("{}+({})", old, value);
+        }
+        old + &value
+    }
+
+    /* the key of name[key]=value */
+    pub fn eval_subscript(&self, conf: &mut ShellCore) -> Option<String> {
+        self.subscript.as_ref().map(|sub| eval_subscript(&self.name, sub, conf))
+    }
+
     pub fn get_text(&self) -> String { self.text.clone() }
 
-    pub fn new(text: &Feeder, name: String, value: Value) -> Substitution{
+    pub fn new(text: &Feeder, name: String, subscript: Option<String>, append: bool, value: Value) -> Substitution{
+        let sub_text = match &subscript {
+            Some(s) => "[".to_owned() + s + "]",
+            None => "".to_string(),
+        };
+
         Substitution {
-            text: name.clone() + "=" + &value.text.clone(),
+            text: name.clone() + &sub_text + if append {"+="}else{"="} + &value.text.clone(),
             name: name, 
+            subscript,
             value: value,
+            append,
+            elems: None,
             debug: DebugInfo::init(text)
         }
     }
 
+    /* [key]=value in (elem ...) */
+    fn parse_keyed_elem(text: &mut Feeder, conf: &mut ShellCore) -> Option<(String, ArrayElem)> {
+        if ! text.starts_with("[") {
+            return None;
+        }
+
+        let backup = text.clone();
+        let mut ans_text = text.consume(1);
+        let pos = text.scanner_until_closing_brace_or("]");
+        let key = text.consume(pos);
+        if ! text.starts_with("]=") {
+            text.rewind(backup);
+            return None;
+        }
+        ans_text += &key;
+        ans_text += &text.consume(2);
+
+        let value = Value::parse(text, conf).unwrap_or(Value::new());
+        ans_text += &value.text;
+        Some((ans_text, ArrayElem::Keyed(key, value)))
+    }
+
+    /* (elem ...) after = . Newlines are allowed between the elements. */
+    fn parse_elems(text: &mut Feeder, conf: &mut ShellCore) -> Option<(String, Vec<ArrayElem>)> {
+        let mut ans_text = text.consume(1);
+        let mut words = vec![];
+        loop {
+            ans_text += &text.request_next_line(conf);
+            if text.len() == 0 {
+                return None;
+            }
+            if text.starts_with(")") {
+                ans_text += &text.consume(1);
+                return Some((ans_text, words));
+            }
+
+            if let Some((t, e)) = Substitution::parse_keyed_elem(text, conf) {
+                ans_text += &t;
+                words.push(e);
+                continue;
+            }
+
+            let w = Word::parse(text, conf, false)?;
+            ans_text += &w.text;
+            words.push(ArrayElem::Word(w));
+        }
+    }
+
     pub fn parse(text: &mut Feeder, conf: &mut ShellCore) -> Option<Substitution> {
         let backup = text.clone();
         let varname_pos = text.scanner_name(0);
         let var_part = text.consume(varname_pos);//VarName::new(text, varname_pos);
 
+        let mut subscript = None;
+        if varname_pos > 0 && text.starts_with("[") {
+            text.consume(1);
+            let pos = text.scanner_until_closing_brace_or("]");
+            subscript = Some(text.consume(pos));
+            if ! text.starts_with("]") {
+                text.rewind(backup);
+                return None;
+            }
+            text.consume(1);
+        }
+
+        let append = varname_pos > 0 && text.starts_with("+=");
+        if append {
+            text.consume(1);
+        }
+
         if ! text.starts_with("=") {
             text.rewind(backup);
             return None;
         }
         text.consume(1); // consume of "=" 
+
+        if text.starts_with("(") {
+            let (elems_text, elems) = match Substitution::parse_elems(text, conf) {
+                Some(e) => e,
+                None => {
+                    text.rewind(backup);
+                    return None;
+                },
+            };
+            let mut ans = Substitution::new(text, var_part, subscript, append, Value::new());
+            ans.text += &elems_text;
+            ans.elems = Some(elems);
+            return Some(ans);
+        }
  
         if let Some(value_part) = Value::parse(text, conf){
-            Some(Substitution::new(text, var_part, value_part))
+            Some(Substitution::new(text, var_part, subscript, append, value_part))
         }else{ // empty value
             let empty_word = Value::new();
-            Some(Substitution::new(text, var_part, empty_word))
+            Some(Substitution::new(text, var_part, subscript, append, empty_word))
         }
     }
 }
@@ -1,6 +1,7 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+pub mod ansi_c_quoted;
 pub mod braced;
 pub mod command_substitution;
 pub mod double_quoted;
@@ -12,19 +13,21 @@ pub mod tilde;
 pub mod variable;
 
 use crate::{Feeder, ShellCore}; 
+use crate::utils::Field;
 
 use self::command_substitution::SubwordCommandSubstitution;
 use self::math_substitution::SubwordMathSubstitution;
 use self::string_non_quoted::SubwordStringNonQuoted;
 use self::double_quoted::SubwordDoubleQuoted;
 use self::single_quoted::SubwordSingleQuoted;
+use self::ansi_c_quoted::SubwordAnsiCQuoted;
 use self::braced::SubwordBraced;
 use self::variable::SubwordVariable;
 use std::fmt::Debug;
 use std::fmt;
 
 pub trait Subword {
-    fn eval(&mut self, _conf: &mut ShellCore, remove_lf: bool) -> Vec<Vec<String>>;
+    fn eval(&mut self, _conf: &mut ShellCore, remove_lf: bool) -> Vec<Vec<Field>>;
     fn get_text(&self) -> String;
     fn permit_lf(&self) -> bool {false}
 }
@@ -42,20 +45,22 @@ impl Debug for dyn Subword {
 pub fn parse_in_arg(text: &mut Feeder, conf: &mut ShellCore, is_in_brace: bool) -> Option<Box<dyn Subword>> {
     if let Some(a) = SubwordMathSubstitution::parse(text, conf)                   {Some(Box::new(a))}
     else if let Some(a) = SubwordCommandSubstitution::parse(text, conf)           {Some(Box::new(a))}
+    else if let Some(a) = SubwordAnsiCQuoted::parse(text, conf)                   {Some(Box::new(a))}
+    else if let Some(a) = SubwordDoubleQuoted::parse(text, conf)                  {Some(Box::new(a))}
     else if let Some(a) = SubwordVariable::parse(text)                            {Some(Box::new(a))}
     else if let Some(a) = SubwordBraced::parse(text, conf)                        {Some(Box::new(a))}
     else if let Some(a) = SubwordSingleQuoted::parse(text, conf)                  {Some(Box::new(a))}
-    else if let Some(a) = SubwordDoubleQuoted::parse(text, conf)                  {Some(Box::new(a))}
-    else if let Some(a) = SubwordStringNonQuoted::parse(text, is_in_brace, false) {Some(Box::new(a))}
+    else if let Some(a) = SubwordStringNonQuoted::parse(text, is_in_brace, false, conf.shopts.get("extglob")) {Some(Box::new(a))}
     else {None}
 }
 
 pub fn parse_in_value(text: &mut Feeder, conf: &mut ShellCore) -> Option<Box<dyn Subword>> {
     if let Some(a) = SubwordMathSubstitution::parse(text, conf)               {Some(Box::new(a))}
     else if let Some(a) = SubwordCommandSubstitution::parse(text, conf)       {Some(Box::new(a))}
+    else if let Some(a) = SubwordAnsiCQuoted::parse(text, conf)               {Some(Box::new(a))}
+    else if let Some(a) = SubwordDoubleQuoted::parse(text, conf)              {Some(Box::new(a))}
     else if let Some(a) = SubwordVariable::parse(text)                        {Some(Box::new(a))}
     else if let Some(a) = SubwordSingleQuoted::parse(text, conf)              {Some(Box::new(a))}
-    else if let Some(a) = SubwordDoubleQuoted::parse(text, conf)              {Some(Box::new(a))}
-    else if let Some(a) = SubwordStringNonQuoted::parse(text, false, true)    {Some(Box::new(a))}
+    else if let Some(a) = SubwordStringNonQuoted::parse(text, false, true, false) {Some(Box::new(a))}
     else {None}
 }
@@ -0,0 +1,152 @@
+//SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::debuginfo::DebugInfo;
+use crate::ShellCore;
+use crate::utils::Field;
+use crate::Feeder;
+use crate::bash_glob::escape_glob;
+
+use crate::elements::subword::Subword;
+
+/* $'...' */
+#[derive(Debug)]
+pub struct SubwordAnsiCQuoted {
+    pub text: String,
+    pub pos: DebugInfo,
+}
+
+impl Subword for SubwordAnsiCQuoted {
+    fn eval(&mut self, _conf: &mut ShellCore, _: bool) -> Vec<Vec<Field>> {
+        let strip = SubwordAnsiCQuoted::unescape(&self.text[2..self.text.len()-1]);
+        let s = escape_glob(&strip.replace("\\", "\\\\"));
+        vec!(vec!(Some(s)))
+    }
+
+    fn get_text(&self) -> String {
+        self.text.clone()
+    }
+
+    fn permit_lf(&self) -> bool {true}
+}
+
+impl SubwordAnsiCQuoted {
+    /* reads at most max digits of the radix and returns the value and the length */
+    fn read_number(chars: &[char], radix: u32, max: usize) -> (u32, usize) {
+        let mut value = 0;
+        let mut len = 0;
+        while len < max && len < chars.len() {
+            match chars[len].to_digit(radix) {
+                Some(d) => value = value*radix + d,
+                None    => break,
+            }
+            len += 1;
+        }
+        (value, len)
+    }
+
+    pub fn unescape(s: &str) -> String {
+        let chars = s.chars().collect::<Vec<char>>();
+        let mut ans = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] != '\\' || i+1 == chars.len() {
+                ans.push(chars[i]);
+                i += 1;
+                continue;
+            }
+
+            i += 1;
+            let simple = match chars[i] {
+                'a' => Some('\x07'),
+                'b' => Some('\x08'),
+                'e' | 'E' => Some('\x1b'),
+                'f' => Some('\x0c'),
+                'n' => Some('\n'),
+                'r' => Some('\r'),
+                't' => Some('\t'),
+                'v' => Some('\x0b'),
+                '\\' | '\'' | '"' | '?' => Some(chars[i]),
+                _ => None,
+            };
+            if let Some(c) = simple {
+                ans.push(c);
+                i += 1;
+                continue;
+            }
+
+            let (radix, max, skip) = match chars[i] {
+                '0'..='7' => (8, 3, 0),
+                'x' => (16, 2, 1),
+                'u' => (16, 4, 1),
+                'U' => (16, 8, 1),
+                'c' if i+1 < chars.len() && chars[i+1].is_ascii() => {
+                    let c = ((chars[i+1].to_ascii_uppercase() as u8) ^ 0x40) as char;
+                    if c == '\0' { // \c@ is NUL
+                        return ans;
+                    }
+                    ans.push(c);
+                    i += 2;
+                    continue;
+                },
+                _ => {
+                    ans.push('\\');
+                    continue;
+                },
+            };
+
+            let (value, len) = SubwordAnsiCQuoted::read_number(&chars[i+skip..], radix, max);
+            if len == 0 { // e.g. \x without digits
+                ans.push('\\');
+                continue;
+            }
+            let value = if radix == 8 { value & 0xff }else{ value };
+            if value == 0 { // the string ends at NUL like bash
+                return ans;
+            }
+            if let Some(c) = char::from_u32(value) {
+                ans.push(c);
+            }
+            i += skip + len;
+        }
+        ans
+    }
+
+    /* the position of the closing quote, or None if it is not in the feeder */
+    fn scanner_closing_quote(text: &mut Feeder) -> Option<usize> {
+        let mut escaped = false;
+        let mut pos = 2;
+        for ch in text.chars_after(2) {
+            if escaped {
+                escaped = false;
+            }else if ch == '\\' {
+                escaped = true;
+            }else if ch == '\'' {
+                return Some(pos);
+            }
+            pos += ch.len_utf8();
+        }
+        None
+    }
+
+    pub fn parse(text: &mut Feeder, core: &mut ShellCore) -> Option<SubwordAnsiCQuoted> {
+        if ! text.starts_with("$'") {
+            return None;
+        };
+
+        loop {
+            if let Some(pos) = SubwordAnsiCQuoted::scanner_closing_quote(text) {
+                return Some(SubwordAnsiCQuoted{text: text.consume(pos+1),
+                                               pos: DebugInfo::init(text)});
+            }
+
+            if !text.feed_additional_line(core){
+                eprintln!("bash: unexpected EOF while looking for matching `''");
+                core.set_var("?", "2");
+                core.interrupted = true; // the whole line is not executed
+                text.consume(text.len());
+                return None;
+            }
+        }
+    }
+}
@@ -9,7 +9,72 @@ use crate::elements::word::Word;
 
 use crate::elements::word::word_in_brace;
 use crate::elements::subword::Subword;
-use crate::utils::combine_with;
+use crate::utils::{combine_with, to_fields, Field};
+
+/* {1..5}, {01..10}, {1..10..2}, {a..e} */
+fn expand_sequence(text: &str) -> Option<Vec<String>> {
+    let elems = text.split("..").collect::<Vec<&str>>();
+    if elems.len() < 2 || elems.len() > 3 {
+        return None;
+    }
+
+    let step = if elems.len() == 3 {
+        match elems[2].parse::<i64>() {
+            Ok(0) => 1,
+            Ok(n) => n.checked_abs()?, // i64::MIN has no abs
+            _ => return None,
+        }
+    }else{
+        1
+    };
+
+    if let (Ok(from), Ok(to)) = (elems[0].parse::<i64>(), elems[1].parse::<i64>()) {
+        let zero_padded = |s: &str| {
+            let digits = s.trim_start_matches(['-', '+']);
+            digits.len() > 1 && digits.starts_with('0')
+        };
+        let width = if zero_padded(elems[0]) || zero_padded(elems[1]) {
+            std::cmp::max(elems[0].len(), elems[1].len())
+        }else{
+            0
+        };
+
+        return Some(sequence(from, to, step).iter()
+                    .map(|n| format!("{:0width$}", n, width = width))
+                    .collect());
+    }
+
+    let to_char = |s: &str| {
+        let chars = s.chars().collect::<Vec<char>>();
+        if chars.len() == 1 && chars[0].is_ascii_alphabetic() {
+            Some(chars[0])
+        }else{
+            None
+        }
+    };
+
+    if let (Some(from), Some(to)) = (to_char(elems[0]), to_char(elems[1])) {
+        return Some(sequence(from as i64, to as i64, step).iter()
+                    .map(|n| ((*n as u8) as char).to_string())
+                    .collect());
+    }
+
+    None
+}
+
+fn sequence(from: i64, to: i64, step: i64) -> Vec<i64> {
+    let mut ans = vec![];
+    let mut n = from;
+    while (from <= to && n <= to) || (from > to && n >= to) {
+        ans.push(n);
+        let next = if from <= to { n.checked_add(step) }else{ n.checked_sub(step) };
+        match next {
+            Some(m) => n = m,
+            None => break, // beyond the range of i64
+        }
+    }
+    ans
+}
 
 #[derive(Debug)]
 pub struct SubwordBraced {
@@ -17,14 +82,19 @@ pub struct SubwordBraced {
     pub pos: DebugInfo,
     pub words: Vec<Word>,
     pub complete: bool,
+    pub sequence: Option<Vec<String>>,
 }
 
 impl Subword for SubwordBraced {
-    fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
+    fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<Field>> {
+        if let Some(seq) = &self.sequence {
+            return seq.iter().map(|s| vec!(Some(s.clone()))).collect();
+        }
+
         if self.complete {
             self.eval_complete(conf)
         }else{
-            self.eval_incomplete(conf)
+            to_fields(self.eval_incomplete(conf))
         }
     }
 
@@ -42,13 +112,14 @@ impl SubwordBraced {
             pos: DebugInfo::init(text),
             words: vec![],
             complete: false,
+            sequence: None,
         }
     }
 
-    fn eval_complete(&mut self, conf: &mut ShellCore) -> Vec<Vec<String>> {
+    fn eval_complete(&mut self, conf: &mut ShellCore) -> Vec<Vec<Field>> {
         let mut ans = vec![];
         for word in &mut self.words {
-            ans.push(word.eval(conf));
+            ans.push(word.eval_fields(conf));
         };
         ans
     }
@@ -56,18 +127,26 @@ impl SubwordBraced {
     fn eval_incomplete(&mut self, conf: &mut ShellCore) -> Vec<Vec<String>> {
         if self.words.len() == 0 {
             return vec!(vec!(self.text.clone()));
-        }else if self.words.len() == 1 {
-            let mut ans = vec![];
-            let mut v = "{".to_string();
-            v += &self.words[0].eval(conf).join(" ");
-            if let Some(c) = self.text.chars().last() {
-                if c == ',' || c == '}' {
-                    ans.push(v + &c.to_string());
-                }else{
-                    ans.push(v);
+        }else if self.words.len() == 1 { // {a{b,c}} -> {ab} {ac}
+            let tail = match self.text.chars().last() {
+                Some(c) if c == ',' || c == '}' => c.to_string(),
+                _ => "".to_string(),
+            };
+
+            let mut ans = self.words[0].eval_alternatives(conf).into_iter()
+                          .filter(|v| ! v.is_empty())
+                          .map(|v| v.into_iter().map(Option::unwrap_or_default).collect::<Vec<String>>())
+                          .collect::<Vec<Vec<String>>>();
+            if ans.is_empty() {
+                return vec!(vec!("{".to_string() + &tail));
+            }
+            for v in &mut ans {
+                v[0] = "{".to_owned() + &v[0];
+                if let Some(last) = v.last_mut() {
+                    *last += &tail;
                 }
             }
-            return vec!(ans);
+            return ans;
         }
 
         let mut ans = vec![];
@@ -119,6 +198,9 @@ impl SubwordBraced {
         };
 
         if ans.words.len() < 2 {
+            if ans.complete {
+                ans.sequence = expand_sequence(&ans.words[0].text);
+            }
             ans.complete = false;
             return Some(ans);
         }
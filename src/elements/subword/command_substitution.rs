@@ -8,6 +8,7 @@ use crate::Feeder;
 use crate::elements::subword::Subword;
 use crate::elements::command::Command;
 use crate::elements::command::paren::CommandParen;
+use crate::utils::{split_fields, Field};
 
 #[derive(Debug)]
 pub struct SubwordCommandSubstitution {
@@ -17,24 +18,22 @@ pub struct SubwordCommandSubstitution {
 }
 
 impl Subword for SubwordCommandSubstitution {
-    fn eval(&mut self, conf: &mut ShellCore, remove_lf: bool) -> Vec<Vec<String>> {
+    fn eval(&mut self, conf: &mut ShellCore, remove_lf: bool) -> Vec<Vec<Field>> {
         self.com.substitution = true;
         self.com.exec(conf);
 
+        let text = self.com.substitution_text.replace("\\", "\\\\");
         if ! remove_lf {
-            return vec!(vec!(self.com.substitution_text.clone()));
+            return vec!(vec!(Some(text)));
         }
-        let ans = self.com.substitution_text
-                .split(" ")
-                .map(|x| x.to_string())
-                .collect::<Vec<String>>();
-
-        vec!(ans)
+        vec!(split_fields(&text, conf))
     }
 
     fn get_text(&self) -> String {
         self.text.clone()
     }
+
+    fn permit_lf(&self) -> bool {true}
 }
 
 impl SubwordCommandSubstitution {
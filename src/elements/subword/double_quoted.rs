@@ -9,7 +9,9 @@ use crate::elements::subword::Subword;
 use crate::elements::subword::string_double_quoted::SubwordStringDoubleQuoted;
 use crate::elements::subword::variable::SubwordVariable;
 use crate::elements::subword::command_substitution::SubwordCommandSubstitution;
-use crate::utils::combine;
+use crate::elements::subword::math_substitution::SubwordMathSubstitution;
+use crate::utils::{combine, Field};
+use crate::bash_glob::escape_glob;
 
 #[derive(Debug)]
 pub struct SubwordDoubleQuoted {
@@ -19,7 +21,11 @@ pub struct SubwordDoubleQuoted {
 }
 
 impl Subword for SubwordDoubleQuoted {
-    fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
+    fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<Field>> {
+        if self.subwords.is_empty() { // ""
+            return vec!(vec!(Some("".to_string())));
+        }
+
         conf.in_double_quot = true;
 
         let mut vvv = vec![];
@@ -36,8 +42,7 @@ impl Subword for SubwordDoubleQuoted {
         for ss in strings {
             let mut anselem = vec![];
             for s in ss {
-                let x = s.replace("*", "\\*");
-                anselem.push(x);
+                anselem.push(Some(escape_glob(&s.unwrap_or_default())));
             }
             ans.push(anselem);
         }
@@ -57,7 +62,7 @@ impl Subword for SubwordDoubleQuoted {
 impl SubwordDoubleQuoted {
 /* parser for a string such as "aaa${var}" */
     pub fn parse(text: &mut Feeder, conf: &mut ShellCore) -> Option<SubwordDoubleQuoted> {
-        if ! text.starts_with("\"") {
+        if ! text.starts_with("\"") && ! text.starts_with("$\"") {
             return None;
         };
 
@@ -67,10 +72,18 @@ impl SubwordDoubleQuoted {
             subwords: vec![],
         };
     
+        /* $"..." is for translation with the locale. No message catalog
+           is supported now, so it is treated as a usual double quote. */
+        if text.starts_with("$") {
+            ans.text += &text.consume(1);
+        }
         ans.text += &text.consume(1);
     
         loop {
-            if let Some(a) = SubwordCommandSubstitution::parse(text, conf) {
+            if let Some(a) = SubwordMathSubstitution::parse(text, conf) {
+                ans.text += &a.text.clone();
+                ans.subwords.push(Box::new(a));
+            }else if let Some(a) = SubwordCommandSubstitution::parse(text, conf) {
                 ans.text += &a.text.clone();
                 ans.subwords.push(Box::new(a));
             }else if let Some(a) = SubwordVariable::parse(text) {
@@ -86,6 +99,10 @@ impl SubwordDoubleQuoted {
                 ans.text += &text.consume(1);
                 break;
             }
+
+            if text.len() == 0 { // EOF in the quote
+                return None;
+            }
         }
     
         Some(ans)
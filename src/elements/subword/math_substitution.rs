@@ -3,6 +3,7 @@
 
 use crate::debuginfo::DebugInfo;
 use crate::ShellCore;
+use crate::utils::Field;
 use crate::Feeder;
 
 use crate::elements::subword::Subword;
@@ -18,12 +19,12 @@ pub struct SubwordMathSubstitution {
 }
 
 impl Subword for SubwordMathSubstitution {
-    fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
+    fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<Field>> {
         self.com.substitution = true;
         self.com.exec(conf);
 
 //        if self.is_value {
-            return vec!(vec!(self.com.substitution_text.clone()));
+            return vec!(vec!(Some(self.com.substitution_text.clone())));
  //       }
 
             /*
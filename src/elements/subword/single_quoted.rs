@@ -3,7 +3,9 @@
 
 use crate::debuginfo::DebugInfo;
 use crate::ShellCore;
+use crate::utils::Field;
 use crate::Feeder;
+use crate::bash_glob::escape_glob;
 //use crate::feeder::scanner::*;
 
 use crate::elements::subword::Subword;
@@ -16,15 +18,17 @@ pub struct SubwordSingleQuoted {
 }
 
 impl Subword for SubwordSingleQuoted {
-    fn eval(&mut self, _conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
+    fn eval(&mut self, _conf: &mut ShellCore, _: bool) -> Vec<Vec<Field>> {
         let strip = self.text[1..self.text.len()-1].to_string();
-        let s = strip.replace("\\", "\\\\").replace("*", "\\*"); 
-        vec!(vec!(s))
+        let s = escape_glob(&strip.replace("\\", "\\\\"));
+        vec!(vec!(Some(s)))
     }
 
     fn get_text(&self) -> String {
         self.text.clone()
     }
+
+    fn permit_lf(&self) -> bool {true}
 }
 
 impl SubwordSingleQuoted {
@@ -36,6 +40,10 @@ impl SubwordSingleQuoted {
         let mut pos = text.scanner_until(1, "'");
         while pos == text.len() {
             if !text.feed_additional_line(core){
+                eprintln!("bash: unexpected EOF while looking for matching `''");
+                core.set_var("?", "2");
+                core.interrupted = true; // the whole line is not executed
+                text.consume(text.len());
                 return None;
             }
             pos = text.scanner_until(1, "'");
@@ -3,6 +3,7 @@
 
 use crate::debuginfo::DebugInfo;
 use crate::ShellCore;
+use crate::utils::Field;
 use crate::Feeder;
 //use crate::feeder::scanner::*;
 
@@ -20,11 +21,12 @@ impl Subword for SubwordStringDoubleQuoted {
         self.text.clone()
     }
 
-    fn eval(&mut self, _conf: &mut ShellCore, remove_lf: bool) -> Vec<Vec<String>> {
+    fn eval(&mut self, _conf: &mut ShellCore, remove_lf: bool) -> Vec<Vec<Field>> {
+        let text = SubwordStringDoubleQuoted::escape_backslash(&self.text);
         if remove_lf {
-            vec!(vec!(self.text.replace("\n", " ")))
+            vec!(vec!(Some(text.replace("\n", " "))))
         }else{
-            vec!(vec!(self.text.clone()))
+            vec!(vec!(Some(text)))
         }
     }
 }
@@ -38,10 +40,29 @@ impl SubwordStringDoubleQuoted {
         }
     }
 
+    /* a backslash escapes only $ ` " \ and newline in double quotes. Otherwise
+       it is a literal character, and is escaped for the later processes. */
+    pub fn escape_backslash(text: &str) -> String {
+        let mut ans = String::new();
+        let mut escaped = false;
+        for ch in text.chars() {
+            if escaped && ! "$`\"\\\n".contains(ch) {
+                ans.push('\\');
+            }
+            ans.push(ch);
+            escaped = ! escaped && ch == '\\';
+        }
+        ans
+    }
+
     pub fn parse(text: &mut Feeder, conf: &mut ShellCore) -> Option<SubwordStringDoubleQuoted> {
         let mut pos = text.scanner_double_quoted_word();
         while pos == text.len() {
             if !text.feed_additional_line(conf){
+                eprintln!("bash: unexpected EOF while looking for matching `\"'");
+                conf.set_var("?", "2");
+                conf.interrupted = true; // the whole line is not executed
+                text.consume(text.len());
                 return None;
             }
             pos = text.scanner_double_quoted_word();
@@ -4,6 +4,7 @@
 use crate::debuginfo::DebugInfo;
 use crate::ShellCore;
 use crate::Feeder;
+use crate::utils::Field;
 //use crate::feeder::scanner::*;
 
 use crate::elements::subword::Subword;
@@ -20,13 +21,16 @@ impl Subword for SubwordStringNonQuoted {
         self.text.clone()
     }
 
-    fn eval(&mut self, _conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
+    fn eval(&mut self, _conf: &mut ShellCore, _: bool) -> Vec<Vec<Field>> {
+        if self.text.is_empty() { // the empty alternative of {a,}
+            return vec!(vec!(None));
+        }
         /*
         if self.is_value {
             vec!(vec!(self.text.clone()))
         }else{
         */
-            vec!(vec!(self.text.replace("\n", " ")))
+            vec!(vec!(Some(self.text.replace("\n", " "))))
         //}
     }
 }
@@ -40,8 +44,8 @@ impl SubwordStringNonQuoted {
         }
     }
 
-    pub fn parse(text: &mut Feeder, is_in_brace: bool, ignore_brace: bool) -> Option<SubwordStringNonQuoted> {
-        let pos = text.scanner_non_quoted_word(is_in_brace, ignore_brace);
+    pub fn parse(text: &mut Feeder, is_in_brace: bool, ignore_brace: bool, extglob: bool) -> Option<SubwordStringNonQuoted> {
+        let pos = text.scanner_non_quoted_word(is_in_brace, ignore_brace, extglob);
         if pos == 0{
             None
         }else{
@@ -5,7 +5,7 @@ use crate::debuginfo::DebugInfo;
 use crate::ShellCore;
 use crate::Feeder;
 //use crate::feeder::scanner::*;
-use crate::utils::expand_tilde;
+use crate::utils::{expand_tilde, Field};
 
 use crate::elements::subword::Subword;
 
@@ -20,14 +20,26 @@ impl Subword for SubwordTildePrefix {
         self.text.clone()
     }
 
-    fn eval(&mut self, _conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
-        vec!(vec!(expand_tilde(&self.text).0))
+    fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<Field>> {
+        let dir = match self.text.as_str() {
+            "~+" => "PWD",
+            "~-" => "OLDPWD",
+            _ => return vec!(vec!(Some(expand_tilde(&self.text).0))),
+        };
+
+        match conf.has_var(dir) {
+            true  => vec!(vec!(Some(conf.get_var(dir)))),
+            false => vec!(vec!(Some(self.text.clone()))),
+        }
     }
 }
 
 impl SubwordTildePrefix {
     pub fn parse(text: &mut Feeder, _: bool) -> Option<SubwordTildePrefix> {
         let pos = text.scanner_tilde_prefix();
+        if pos < text.len() && "\"'$".contains(text.nth(pos)) { // a quoted prefix is not expanded
+            return None;
+        }
         if pos != 0 {
             Some( SubwordTildePrefix{text: text.consume(pos), pos: DebugInfo::init(text) } )
         }else{
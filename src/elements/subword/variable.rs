@@ -7,6 +7,52 @@ use crate::Feeder;
 //use crate::feeder::scanner::*;
 
 use crate::elements::subword::Subword;
+use crate::elements::subword::string_double_quoted::SubwordStringDoubleQuoted;
+use crate::elements::value::Value;
+use crate::elements::word::Word;
+use crate::bash_glob::glob_match_ext;
+use crate::calculator::calculate;
+use crate::utils::{split_fields, to_fields, Field};
+use std::process;
+
+/* evaluates the offset and the length in ${name:offset:length} */
+fn eval_arithmetic(expression: &str, conf: &mut ShellCore) -> i64 {
+    let exp = Word::remove_escape(&eval_word(expression, conf));
+    match calculate(&exp, conf) {
+        Ok(n) => n,
+        Err(msg) => {
+            if ! msg.is_empty() {
+                eprintln!("bash: {}: {}", exp.trim(), msg);
+            }
+            0
+        },
+    }
+}
+
+/* expands the word in ${name:-word} etc. */
+pub fn eval_word(word: &str, conf: &mut ShellCore) -> String {
+    let mut feeder = Feeder::new_from(word.to_string());
+    let mut ans = String::new();
+    while feeder.len() != 0 {
+        ans += &feeder.consume_blank();
+        if let Some(mut v) = Value::parse(&mut feeder, conf) {
+            ans += &v.eval(conf).join("");
+        }else if feeder.len() != 0 {
+            let len = feeder.nth(0).len_utf8();
+            ans += &feeder.consume(len);
+        }
+    }
+    ans
+}
+
+/* a string subscript of an associative array is not evaluated as arithmetic */
+pub fn eval_subscript(name: &str, subscript: &str, conf: &mut ShellCore) -> String {
+    if conf.is_assoc(name) {
+        Word::remove_escape(&eval_word(subscript, conf))
+    }else{
+        eval_arithmetic(subscript, conf).to_string()
+    }
+}
 
 #[derive(Debug)]
 pub struct SubwordVariable {
@@ -14,57 +60,408 @@ pub struct SubwordVariable {
     pub name: String,
     pub empty_option: String,
     pub empty_option_string: String,
+    pub remove_option: String,
+    pub remove_pattern: String,
+    pub replace_option: String,
+    pub replace_pattern: String,
+    pub replace_string: String,
+    pub case_option: String,
+    pub case_pattern: String,
+    pub substring_offset: Option<String>,
+    pub substring_length: Option<String>,
+    pub subscript: Option<String>,
+    pub key_list: bool,
+    pub indirect: bool, // ${!name}
+    pub name_list: Option<String>, // "*" or "@" of ${!prefix*} and ${!prefix@}
     pub pos: DebugInfo,
 }
 
 impl Subword for SubwordVariable {
-    fn eval(&mut self, conf: &mut ShellCore, _: bool) -> Vec<Vec<String>> {
-        let val = conf.get_var(&self.name);
-
-        if val.len() == 0 {
-            vec!(vec!(self.empty_treat(conf)))
-        }else if self.empty_option == ":+" {
-            vec!(vec!(self.empty_option_string.clone()))
-        }else{
-            vec!(vec!(val))
+    fn eval(&mut self, conf: &mut ShellCore, remove_lf: bool) -> Vec<Vec<Field>> {
+        if ! remove_lf { // in double quotes or in a value of an assignment
+            return to_fields(self.eval_value(conf));
         }
+
+        let words = match self.text.as_str() {
+            "$@" | "$*" | "${@}" | "${*}" if conf.args.len() > 1 => vec!(conf.args[1..].iter()
+                                                 .map(|a| a.replace("\\", "\\\\")).collect()),
+            _ => self.eval_value(conf),
+        };
+
+        words.iter().map(|ws| SubwordVariable::split(ws, conf)).collect()
     }
 
     fn get_text(&self) -> String {
         self.text.clone()
     }
+
+    fn permit_lf(&self) -> bool {true}
 }
 
 impl SubwordVariable {
+    /* word splitting with IFS. None at the boundaries of the
+       words are left only at the both ends. */
+    fn split(words: &[String], conf: &ShellCore) -> Vec<Field> {
+        let mut ans = vec![];
+        for (i, w) in words.iter().enumerate() {
+            let mut fields = split_fields(w, conf);
+            if i+1 < words.len() && fields.len() > 1 && fields[fields.len()-1].is_none() {
+                fields.pop();
+            }
+            if i > 0 && fields.len() > 1 && fields[0].is_none() {
+                fields.remove(0);
+            }
+            ans.append(&mut fields);
+        }
+        ans
+    }
+
+    fn eval_value(&mut self, conf: &mut ShellCore) -> Vec<Vec<String>> {
+        if self.text == "$" { // not followed by a name
+            return vec!(vec!("$".to_string()));
+        }
+
+        if let Some(sep) = self.name_list.clone() {
+            return self.eval_name_list(&sep, conf);
+        }
+        if self.indirect {
+            return self.eval_indirect(conf);
+        }
+
+        if let Some(sub) = self.subscript.clone() {
+            return self.eval_array(&sub, conf);
+        }
+
+        let val = conf.get_var(&self.name);
+        let colon = self.empty_option.starts_with(':');
+        let unset = ! conf.has_var(&self.name) || (colon && val.is_empty());
+
+        if conf.has_flag('u') && self.empty_option.is_empty() && ! conf.has_var(&self.name)
+        && self.name != "@" && self.name != "*" { // set -u
+            let positional = self.name.parse::<usize>().is_ok();
+            eprintln!("bash: {}{}: unbound variable", if positional {"$"}else{""}, self.name);
+            conf.set_var("?", "1");
+            if ! conf.has_flag('i') {
+                conf.run_exit_trap(1);
+                process::exit(1);
+            }
+            return vec!(vec!("".to_string()));
+        }
+
+        match self.empty_option.trim_start_matches(':') {
+            "-" | "=" | "?" if unset => vec!(vec!(self.empty_treat(conf))),
+            "+" if unset => vec!(vec!("".to_string())),
+            "+" => vec!(vec!(eval_word(&self.empty_option_string, conf))),
+            _ => {
+                let val = self.remove(val, conf);
+                let val = self.replace(val, conf);
+                let val = self.convert_case(val, conf);
+                vec!(vec!(self.substring(val, conf).replace("\\", "\\\\")))
+            },
+        }
+    }
+
     pub fn new(text: &mut Feeder) -> SubwordVariable {
         SubwordVariable {
             name: String::new(),
             text: String::new(),
             empty_option: String::new(),
             empty_option_string: String::new(),
+            remove_option: String::new(),
+            remove_pattern: String::new(),
+            replace_option: String::new(),
+            replace_pattern: String::new(),
+            replace_string: String::new(),
+            case_option: String::new(),
+            case_pattern: String::new(),
+            substring_offset: None,
+            substring_length: None,
+            subscript: None,
+            key_list: false,
+            indirect: false,
+            name_list: None,
             pos: DebugInfo::init(text),
         }
     }
 
+    /* ${name[key]}, ${name[@]}, ${!name[@]} */
+    fn eval_array(&self, sub: &str, conf: &mut ShellCore) -> Vec<Vec<String>> {
+        if sub == "@" || sub == "*" {
+            let keys = conf.get_array_keys(&self.name);
+            let words = match self.key_list {
+                true  => keys,
+                false => {
+                    let vals = keys.iter().filter_map(|k| conf.get_array_elem(&self.name, k)).collect::<Vec<String>>();
+                    vals.into_iter().map(|v| self.convert_case(v, conf)).collect()
+                },
+            };
+            let words = words.iter().map(|w| w.replace("\\", "\\\\")).collect::<Vec<String>>();
+
+            return match (sub, words.is_empty()) {
+                (_, true) => vec!(vec!("".to_string())),
+                ("*", _) => vec!(vec!(words.join(" "))),
+                _ => vec!(words),
+            };
+        }
+
+        let key = eval_subscript(&self.name, sub, conf);
+        let val = conf.get_array_elem(&self.name, &key);
+        let colon = self.empty_option.starts_with(':');
+        let unset = val.is_none() || (colon && val == Some("".to_string()));
+
+        match self.empty_option.trim_start_matches(':') {
+            "-" if unset => vec!(vec!(eval_word(&self.empty_option_string, conf))),
+            "+" if unset => vec!(vec!("".to_string())),
+            "+" => vec!(vec!(eval_word(&self.empty_option_string, conf))),
+            _ => vec!(vec!(self.convert_case(val.unwrap_or_default(), conf).replace("\\", "\\\\"))),
+        }
+    }
+
+    /* ${!prefix*} and ${!prefix@}: the names of the variables starting with the prefix */
+    fn eval_name_list(&self, sep: &str, conf: &ShellCore) -> Vec<Vec<String>> {
+        let mut names = conf.vars.keys().chain(conf.assoc_arrays.keys()).cloned()
+                        .chain(std::env::vars().map(|(k, _)| k))
+                        .filter(|k| k.starts_with(&self.name))
+                        .collect::<Vec<String>>();
+        names.sort();
+        names.dedup();
+
+        match (sep, names.is_empty()) {
+            (_, true) => vec!(vec!("".to_string())),
+            ("*", _) => vec!(vec!(names.join(" "))),
+            _ => vec!(names),
+        }
+    }
+
+    /* ${!name}: the value of name is the name of the expanded variable.
+       The value can be an array element like arr[1]. */
+    fn eval_indirect(&mut self, conf: &mut ShellCore) -> Vec<Vec<String>> {
+        let target = match &self.subscript {
+            Some(sub) => {
+                let key = eval_subscript(&self.name, sub, conf);
+                conf.get_array_elem(&self.name, &key).unwrap_or_default()
+            },
+            None if conf.is_array(&self.name) => conf.get_array_elem(&self.name, "0").unwrap_or_default(),
+            None => conf.get_var(&self.name),
+        };
+
+        let (name, subscript) = match target.find('[') {
+            Some(n) if target.ends_with(']') => (target[..n].to_string(), Some(target[n+1..target.len()-1].to_string())),
+            _ => (target.clone(), None),
+        };
+
+        let mut feeder = Feeder::new_from(name.clone());
+        let is_name = ! name.is_empty() && feeder.scanner_name_or_parameter() == name.len();
+        if ! is_name {
+            match target.is_empty() {
+                true  => eprintln!("bash: {}: invalid indirect expansion", self.name),
+                false => eprintln!("bash: {}: invalid variable name", target),
+            }
+            conf.set_var("?", "1");
+            if ! conf.has_flag('i') {
+                conf.run_exit_trap(1);
+                process::exit(1);
+            }
+            return vec!(vec!("".to_string()));
+        }
+
+        let backup = (self.name.clone(), self.subscript.clone());
+        (self.name, self.subscript, self.indirect) = (name, subscript, false);
+        let ans = self.eval_value(conf);
+        (self.name, self.subscript) = backup;
+        self.indirect = true;
+        ans
+    }
+
     fn empty_treat(&self, conf: &mut ShellCore) -> String {
-        let opt: &str = &self.empty_option.clone();
-
-        match opt {
-            "" => "".to_string(),
-            ":-" => self.empty_option_string.clone(),
-            ":=" => {
-                conf.set_var(&self.name, &self.empty_option_string);
-                self.empty_option_string.clone()
+        let in_double_quot = conf.in_double_quot;
+        let mut word = eval_word(&self.empty_option_string, conf);
+        if in_double_quot { // "${a:-\q}" gives \q
+            word = SubwordStringDoubleQuoted::escape_backslash(&word);
+        }
+
+        match self.empty_option.trim_start_matches(':') {
+            "-" => word,
+            "=" => {
+                conf.substitute_var(&self.name, &Word::remove_escape(&word));
+                word
             },
-            ":?" => {
-                eprintln!("bash: {}: {}",self.name.clone(), self.empty_option_string.clone());
+            "?" => {
+                let msg = if word.is_empty() {
+                    if self.empty_option == ":?" {
+                        "parameter null or not set".to_string()
+                    }else{
+                        "parameter not set".to_string()
+                    }
+                }else{
+                    Word::remove_escape(&word)
+                };
+                eprintln!("bash: {}: {}", self.name, msg);
                 conf.set_var("?", "1");
+                if ! conf.has_flag('i') {
+                    conf.run_exit_trap(1);
+                    process::exit(1);
+                }
                 "".to_string()
             },
             _ => "".to_string(),
         }
     }
 
+    /* ${name#pattern}, ${name##pattern}, ${name%pattern}, ${name%%pattern} */
+    fn remove(&self, val: String, conf: &mut ShellCore) -> String {
+        if self.remove_option.is_empty() {
+            return val;
+        }
+
+        let pattern = eval_word(&self.remove_pattern, conf);
+        let extglob = conf.shopts.get("extglob");
+        let chars = val.chars().collect::<Vec<char>>();
+        let len = chars.len();
+        let prefix = self.remove_option.starts_with('#');
+        let longest = self.remove_option.len() == 2;
+
+        let positions: Vec<usize> = if prefix == longest {
+            (0..len+1).rev().collect()
+        }else{
+            (0..len+1).collect()
+        };
+
+        for i in positions {
+            let (head, tail): (String, String) = (chars[..i].iter().collect(), chars[i..].iter().collect());
+            if prefix && glob_match_ext(&pattern, &head, extglob) {
+                return tail;
+            }else if ! prefix && glob_match_ext(&pattern, &tail, extglob) {
+                return head;
+            }
+        }
+
+        val
+    }
+
+    /* ${name/pattern/string}, ${name//pattern/string}, ${name/#pattern/string}, ${name/%pattern/string} */
+    fn replace(&self, val: String, conf: &mut ShellCore) -> String {
+        if self.replace_option.is_empty() {
+            return val;
+        }
+
+        let pattern = eval_word(&self.replace_pattern, conf);
+        let extglob = conf.shopts.get("extglob");
+        let string = Word::remove_escape(&eval_word(&self.replace_string, conf));
+        let chars = val.chars().collect::<Vec<char>>();
+        let len = chars.len();
+        let matches = |from: usize, to: usize| {
+            glob_match_ext(&pattern, &chars[from..to].iter().collect::<String>(), extglob)
+        };
+
+        match self.replace_option.as_str() {
+            "/#" => {
+                for i in (0..len+1).rev() {
+                    if matches(0, i) {
+                        return string + &chars[i..].iter().collect::<String>();
+                    }
+                }
+                return val;
+            },
+            "/%" => {
+                for i in 0..len+1 {
+                    if matches(i, len) {
+                        return chars[..i].iter().collect::<String>() + &string;
+                    }
+                }
+                return val;
+            },
+            _ => {},
+        }
+
+        if pattern.is_empty() {
+            return val;
+        }
+
+        let global = self.replace_option == "//";
+        let mut ans = String::new();
+        let mut from = 0;
+        let mut replaced = false;
+        while from < len {
+            let found = if replaced && ! global {
+                None
+            }else{
+                (from+1..len+1).rev().find(|to| matches(from, *to))
+            };
+
+            if let Some(to) = found {
+                ans += &string;
+                from = to;
+                replaced = true;
+            }else{
+                ans.push(chars[from]);
+                from += 1;
+            }
+        }
+        ans
+    }
+
+    /* ${name^}, ${name^^}, ${name,}, ${name,,}. Only the characters matching
+       the pattern are converted when it is given. */
+    fn convert_case(&self, val: String, conf: &mut ShellCore) -> String {
+        if self.case_option.is_empty() {
+            return val;
+        }
+
+        let pattern = match self.case_pattern.is_empty() {
+            true  => "?".to_string(),
+            false => eval_word(&self.case_pattern, conf),
+        };
+        let extglob = conf.shopts.get("extglob");
+        let all = self.case_option.len() == 2;
+        let upper = self.case_option.starts_with('^');
+
+        let mut ans = String::new();
+        for (i, ch) in val.chars().enumerate() {
+            if (i > 0 && ! all) || ! glob_match_ext(&pattern, &ch.to_string(), extglob) {
+                ans.push(ch);
+                continue;
+            }
+
+            /* a character like ß is kept if it becomes two or more characters */
+            let converted = match upper {
+                true  => ch.to_uppercase().collect::<Vec<char>>(),
+                false => ch.to_lowercase().collect::<Vec<char>>(),
+            };
+            ans.push(if converted.len() == 1 { converted[0] }else{ ch });
+        }
+        ans
+    }
+
+    /* ${name:offset}, ${name:offset:length} */
+    fn substring(&self, val: String, conf: &mut ShellCore) -> String {
+        let offset = match &self.substring_offset {
+            Some(s) => eval_arithmetic(s, conf),
+            None => return val,
+        };
+
+        let chars = val.chars().collect::<Vec<char>>();
+        let len = chars.len() as i64;
+        let from = if offset < 0 { len + offset }else{ offset };
+        if from < 0 || from > len {
+            return "".to_string();
+        }
+
+        let to = match &self.substring_length {
+            Some(s) => {
+                let length = eval_arithmetic(s, conf);
+                if length < 0 { len + length }else{ std::cmp::min(from + length, len) }
+            },
+            None => len,
+        };
+        if to < from {
+            eprintln!("bash: {}: substring expression < 0", self.substring_length.clone().unwrap_or_default().trim());
+            return "".to_string();
+        }
+
+        chars[from as usize..to as usize].iter().collect()
+    }
+
     pub fn parse(text: &mut Feeder) -> Option<SubwordVariable> {
         if text.starts_with("${") {
             return SubwordVariable::parse_in_brace(text);
@@ -87,19 +484,89 @@ impl SubwordVariable {
         let backup = text.clone();
 
         ans.text = text.consume(2);
+        if text.starts_with("!") && text.len() > 1 && text.nth(1) != '}' {
+            ans.indirect = true;
+            ans.text += &text.consume(1);
+        }
         
         let pos = text.scanner_name_or_parameter();
         ans.name = text.consume(pos);
         ans.text += &ans.name.clone();
 
-        if text.starts_with( ":-") || text.starts_with( ":=") 
-            || text.starts_with( ":?") || text.starts_with( ":+") {
-            ans.empty_option = text.consume(2);
+        if text.starts_with("[") {
+            ans.text += &text.consume(1);
+            let pos = text.scanner_until_closing_brace_or("]");
+            let sub = text.consume(pos);
+            ans.text += &sub.clone();
+            if ! text.starts_with("]") {
+                text.rewind(backup);
+                return None;
+            }
+            ans.text += &text.consume(1);
+            ans.subscript = Some(sub);
+        }
+
+        if ans.indirect && (ans.subscript == Some("@".to_string()) || ans.subscript == Some("*".to_string())) {
+            (ans.indirect, ans.key_list) = (false, true);
+        }else if ans.indirect && ans.subscript.is_none() && (text.starts_with("*}") || text.starts_with("@}")) {
+            ans.indirect = false;
+            ans.name_list = Some(text.consume(1));
+            ans.text += &ans.name_list.clone().unwrap();
+        }
+
+        let op_len = if text.starts_with(":") { 2 }else{ 1 };
+        if text.len() >= op_len && "-=?+".contains(text.nth(op_len-1)) {
+            ans.empty_option = text.consume(op_len);
             ans.text += &ans.empty_option.clone();
 
-            let pos = text.scanner_until_escape("}");
+            let pos = text.scanner_until_closing_brace();
             ans.empty_option_string = text.consume(pos);
             ans.text += &ans.empty_option_string.clone();
+        }else if text.starts_with(":") {
+            ans.text += &text.consume(1);
+            let pos = text.scanner_until_closing_brace_or(":");
+            let offset = text.consume(pos);
+            ans.text += &offset.clone();
+            ans.substring_offset = Some(offset);
+
+            if text.starts_with(":") {
+                ans.text += &text.consume(1);
+                let pos = text.scanner_until_closing_brace();
+                let length = text.consume(pos);
+                ans.text += &length.clone();
+                ans.substring_length = Some(length);
+            }
+        }else if text.starts_with("^") || text.starts_with(",") {
+            let len = if text.starts_with("^^") || text.starts_with(",,") { 2 }else{ 1 };
+            ans.case_option = text.consume(len);
+            ans.text += &ans.case_option.clone();
+
+            let pos = text.scanner_until_closing_brace();
+            ans.case_pattern = text.consume(pos);
+            ans.text += &ans.case_pattern.clone();
+        }else if text.starts_with("#") || text.starts_with("%") {
+            let len = if text.starts_with("##") || text.starts_with("%%") { 2 }else{ 1 };
+            ans.remove_option = text.consume(len);
+            ans.text += &ans.remove_option.clone();
+
+            let pos = text.scanner_until_closing_brace();
+            ans.remove_pattern = text.consume(pos);
+            ans.text += &ans.remove_pattern.clone();
+        }else if text.starts_with("/") {
+            let len = if text.starts_with("//") || text.starts_with("/#") || text.starts_with("/%") { 2 }else{ 1 };
+            ans.replace_option = text.consume(len);
+            ans.text += &ans.replace_option.clone();
+
+            let pos = text.scanner_until_closing_brace_or("/");
+            ans.replace_pattern = text.consume(pos);
+            ans.text += &ans.replace_pattern.clone();
+
+            if text.starts_with("/") {
+                ans.text += &text.consume(1);
+                let pos = text.scanner_until_closing_brace();
+                ans.replace_string = text.consume(pos);
+                ans.text += &ans.replace_string.clone();
+            }
         }
 
         if text.starts_with("}") {
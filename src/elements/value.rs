@@ -45,6 +45,13 @@ impl Value {
         while let Some(result) = subword::parse_in_value(text, conf) {
             ans.text += &(*result).get_text();
             ans.subvalues.push(result);
+
+            if ans.text.ends_with(':') {
+                if let Some(result) = SubwordTildePrefix::parse(text, true) {
+                    ans.text += &result.get_text();
+                    ans.subvalues.push(Box::new(result));
+                }
+            }
     
             if text.len() == 0 {
                 break;
@@ -73,7 +80,7 @@ impl Value {
 
         let mut ans = vec![];
         for v in strings {
-            ans.append(&mut v.clone());
+            ans.extend(v.into_iter().map(Option::unwrap_or_default));
         }
         ans
     }
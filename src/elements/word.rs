@@ -2,7 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::ShellCore;
-use crate::utils::combine;
+use crate::utils::{combine, Field};
 use crate::debuginfo::DebugInfo;
 use crate::Feeder;
 use crate::elements::subword;
@@ -18,23 +18,21 @@ pub struct Word {
 }
 
 impl Word {
+    /* removes the backslashes for quoting. A backslash which should be
+       left as a character has been escaped by another one. */
     pub fn remove_escape(text: &String) -> String{
         let mut escaped = false;
         let mut ans = "".to_string();
 
-        let deescape_twordet = |c: char| {
-            "$*\" \\`{};()^<>?[]'!".chars().any(|x| x == c)
-        };
-        
         for ch in text.chars() {
             if escaped || ch != '\\' {
-                if escaped && !deescape_twordet(ch) {
-                    ans.push('\\');
-                }
                 ans.push(ch);
             };
             escaped = !escaped && ch == '\\';
         }
+        if escaped { // a backslash at the end
+            ans.push('\\');
+        }
         ans
     }
 
@@ -82,6 +80,22 @@ impl Word {
     }
 
     pub fn eval(&mut self, conf: &mut ShellCore) -> Vec<String> {
+        self.eval_fields(conf).into_iter()
+            .map(Option::unwrap_or_default)
+            .collect()
+    }
+
+    /* None is left where an unquoted expansion gives nothing */
+    pub fn eval_fields(&mut self, conf: &mut ShellCore) -> Vec<Field> {
+        let mut ans = vec![];
+        for v in self.eval_alternatives(conf) {
+            ans.append(&mut v.clone());
+        }
+        ans
+    }
+
+    /* each element corresponds to an alternative of brace expansion */
+    pub fn eval_alternatives(&mut self, conf: &mut ShellCore) -> Vec<Vec<Field>> {
         let mut subevals = vec![];
         for sa in &mut self.subwords {
             let vs = sa.eval(conf, true);
@@ -91,7 +105,7 @@ impl Word {
                 cvs = vs;
             }else{
                 for v in vs {
-                    let cv = v.iter().map(|s| s.replace("\n", " ")).collect();
+                    let cv = v.iter().map(|s| s.as_ref().map(|s| s.replace("\n", " "))).collect();
                     cvs.push(cv);
                 }
             }
@@ -104,12 +118,7 @@ impl Word {
         for ss in subevals {
             strings = combine(&mut strings, ss);
         }
-
-        let mut ans = vec![];
-        for v in strings {
-            ans.append(&mut v.clone());
-        }
-        ans
+        strings
     }
 
     pub fn get_text(&self) -> String { self.text.clone() }
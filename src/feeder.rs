@@ -3,9 +3,13 @@
 
 mod scanner;
 mod term;
+mod history;
+pub mod words;
 
 use std::io;
 use std::str::Chars;
+use std::rc::Rc;
+use std::cell::RefCell;
 use crate::ShellCore;
 //use crate::term;
 
@@ -16,6 +20,9 @@ pub struct Feeder {
     from_lineno: u32,
     to_lineno: u32,
     pos_in_line: u32,
+    additional_lines: Rc<RefCell<Vec<String>>>, // shared with the backups
+    additional_num: usize, // the number of the additional lines in remaining
+    here_docs: String, // bodies cut from remaining, given back after the newline
 }
 
 impl Feeder {
@@ -25,6 +32,9 @@ impl Feeder {
             from_lineno: 0,
             to_lineno: 0,
             pos_in_line: 0,
+            additional_lines: Rc::new(RefCell::new(vec![])),
+            additional_num: 0,
+            here_docs: String::new(),
         }
     }
 
@@ -71,21 +81,70 @@ impl Feeder {
         }
     }
 
+    /* lines fed after the backup are not lost */
     pub fn rewind(&mut self, backup: Feeder) {
-        self.remaining = backup.remaining.clone();
+        let lines = self.additional_lines.borrow();
+        let start = std::cmp::min(backup.additional_num, lines.len());
+        self.remaining = backup.remaining.clone() + &lines[start..].concat();
         self.from_lineno = backup.from_lineno;
-        self.to_lineno = backup.to_lineno;
+        self.to_lineno = backup.to_lineno + (lines.len() - start) as u32;
         self.pos_in_line = backup.pos_in_line;
+        self.additional_num = lines.len();
+        self.here_docs = backup.here_docs;
     }
 
     pub fn consume(&mut self, cutpos: usize) -> String {
-        let cut = self.remaining[0..cutpos].to_string();
+        let mut cut = self.remaining[0..cutpos].to_string();
         self.pos_in_line += cutpos as u32;
         self.remaining = self.remaining[cutpos..].to_string();
 
+        if ! self.here_docs.is_empty() {
+            if let Some(nl) = cut.find('\n') {
+                cut.insert_str(nl+1, &self.here_docs);
+                self.here_docs.clear();
+            }
+        }
         cut
     }
 
+    /* cuts the body of a here document from the lines after the current line.
+       The cut lines are returned to the text when the current line ends. */
+    pub fn cut_here_document(&mut self, delim: &str, strip_tabs: bool, core: &mut ShellCore) -> String {
+        loop {
+            if let Some(nl) = self.remaining.find('\n') {
+                let mut end = nl + 1;
+                let mut body = String::new();
+                for line in self.remaining[nl+1..].split_inclusive('\n') {
+                    end += line.len();
+                    let line = if strip_tabs { line.trim_start_matches('\t') }else{ line };
+                    if line.trim_end_matches('\n') == delim {
+                        self.here_docs += &self.remaining[nl+1..end];
+                        self.remaining.replace_range(nl+1..end, "");
+                        return body;
+                    }
+                    body += line;
+                }
+            }
+
+            if ! self.feed_additional_line(core) {
+                break;
+            }
+        }
+
+        eprintln!("bash: warning: here-document delimited by end-of-file (wanted `{}')", delim);
+        let nl = match self.remaining.find('\n') {
+            Some(n) => n + 1,
+            None => return String::new(),
+        };
+        let body = self.remaining[nl..].to_string();
+        self.here_docs += &body;
+        self.remaining.truncate(nl);
+        match strip_tabs {
+            true  => body.split_inclusive('\n').map(|l| l.trim_start_matches('\t')).collect(),
+            false => body,
+        }
+    }
+
     pub fn consume_blank(&mut self) -> String {
         let d = self.scanner_blank();
         self.consume(d)
@@ -125,10 +184,36 @@ impl Feeder {
         self.remaining = self.remaining.replacen(from, to, 1);
     }
 
+    /* history expansion is applied before the line is recorded and parsed */
+    fn preprocess_line(line: String, core: &mut ShellCore) -> Option<String> {
+        let line = if core.has_flag('H') {
+            match history::expand(&line, &core.history) {
+                Ok(ln) => {
+                    if ln != line {
+                        eprint!("{}", ln);
+                    }
+                    ln
+                },
+                Err(msg) => {
+                    eprintln!("bash: {}", msg);
+                    return None;
+                },
+            }
+        }else{
+            line
+        };
+
+        let entry = line.trim_end_matches('\n');
+        if core.has_flag('i') && ! entry.is_empty() {
+            core.push_history(entry, None);
+        }
+        Some(line)
+    }
+
     pub fn feed_additional_line(&mut self, core: &mut ShellCore) -> bool {
         //let ret = if core.flags.i {
         let ret = if core.has_flag('i') {
-            let len_prompt = term::prompt_additional();
+            let len_prompt = term::prompt_additional(core);
             if let Some(s) = term::read_line_terminal(len_prompt, core){
                 Some(s)
             }else {
@@ -143,6 +228,12 @@ impl Feeder {
         };
 
         if let Some(line) = ret {
+            let line = match Self::preprocess_line(line, core) {
+                Some(ln) => ln,
+                None => return false,
+            };
+            self.additional_lines.borrow_mut().push(line.clone());
+            self.additional_num += 1;
             self.add_line(line);
             true
         }else{
@@ -166,13 +257,19 @@ impl Feeder {
                 return false;
             }
         };
+        self.additional_lines = Rc::new(RefCell::new(vec![]));
+        self.additional_num = 0;
+        let line = match Self::preprocess_line(line, core) {
+            Some(ln) => ln,
+            None => return true,
+        };
         self.add_line(line);
 
         if self.len_as_chars() < 2 {
             return true;
         }
 
-        while self.from_to_as_chars(self.len_as_chars()-2, self.len_as_chars()) == "\\\n" {
+        while self.ends_with_line_continuation() {
             self.remaining = self.from_to_as_chars(0, self.len_as_chars()-2);
             if !self.feed_additional_line(core){
                 self.remaining = "".to_string();
@@ -182,6 +279,44 @@ impl Feeder {
         true
     }
 
+    /* true if the text ends with an unquoted backslash and a newline.
+       A backslash in a comment is not a line continuation. */
+    fn ends_with_line_continuation(&self) -> bool {
+        if ! self.remaining.ends_with("\\\n") {
+            return false;
+        }
+
+        let mut escaped = false;
+        let mut single_quoted = false;
+        let mut double_quoted = false;
+        let mut comment = false;
+        let mut prev = '\n';
+        for ch in self.remaining.chars() {
+            if comment {
+                comment = ch != '\n';
+            }else if escaped {
+                escaped = false;
+                if ch == '\n' {
+                    return true; // only the last newline reaches here
+                }
+                prev = '\\'; // an escaped blank does not end a word
+                continue;
+            }else if single_quoted {
+                single_quoted = ch != '\'';
+            }else if ch == '\\' {
+                escaped = true;
+            }else if ch == '\'' && ! double_quoted {
+                single_quoted = true;
+            }else if ch == '"' {
+                double_quoted = ! double_quoted;
+            }else if ch == '#' && ! double_quoted && " \t\n;&|()<>".contains(prev) {
+                comment = true; // # at the head of a word
+            }
+            prev = ch;
+        }
+        false
+    }
+
     fn add_line(&mut self, line: String) {
         self.to_lineno += 1;
 
@@ -195,10 +330,11 @@ impl Feeder {
     }
 
     pub fn request_next_line(&mut self, conf: &mut ShellCore) -> String {
-        let t = self.consume_blank_return();
+        let mut t = self.consume_blank_return();
     
         if self.len() == 0 {
             let _ = self.feed_additional_line(conf);
+            t += &self.consume_blank_return();
         }
 
         t
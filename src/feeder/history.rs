@@ -0,0 +1,119 @@
+//SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use super::words::split_words;
+use crate::core::HistoryEntry;
+
+/* history expansion: !!, !n, !-n, !string and the word designators $ ^ * :n */
+pub fn expand(line: &str, history: &[HistoryEntry]) -> Result<String, String> {
+    let chars = line.chars().collect::<Vec<char>>();
+    let mut ans = String::new();
+    let mut single_quoted = false;
+    let mut double_quoted = false;
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        if c == '\\' && ! single_quoted && pos+1 < chars.len() {
+            ans.push(c);
+            ans.push(chars[pos+1]);
+            pos += 2;
+            continue;
+        }
+
+        if c == '\'' && ! double_quoted {
+            single_quoted = ! single_quoted;
+        }else if c == '"' && ! single_quoted {
+            double_quoted = ! double_quoted;
+        }
+
+        let next = chars.get(pos+1).copied().unwrap_or('\n');
+        let after_dollar = ans.ends_with('$') || ans.ends_with("${"); // $! and ${!name}
+        if c != '!' || single_quoted || after_dollar || " \t\n=(".contains(next)
+        || ( double_quoted && next == '"' ) {
+            ans.push(c);
+            pos += 1;
+            continue;
+        }
+
+        let (len, text) = expand_event(&chars[pos..], history)?;
+        ans += &text;
+        pos += len;
+    }
+
+    Ok(ans)
+}
+
+/* returns the length of the event with its designator and the replacement */
+fn expand_event(chars: &[char], history: &[HistoryEntry]) -> Result<(usize, String), String> {
+    let mut pos = 1;
+    let entry = match chars[1] {
+        '!' => {
+            pos = 2;
+            history.last()
+        },
+        '$' | '^' | '*' | ':' => history.last(),
+        '-' | '0'..='9' => {
+            pos = number_end(chars, 2);
+            let event = chars[1..pos].iter().collect::<String>();
+            match event.parse::<i64>() {
+                Ok(n) if n > 0 => history.get(n as usize - 1),
+                Ok(n) if n < 0 && (-n) as usize <= history.len() => history.get(history.len() - (-n) as usize),
+                _ => None,
+            }
+        },
+        _ => {
+            while pos < chars.len() && ! " \t\n;&|()<>\"':".contains(chars[pos]) {
+                pos += 1;
+            }
+            let prefix = chars[1..pos].iter().collect::<String>();
+            if prefix.is_empty() {
+                return Ok((1, "!".to_string()));
+            }
+            history.iter().rev().find(|h| h.line.starts_with(&prefix))
+        },
+    };
+
+    let entry = match entry {
+        Some(e) => &e.line,
+        None => return Err(format!("{}: event not found", chars[..pos].iter().collect::<String>())),
+    };
+
+    let start = pos;
+    let designator = match chars.get(pos) {
+        Some('$') | Some('^') | Some('*') => {
+            pos += 1;
+            chars[start..pos].iter().collect::<String>()
+        },
+        Some(':') if chars.get(pos+1).is_some_and(|c| "$^*0123456789".contains(*c)) => {
+            pos = number_end(chars, pos+1);
+            if pos == start+1 {
+                pos += 1;
+            }
+            chars[start+1..pos].iter().collect::<String>()
+        },
+        _ => return Ok((pos, entry.clone())),
+    };
+
+    let words = split_words(entry).into_iter().map(|w| w.text)
+                .filter(|w| ! w.is_empty()).collect::<Vec<String>>();
+    let word = match designator.as_ref() {
+        "$" => words.last().cloned(),
+        "^" => words.get(1).cloned(),
+        "*" => Some(words.iter().skip(1).cloned().collect::<Vec<String>>().join(" ")),
+        n   => n.parse::<usize>().ok().and_then(|n| words.get(n)).cloned(),
+    };
+
+    match word {
+        Some(w) => Ok((pos, w)),
+        None => Err(format!("{}: bad word specifier", chars[start..pos].iter().collect::<String>())),
+    }
+}
+
+fn number_end(chars: &[char], from: usize) -> usize {
+    let mut pos = from;
+    while pos < chars.len() && chars[pos].is_ascii_digit() {
+        pos += 1;
+    }
+    pos
+}
@@ -19,6 +19,61 @@ impl Feeder {
         pos
     }
     
+    /* the end of the word in ${name:-word} */
+    pub fn scanner_until_closing_brace(&mut self) -> usize {
+        self.scanner_until_closing_brace_or("")
+    }
+
+    /* the end of the pattern in ${name/pattern/string} */
+    pub fn scanner_until_closing_brace_or(&mut self, to: &str) -> usize {
+        let mut pos = 0;
+        let mut depth = 0;
+        let mut escaped = false;
+        let mut quote = None;
+        for ch in self.chars_after(0) {
+            if escaped {
+                escaped = false;
+            }else if ch == '\\' && quote != Some('\'') {
+                escaped = true;
+            }else if let Some(q) = quote {
+                if ch == q {
+                    quote = None;
+                }
+            }else if ch == '\'' || ch == '"' {
+                quote = Some(ch);
+            }else if depth == 0 && to.contains(ch) {
+                break;
+            }else if ch == '{' {
+                depth += 1;
+            }else if ch == '}' {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            }
+            pos += ch.len_utf8();
+        }
+        pos
+    }
+
+    /* the end of the expression in ((expression)) */
+    pub fn scanner_until_closing_paren(&mut self) -> usize {
+        let mut pos = 0;
+        let mut depth = 0;
+        for ch in self.chars_after(0) {
+            if ch == '(' {
+                depth += 1;
+            }else if ch == ')' {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            }
+            pos += ch.len_utf8();
+        }
+        pos
+    }
+
     pub fn scanner_until(&mut self, from: usize, to: &str) -> usize {
         let mut pos = from;
         for ch in self.chars_after(from) {
@@ -51,10 +106,49 @@ impl Feeder {
         pos
     }
 
-    pub fn scanner_non_quoted_word(&mut self, in_brace: bool, ignore_brace: bool) -> usize {
+    /* the length of ?(..), *(..), +(..), @(..) or !(..) of extglob at the head */
+    fn scanner_extglob_group(s: &str) -> usize {
+        if ! s.starts_with(|c| "?*+@!".contains(c)) || ! s[1..].starts_with('(') {
+            return 0;
+        }
+
+        let mut depth = 0;
+        let mut escaped = false;
+        for (i, ch) in s.char_indices().skip(1) {
+            if escaped {
+                escaped = false;
+            }else if ch == '\\' {
+                escaped = true;
+            }else if ch == '(' {
+                depth += 1;
+            }else if ch == ')' {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }else if ch == '\n' {
+                break;
+            }
+        }
+        0
+    }
+
+    pub fn scanner_non_quoted_word(&mut self, in_brace: bool, ignore_brace: bool, extglob: bool) -> usize {
         let mut escaped = false;
         let mut pos = 0;
-        for ch in self.remaining.chars() {
+        for (i, ch) in self.remaining.char_indices() {
+            if i < pos { // in an extglob group
+                continue;
+            }
+
+            if extglob && ! escaped {
+                let len = Feeder::scanner_extglob_group(&self.remaining[i..]);
+                if len > 0 {
+                    pos += len;
+                    continue;
+                }
+            }
+
             if escaped {
                 escaped = false;
                 pos += ch.len_utf8();
@@ -79,6 +173,9 @@ impl Feeder {
             }
 
             pos += ch.len_utf8();
+            if ignore_brace && ch == ':' { // a tilde prefix can follow it in a value
+                break;
+            }
         }
 
         pos
@@ -122,10 +219,14 @@ impl Feeder {
             return (2, Some(RedirectOp::HereDoc));
         }else if self.starts_with(">&") {
             return (2, Some(RedirectOp::OutputAnd));
+        }else if self.starts_with("<&") {
+            return (2, Some(RedirectOp::InputAnd));
         }else if self.starts_with("&>") {
             return (2, Some(RedirectOp::AndOutput));
         }else if self.starts_with("<>") {
             return (2, Some(RedirectOp::InOut));
+        }else if self.starts_with(">|") {
+            return (2, Some(RedirectOp::Clobber));
         }else if self.starts_with(">") {
             return (1, Some(RedirectOp::Output));
         }else if self.starts_with("<") {
@@ -139,7 +240,7 @@ impl Feeder {
             return from;
         }
     
-        let h = &self.nth(0);
+        let h = &self.nth(from);
         if !((*h >= 'A' && *h <= 'Z') || (*h >= 'a' && *h <= 'z') || *h == '_') {
             return from;
         }
@@ -239,33 +340,6 @@ impl Feeder {
         0
     }
 
-    pub fn scanner_integer(&mut self) -> usize {
-        if self.len() == 0 {
-            return 0;
-        }
-    
-        let mut pos = 0;
-        let mut minus = false;
-        if self.starts_with("-") {
-            pos += 1;
-            minus = true;
-        }
-    
-        for ch in self.chars_after(pos) {
-            if ch < '0' || ch > '9' {
-                break;
-            }
-    
-            pos += 1;
-        }
-    
-        if minus && pos == 1 {
-            0
-        }else{
-            pos
-        }
-    }
-
     fn scanner_parameter(&mut self, from: usize) -> usize {
         if self.len() < from {
             return from;
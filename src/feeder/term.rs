@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 pub mod completion;
+pub mod vi;
 
 use std::io;
 use std::env;
@@ -15,12 +16,15 @@ use termion::raw::{IntoRawMode, RawTerminal};
 use termion::input::TermRead;
 
 use crate::ShellCore;
+use crate::core::HistoryEntry;
 use completion::*;
+use vi::ViState;
 
-use crate::utils::chars_to_string;
+use crate::utils::{chars_to_string, is_history_timestamp};
+use crate::feeder::words::{LineWord, split_words};
 
 extern crate unicode_width;
-use unicode_width::UnicodeWidthStr;
+use unicode_width::UnicodeWidthChar;
 extern crate rev_lines;
 use rev_lines::RevLines;
 
@@ -32,12 +36,65 @@ pub struct Writer {
     pub erased_line_num: usize,
     ch_ptr: usize,
     hist_ptr: i32,
+    hist_file: String,
     left_shift: u16,
+    width: u32, // the terminal width used for fold_points
+    undo_stack: Vec<(Vec<char>, usize)>, // chars and ch_ptr before each edit
+    inserting: bool, // consecutive insertions are undone at once
 }
 
+const BRACKETED_PASTE_ON: &str = "\x1b[?2004h";
+const BRACKETED_PASTE_OFF: &str = "\x1b[?2004l";
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/* 0 for combining marks and control characters, 2 for CJK and emoji */
 fn char_to_width(c: char) -> u8{
-    let s: &str = &c.to_string();
-    UnicodeWidthStr::width(s) as u8
+    UnicodeWidthChar::width(c).unwrap_or(0) as u8
+}
+
+/* combining marks. Control characters like newlines are not included. */
+fn is_zero_width(c: char) -> bool {
+    char_to_width(c) == 0 && ! c.is_control()
+}
+
+/* moves ptr by inc characters. Zero width characters
+   (combining marks) are skipped together with the preceding one. */
+fn moved_char_ptr(chars: &[char], mut ptr: usize, inc: i32) -> usize {
+    for _ in 0..inc.abs() {
+        if inc > 0 && ptr < chars.len() {
+            ptr += 1;
+            while ptr < chars.len() && is_zero_width(chars[ptr]) {
+                ptr += 1;
+            }
+        }else if inc < 0 && ptr > 0 {
+            ptr -= 1;
+            while ptr > 0 && is_zero_width(chars[ptr]) {
+                ptr -= 1;
+            }
+        }
+    }
+    ptr
+}
+
+/* a command name is completed at the head of a line or after a control
+   operator. A word after a redirection operator is always a file name. */
+fn is_command_position(line: &str) -> bool {
+    let mut words = split_words(line);
+    let last = words.pop().unwrap();
+    if last.text.starts_with('.') || last.text.starts_with('/') {
+        return false;
+    }
+
+    match words.last() {
+        None => true,
+        Some(w) => w.is_operator && ! w.text.contains('<') && ! w.text.contains('>'),
+    }
+}
+
+/* a newline in the buffer needs a carriage return in the raw mode */
+fn to_display(text: &str) -> String {
+    text.replace('\n', "\r\n")
 }
 
 fn chars_to_width(chars: &Vec<char>) -> u32 {
@@ -47,7 +104,7 @@ fn chars_to_width(chars: &Vec<char>) -> u32 {
 }
 
 impl Writer {
-    pub fn new(hist_len: usize, left_shift: u16) -> Writer{
+    pub fn new(hist_len: usize, hist_file: String, left_shift: u16) -> Writer{
         Writer {
             stdout: stdout().into_raw_mode().unwrap(),
             chars: vec![],
@@ -56,7 +113,11 @@ impl Writer {
             erased_line_num: 0,
             ch_ptr: 0,
             hist_ptr: hist_len as i32,
+            hist_file,
             left_shift: left_shift,
+            width: terminal_size().map(|(wx, _)| wx as u32).unwrap_or(0),
+            undo_stack: vec![],
+            inserting: false,
         }
     }
 
@@ -64,7 +125,7 @@ impl Writer {
         write!(self.stdout, "{}{}{}",
                termion::cursor::Goto(self.left_shift+1, y),
                termion::clear::UntilNewline,
-               text).unwrap();
+               to_display(&text)).unwrap();
         self.stdout.flush().unwrap();
     }
 
@@ -99,27 +160,27 @@ impl Writer {
     }
 
     pub fn call_history_from_file(&mut self) -> String {
-        let home = env::var("HOME").expect("HOME is not defined");
         let pos = - self.hist_ptr - 1;
 
-        if let Ok(hist_file) = File::open(home + "/.bash_history"){
-            let mut rev_lines = RevLines::new(BufReader::new(hist_file)).unwrap();
-            if let Some(s) = rev_lines.nth(pos as usize) {
-                return s;
-            };
+        if let Ok(hist_file) = File::open(&self.hist_file){
+            if let Ok(rev_lines) = RevLines::new(BufReader::new(hist_file)) {
+                if let Some(s) = rev_lines.filter(|l| ! is_history_timestamp(l)).nth(pos as usize) {
+                    return s;
+                };
+            }
         };
 
         "".to_string()
     }
 
-    pub fn call_history(&mut self, inc: i32, history: &Vec<String>){
+    pub fn call_history(&mut self, inc: i32, history: &[HistoryEntry]){
         self.hist_ptr += inc;
         let len = history.len() as i32;
 
         let h = if self.hist_ptr < 0 {
             self.call_history_from_file()
         }else if self.hist_ptr < len {
-            history[self.hist_ptr as usize].to_string()
+            history[self.hist_ptr as usize].line.clone()
         }else{
             self.hist_ptr = len;
             "".to_string()
@@ -134,15 +195,7 @@ impl Writer {
     }
 
     pub fn move_char_ptr(&mut self, inc: i32){
-       let pos = self.ch_ptr as i32 + inc; 
-
-       self.ch_ptr = if pos < 0 {
-           0
-       }else if pos > self.chars.len() as i32 {
-           self.chars.len()
-       }else{
-           pos as usize
-       }
+        self.ch_ptr = moved_char_ptr(&self.chars, self.ch_ptr, inc);
     }
 
     fn move_cursor_to_head(&mut self) {
@@ -165,6 +218,11 @@ impl Writer {
     fn move_cursor(&mut self, inc: i32) {
         let (_, old_line_no) = self.ch_ptr_to_multiline_origin();
         self.move_char_ptr(inc);
+        self.put_cursor(old_line_no);
+    }
+
+    /* puts the cursor at ch_ptr. old_line_no is the line of the cursor on the terminal */
+    fn put_cursor(&mut self, old_line_no: u16) {
         let (org_x, line_no) = self.ch_ptr_to_multiline_origin();
         let line_len: u16 = chars_to_width(&self.chars[org_x..self.ch_ptr].to_vec()) as u16;
 
@@ -186,30 +244,17 @@ impl Writer {
         self.stdout.flush().unwrap();
     }
 
-    pub fn last_word(&self) -> String {
-        let mut escaped = false;
-        let mut pos = 0;
-        let mut counter = 0;
-        for ch in self.chars.clone() {
-            if escaped{
-                escaped = false;
-                counter += 1;
-                continue;
-            }else if ch == '\\' {
-                escaped = true;
-            }
-
-            if !escaped && ch == ' '{
-                pos = counter+1;
-            }
-            counter += 1;
-        }
+    pub fn last_line_word(&self) -> LineWord {
+        split_words(&chars_to_string(&self.chars)).pop().unwrap()
+    }
 
-        chars_to_string(&self.chars[pos..].to_vec())
+    pub fn last_word(&self) -> String {
+        self.last_line_word().text
     }
 
     fn calculate_fold_points(&mut self){
         let (wx, _) = self.terminal_size();
+        self.width = wx;
         self.previous_fold_points_num = self.fold_points.len();
         self.fold_points.clear();
 
@@ -217,6 +262,13 @@ impl Writer {
         let mut sum_length: u32 = 0;
         let mut shift = self.left_shift;
         for ch in &self.chars {
+            if *ch == '\n' { // a pasted newline starts the next row
+                shift = 0;
+                sum_length = 0;
+                self.fold_points.push(i);
+                i += 1;
+                continue;
+            }
             sum_length += char_to_width(*ch) as u32;
 
             if wx < sum_length + shift as u32 {
@@ -229,14 +281,18 @@ impl Writer {
     }
 
     fn tab_completion(&mut self, tab_num: u32, core: &mut ShellCore) {
-        if chars_to_string(&self.chars) == self.last_word() && 
-            self.last_word().chars().nth(0) != Some('.') &&
-            self.last_word().chars().nth(0) != Some('/') {
+        if is_command_position(&chars_to_string(&self.chars)) {
             if tab_num == 1 {
                 command_completion(self, core);
             }else {
                 show_command_candidates(self, core);
             };
+        }else if let Some(candidates) = wordlist_candidates(self, core) {
+            if tab_num == 1 {
+                wordlist_completion(self, &candidates);
+            }else {
+                show_wordlist_candidates(self, core, &candidates);
+            };
         }else{
             if tab_num == 1 {
                 file_completion(self);
@@ -287,24 +343,76 @@ impl Writer {
         self.stdout.flush().unwrap();
     }
 
+    /* the line is folded again and repainted when the width of the terminal has changed */
+    fn check_resize(&mut self) {
+        if self.terminal_size().0 == self.width {
+            return;
+        }
+
+        self.calculate_fold_points();
+        let (_, org_y) = self.ch_ptr_to_multiline_origin();
+        if self.cursor_pos().1 <= org_y { // the head of the line has been scrolled out
+            write!(self.stdout, "{}{}{}", termion::cursor::Goto(1, 1),
+                   termion::clear::CurrentLine, termion::cursor::Goto(1, org_y + 1)).unwrap();
+        }
+        self.rewrite_multi_line(org_y);
+    }
+
+    fn save_undo(&mut self, insertion: bool) {
+        if ! insertion || ! self.inserting {
+            self.undo_stack.push((self.chars.clone(), self.ch_ptr));
+        }
+        self.inserting = insertion;
+    }
+
+    fn undo(&mut self) {
+        self.inserting = false;
+        let (chars, ch_ptr) = match self.undo_stack.pop() {
+            Some(state) => state,
+            None => return,
+        };
+
+        let (_, old_org_y) = self.ch_ptr_to_multiline_origin();
+        self.chars = chars;
+        self.ch_ptr = ch_ptr;
+        self.calculate_fold_points();
+        self.rewrite_multi_line(old_org_y);
+    }
+
+    fn delete_range(&mut self, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+
+        self.save_undo(false);
+        let (_, old_org_y) = self.ch_ptr_to_multiline_origin();
+        self.chars.drain(start..end);
+        self.ch_ptr = start;
+        self.calculate_fold_points();
+        self.rewrite_multi_line(old_org_y);
+    }
+
     fn remove(&mut self) {
-        if self.chars.len() == 0 {
+        if self.ch_ptr == 0 {
             return;
         };
 
+        self.save_undo(false);
         let (_, old_org_y) = self.ch_ptr_to_multiline_origin();
+        let end = self.ch_ptr;
         self.move_char_ptr(-1);
-        self.chars.remove(self.ch_ptr);
+        self.chars.drain(self.ch_ptr..end);
 
         self.rewrite_multi_line(old_org_y);
         self.calculate_fold_points();
     }
 
     pub fn insert(&mut self, c: char) {
+        self.save_undo(true);
         if self.ch_ptr == self.chars.len() {
             self.chars.insert(self.ch_ptr, c);
             self.move_char_ptr(1);
-            let _ = write!(self.stdout, "{}", c);
+            let _ = write!(self.stdout, "{}", to_display(&c.to_string()));
             self.stdout.flush().unwrap();
             self.calculate_fold_points();
             return;
@@ -312,18 +420,24 @@ impl Writer {
             let mut remain = self.chars[self.ch_ptr..].to_vec();
             self.chars = self.chars[0..self.ch_ptr].to_vec();
 
+            let pos = self.ch_ptr;
             remain.insert(0, c);
             self.chars.append(&mut remain.clone());
-            self.move_char_ptr(remain.len() as i32);
-            let _ = write!(self.stdout, "{}", chars_to_string(&remain));
+            self.ch_ptr = self.chars.len();
+            let _ = write!(self.stdout, "{}", to_display(&chars_to_string(&remain)));
             self.stdout.flush().unwrap();
             self.calculate_fold_points();
-            self.move_cursor(-(remain.len() as i32) + 1);
+
+            let (_, old_line_no) = self.ch_ptr_to_multiline_origin();
+            self.ch_ptr = pos;
+            self.move_char_ptr(1);
+            self.put_cursor(old_line_no);
             return;
         }
     }
 
     pub fn insert_multi(&mut self, s: Chars) {
+        self.save_undo(false);
         for ch in s {
             self.chars.push(ch);
             self.move_char_ptr(1);
@@ -334,24 +448,136 @@ impl Writer {
     }
 
     fn end(&mut self, text: &str) {
-        write!(self.stdout, "{}", text).unwrap();
+        write!(self.stdout, "{}{}", text, BRACKETED_PASTE_OFF).unwrap();
     }
 }
 
-pub fn prompt_additional() -> u16 {
-    print!("> ");
+/* PS2, or "> " when it is not set */
+fn additional_prompt(core: &ShellCore) -> (String, u16) {
+    let ps2 = if core.has_var("PS2") { core.get_var("PS2") }else{ "> ".to_string() };
+    render_prompt(&ps2, core)
+}
+
+pub fn prompt_additional(core: &mut ShellCore) -> u16 {
+    let (prompt, width) = additional_prompt(core);
+    print!("{}", prompt);
     io::stdout().flush().unwrap();
-    return 2;
+    width
+}
+
+/* HH:MM:SS for \t in prompts */
+fn local_time() -> String {
+    unsafe {
+        let now = nix::libc::time(std::ptr::null_mut());
+        let mut tm: nix::libc::tm = std::mem::zeroed();
+        nix::libc::localtime_r(&now, &mut tm);
+        format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+    }
+}
+
+/* the home directory at the head of the path is replaced with ~ like bash.
+   The replacement is skipped when HOME is / */
+fn tilde_path(cwd: &str, home: &str) -> String {
+    if home.len() > 1 && (cwd == home || cwd.starts_with(&(home.to_string() + "/"))) {
+        "~".to_string() + &cwd[home.len()..]
+    }else{
+        cwd.to_string()
+    }
+}
+
+/* expands the escape sequences in PS1 and so on. The width of the last line is also returned.
+   The part between \[ and \] is not counted in the width. \? is the status of the last command. */
+pub fn render_prompt(ps: &str, core: &ShellCore) -> (String, u16) {
+    let home = env::var("HOME").unwrap_or_default();
+    let cwd = match env::current_dir() {
+        Ok(p) => p.to_string_lossy().to_string(),
+        Err(_) => "".to_string(),
+    };
+
+    let mut ans = String::new();
+    let mut visible = String::new();
+    let mut non_printing = false;
+    let mut chars = ps.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let piece = if ch != '\\' {
+            ch.to_string()
+        }else{
+            match chars.next() {
+                Some('u') => env::var("USER").unwrap_or_default(),
+                Some('h') => core.get_var("HOSTNAME").split('.').next().unwrap_or("").to_string(),
+                Some('H') => core.get_var("HOSTNAME"),
+                Some('w') => tilde_path(&cwd, &home),
+                Some('W') => {
+                    if ! home.is_empty() && cwd == home {
+                        "~".to_string()
+                    }else{
+                        cwd.rsplit('/').find(|s| ! s.is_empty()).unwrap_or("/").to_string()
+                    }
+                },
+                Some('$') => if nix::unistd::geteuid().is_root() { "#" }else{ "$" }.to_string(),
+                Some('?') => { // the exit status of the last command. Red on failure
+                    let status = core.get_var("?");
+                    if status != "0" {
+                        ans += &format!("\x1b[31m{}\x1b[m", status);
+                        if ! non_printing {
+                            visible += &status;
+                        }
+                        continue;
+                    }
+                    status
+                },
+                Some('n') => "\n".to_string(),
+                Some('t') => local_time(),
+                Some('e') => "\x1b".to_string(),
+                Some('a') => "\x07".to_string(),
+                Some(c) if c.is_digit(8) => { // \033
+                    let mut code = c.to_digit(8).unwrap();
+                    for _ in 0..2 {
+                        match chars.peek().and_then(|c| c.to_digit(8)) {
+                            Some(d) => code = code*8 + d,
+                            None => break,
+                        }
+                        chars.next();
+                    }
+                    char::from_u32(code).unwrap_or('?').to_string()
+                },
+                Some('[') => {
+                    non_printing = true;
+                    continue;
+                },
+                Some(']') => {
+                    non_printing = false;
+                    continue;
+                },
+                Some('\\') => "\\".to_string(),
+                Some(c) => format!("\\{}", c),
+                None => "\\".to_string(),
+            }
+        };
+
+        ans += &piece;
+        if ! non_printing {
+            visible += &piece;
+        }
+    }
+
+    let last_line = visible.rsplit('\n').next().unwrap_or("").to_string();
+    let width = chars_to_width(&last_line.chars().collect());
+    (ans, width as u16)
 }
 
 pub fn prompt_normal(core: &mut ShellCore) -> u16 {
+    if core.has_var("PS1") {
+        let (prompt, width) = render_prompt(&core.get_var("PS1"), core);
+        print!("{}", prompt);
+        io::stdout().flush().unwrap();
+        return width;
+    }
+
     let home = env::var("HOME").unwrap_or("unknown".to_string());
 
     let path = if let Ok(p) = env::current_dir(){
-        p.into_os_string()
-            .into_string()
-            .unwrap()
-            .replace(&home, "~")
+        tilde_path(&p.into_os_string().into_string().unwrap(), &home)
     }else{
         "no_path".to_string()
     };
@@ -368,21 +594,62 @@ pub fn prompt_normal(core: &mut ShellCore) -> u16 {
 }
 
 pub fn read_line_terminal(left: u16, core: &mut ShellCore) -> Option<String>{
-    let mut writer = Writer::new(core.history.len(), left);
+    let mut writer = Writer::new(core.history.len(), core.history_file(), left);
     let mut tab_num = 0;
+    let mut pasting = false;
+    let mut vi = ViState::new();
+    writer.stdout.write_all(BRACKETED_PASTE_ON.as_bytes()).unwrap();
+    writer.stdout.flush().unwrap();
+
+    for e in stdin().events() {
+        writer.check_resize();
+        let c = match e.unwrap() {
+            event::Event::Key(k) => k,
+            event::Event::Unsupported(seq) => {
+                if seq == PASTE_START || seq == PASTE_END {
+                    pasting = seq == PASTE_START;
+                    writer.inserting = false; // a paste is undone at once
+                }
+                continue;
+            },
+            _ => continue,
+        };
+
+        if pasting { // pasted text is inserted as it is until the end marker
+            if let event::Key::Char(ch) = c {
+                writer.insert(ch);
+            }
+            continue;
+        }
+
+        if ! matches!(c, event::Key::Char(_)) || (core.vi_mode && vi.normal) {
+            writer.inserting = false;
+        }
 
-    for c in stdin().keys() {
-        match &c.as_ref().unwrap() {
+        if core.vi_mode {
+            if vi.normal && vi::normal_mode_key(&mut writer, &c, &mut vi, core) {
+                continue;
+            }
+            if ! vi.normal && c == event::Key::Esc {
+                vi.enter_normal(&mut writer);
+                continue;
+            }
+        }
+
+        match &c {
             event::Key::Ctrl('a') => writer.move_cursor_to_head(),
             event::Key::Ctrl('b') => writer.move_cursor(-1),
             event::Key::Ctrl('c') => {
+                core.set_var("?", "130");
                 writer.chars.clear();
                 writer.end("^C\r\n");
                 return None;
             },
             event::Key::Ctrl('e') => writer.move_cursor_to_tail(),
             event::Key::Ctrl('f') => writer.move_cursor(1),
+            event::Key::Ctrl('_') | event::Key::Ctrl('7') => writer.undo(), // termion reads C-_ as C-7
             event::Key::Char('\n') => {
+                writer.move_cursor_to_tail();
                 writer.end("\r\n");
                 break;
             },
@@ -396,7 +663,7 @@ pub fn read_line_terminal(left: u16, core: &mut ShellCore) -> Option<String>{
             _  => {},
         }
 
-        if c.unwrap() != event::Key::Char('\t') {
+        if c != event::Key::Char('\t') {
             tab_num = 0;
         }else{
             tab_num += 1;
@@ -404,8 +671,93 @@ pub fn read_line_terminal(left: u16, core: &mut ShellCore) -> Option<String>{
     }
 
     let ans = chars_to_string(&writer.chars);
-    if ans.len() != 0 {
-        core.history.push(ans.clone());
-    };
     Some(ans + "\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn additional_prompt_default() {
+        let core = ShellCore::new();
+        assert_eq!(additional_prompt(&core), ("> ".to_string(), 2));
+    }
+
+    #[test]
+    fn additional_prompt_custom_ps2() {
+        let mut core = ShellCore::new();
+        core.set_var("PS2", "cont\\$ ");
+        assert_eq!(additional_prompt(&core).1, 6);
+
+        core.set_var("PS2", "\\[\\e[32m\\]>>\\[\\e[m\\] ");
+        assert_eq!(additional_prompt(&core), ("\x1b[32m>>\x1b[m ".to_string(), 3));
+    }
+
+    #[test]
+    fn tilde_path_of_prompt() {
+        assert_eq!(tilde_path("/home/u", "/home/u"), "~");
+        assert_eq!(tilde_path("/home/u/src", "/home/u"), "~/src");
+        assert_eq!(tilde_path("/home/user", "/home/u"), "/home/user");
+        assert_eq!(tilde_path("/tmp", "/"), "/tmp");
+        assert_eq!(tilde_path("/", "/"), "/");
+    }
+
+    #[test]
+    fn char_width() {
+        assert_eq!(char_to_width('a'), 1);
+        assert_eq!(char_to_width('あ'), 2);
+        assert_eq!(char_to_width('漢'), 2);
+        assert_eq!(char_to_width('\u{0301}'), 0); // combining acute accent
+        assert_eq!(char_to_width('\n'), 0);
+        assert!(is_zero_width('\u{0301}'));
+        assert!(! is_zero_width('\n'));
+        assert_eq!(chars_to_width(&"e\u{0301}漢a".chars().collect()), 4);
+    }
+
+    #[test]
+    fn char_ptr_with_combining_marks() {
+        let chars: Vec<char> = "e\u{0301}\u{0300}xあ".chars().collect();
+        assert_eq!(moved_char_ptr(&chars, 0, 1), 3);
+        assert_eq!(moved_char_ptr(&chars, 3, -1), 0);
+        assert_eq!(moved_char_ptr(&chars, 0, 2), 4);
+        assert_eq!(moved_char_ptr(&chars, 4, 1), 5);
+        assert_eq!(moved_char_ptr(&chars, 5, 3), 5);
+        assert_eq!(moved_char_ptr(&chars, 5, -5), 0);
+    }
+
+    #[test]
+    fn command_position() {
+        assert!(is_command_position(""));
+        assert!(is_command_position("ec"));
+        assert!(is_command_position("ls | gr"));
+        assert!(is_command_position("true && ec"));
+        assert!(is_command_position("(ec"));
+        assert!(! is_command_position("cat > READ"));
+        assert!(! is_command_position("cat>READ"));
+        assert!(! is_command_position("cat < READ"));
+        assert!(! is_command_position("cat 2>> READ"));
+        assert!(! is_command_position("cat READ"));
+        assert!(! is_command_position("./a.o"));
+        assert!(! is_command_position("ls; /usr/b"));
+    }
+
+    #[test]
+    fn prompt_with_status() {
+        let mut core = ShellCore::new();
+        assert_eq!(render_prompt("[\\?] ", &core), ("[0] ".to_string(), 4));
+
+        core.set_var("?", "127");
+        assert_eq!(render_prompt("[\\?] ", &core), ("[\x1b[31m127\x1b[m] ".to_string(), 6));
+        assert_eq!(render_prompt("\\[\\?\\]> ", &core), ("\x1b[31m127\x1b[m> ".to_string(), 2));
+    }
+
+    #[test]
+    fn prompt_escapes() {
+        let mut core = ShellCore::new();
+        core.set_var("HOSTNAME", "host.example.com");
+        assert_eq!(render_prompt("\\h:\\H", &core).0, "host:host.example.com");
+        assert_eq!(render_prompt("a\\nbc\\\\ ", &core), ("a\nbc\\ ".to_string(), 4));
+        assert_eq!(render_prompt("\\033[1m\\e\\a\\q", &core).0, "\x1b[1m\x1b\x07\\q");
+    }
+}
@@ -8,6 +8,8 @@ use crate::ShellCore;
 use crate::utils::{eval_glob, search_commands, expand_tilde};
 use crate::feeder::term::Writer;
 use crate::feeder::term::prompt_normal;
+use crate::feeder::words::{LineWord, split_words};
+use crate::bash_glob::glob_expand;
 use std::fs;
 use crate::utils::*;
 
@@ -36,7 +38,43 @@ fn compare_nth_char(nth: usize, strs: &Vec<String>) -> bool {
     true
 }
 
+/* completion of a word whose quote is not closed yet. The quote is closed
+   when only one file matches. */
+fn quoted_file_completion(writer: &mut Writer, word: &LineWord, quote: char) {
+    let typed = word.unquoted();
+    let ans = glob_expand(&(typed.clone() + "*"));
+    if ans.is_empty() {
+        return;
+    }
+
+    let base_len = typed.len();
+    if ans.len() == 1 {
+        let tail = if fs::read_dir(&ans[0]).is_ok() {
+            "/".to_string()
+        }else{
+            quote.to_string() + " "
+        };
+        writer.insert_multi((ans[0][base_len..].to_string() + &tail).chars());
+        return;
+    }
+
+    let mut common = String::new();
+    for (i, ch) in ans[0][base_len..].chars().enumerate() {
+        if ! compare_nth_char(i+base_len, &ans) {
+            break;
+        }
+        common.push(ch);
+    }
+    writer.insert_multi(common.chars());
+}
+
 pub fn file_completion(writer: &mut Writer){
+    let word = writer.last_line_word();
+    if let Some(q) = word.quote {
+        quoted_file_completion(writer, &word, q);
+        return;
+    }
+
     let s: String = writer.last_word().replace("\\", "") + "*";
     let (s, home, org) = expand_tilde(&s);
 
@@ -101,8 +139,11 @@ pub fn file_completion(writer: &mut Writer){
 
 
 pub fn show_file_candidates(writer: &mut Writer, core: &mut ShellCore) {
-    let s: String = writer.last_word().replace("\\", "") + "*";
-    let (s, _, _) = expand_tilde(&s);
+    let word = writer.last_line_word();
+    let s = match word.quote {
+        Some(_) => word.unquoted() + "*",
+        None    => expand_tilde(&(writer.last_word().replace("\\", "") + "*")).0,
+    };
 
     let ans = eval_glob(&s);
     if ans.len() == 0 {
@@ -119,8 +160,58 @@ pub fn show_file_candidates(writer: &mut Writer, core: &mut ShellCore) {
     return;
 }
 
+/* the words registered by complete -W for the command of the last word */
+pub fn wordlist_candidates(writer: &Writer, core: &ShellCore) -> Option<Vec<String>> {
+    let mut words = split_words(&chars_to_string(&writer.chars));
+    let last = words.pop()?.text;
+
+    let is_redirect = |w: &LineWord| w.is_operator && (w.text.contains('<') || w.text.contains('>'));
+    match words.last() {
+        Some(w) if ! is_redirect(w) => {},
+        _ => return None,
+    }
+
+    let head = match words.iter().rposition(|w| w.is_operator && ! is_redirect(w)) {
+        Some(pos) => pos + 1,
+        None => 0,
+    };
+    let list = core.completions.get(&words.get(head)?.text)?;
+    Some(list.split_whitespace().filter(|w| w.starts_with(&last))
+         .map(|w| w.to_string()).collect())
+}
+
+pub fn wordlist_completion(writer: &mut Writer, candidates: &Vec<String>) {
+    let base_len = writer.last_word().len();
+    if candidates.len() == 1 {
+        writer.insert_multi((candidates[0][base_len..].to_string() + " ").chars());
+    }else if candidates.len() > 1 {
+        let mut ans = "".to_string();
+        for (i, ch) in candidates[0][base_len..].chars().enumerate() {
+            if ! compare_nth_char(i+base_len, candidates) {
+                break;
+            }
+            ans.push(ch);
+        }
+        writer.insert_multi(ans.chars());
+    }
+}
+
+pub fn show_wordlist_candidates(writer: &mut Writer, core: &mut ShellCore, candidates: &Vec<String>) {
+    if candidates.is_empty() {
+        return;
+    }
+
+    write!(writer.stdout, "\r\n").unwrap();
+    let ans = align_elems_on_term(candidates, writer.terminal_size().0);
+    write!(writer.stdout, "{}", ans).unwrap();
+    writer.stdout.flush().unwrap();
+    prompt_normal(core);
+    let (_, y) = writer.cursor_pos();
+    writer.rewrite_line(y, writer.chars.iter().collect());
+}
+
 pub fn command_completion(writer: &mut Writer, core: &ShellCore){
-    let s = writer.chars.iter().collect::<String>();
+    let s = writer.last_word();
 
     let mut paths = search_commands(&(s.clone() + &"*"));
     paths.append(&mut search_aliases(&s, core));
@@ -154,7 +245,7 @@ pub fn command_completion(writer: &mut Writer, core: &ShellCore){
 }
 
 pub fn show_command_candidates(writer: &mut Writer, core: &mut ShellCore) {
-    let s = writer.chars.iter().collect::<String>();
+    let s = writer.last_word();
 
     let mut paths = search_commands(&(s.clone() + &"*"));
     paths.append(&mut search_aliases(&s, core));
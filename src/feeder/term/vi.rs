@@ -0,0 +1,158 @@
+//SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use termion::event::Key;
+
+use crate::ShellCore;
+use crate::feeder::term::Writer;
+
+/* the state of the vi editing mode. The insert mode is the initial one. */
+pub struct ViState {
+    pub normal: bool,
+    pending: Option<char>, // an operator like d waiting for its motion
+}
+
+impl ViState {
+    pub fn new() -> ViState {
+        ViState {
+            normal: false,
+            pending: None,
+        }
+    }
+
+    pub fn enter_normal(&mut self, writer: &mut Writer) {
+        self.normal = true;
+        self.pending = None;
+        writer.move_cursor(-1);
+    }
+}
+
+/* the head of the next word. Words are delimited by blanks. */
+fn next_word(chars: &[char], from: usize) -> usize {
+    let mut pos = from;
+    while pos < chars.len() && ! chars[pos].is_whitespace() {
+        pos += 1;
+    }
+    while pos < chars.len() && chars[pos].is_whitespace() {
+        pos += 1;
+    }
+    pos
+}
+
+fn prev_word(chars: &[char], from: usize) -> usize {
+    let mut pos = from;
+    while pos > 0 && chars[pos-1].is_whitespace() {
+        pos -= 1;
+    }
+    while pos > 0 && ! chars[pos-1].is_whitespace() {
+        pos -= 1;
+    }
+    pos
+}
+
+fn move_cursor_to(writer: &mut Writer, pos: usize) {
+    let (_, old_line_no) = writer.ch_ptr_to_multiline_origin();
+    writer.ch_ptr = pos;
+    writer.put_cursor(old_line_no);
+}
+
+/* the cursor doesn't go beyond the last character in the normal mode */
+fn move_right(writer: &mut Writer) {
+    let pos = writer.ch_ptr;
+    writer.move_char_ptr(1);
+    let stop = writer.ch_ptr == writer.chars.len();
+    writer.ch_ptr = pos;
+    if ! stop {
+        writer.move_cursor(1);
+    }
+}
+
+fn stay_on_last_char(writer: &mut Writer) {
+    if writer.ch_ptr > 0 && writer.ch_ptr == writer.chars.len() {
+        writer.move_cursor(-1);
+    }
+}
+
+fn delete_char(writer: &mut Writer) {
+    if writer.ch_ptr < writer.chars.len() {
+        let pos = writer.ch_ptr;
+        writer.move_char_ptr(1);
+        let end = writer.ch_ptr;
+        writer.ch_ptr = pos;
+        writer.delete_range(pos, end);
+    }
+}
+
+/* returns false for the keys that the normal mode doesn't handle */
+pub fn normal_mode_key(writer: &mut Writer, key: &Key, state: &mut ViState, core: &mut ShellCore) -> bool {
+    let ch = match key {
+        Key::Char('\n') => return false,
+        Key::Char(c) => *c,
+        Key::Esc => {
+            state.pending = None;
+            return true;
+        },
+        _ => return false,
+    };
+
+    if let Some(op) = state.pending.take() {
+        match (op, ch) {
+            ('d', 'd') => writer.delete_range(0, writer.chars.len()),
+            ('d', 'w') => {
+                let end = next_word(&writer.chars, writer.ch_ptr);
+                writer.delete_range(writer.ch_ptr, end);
+            },
+            ('d', 'b') => {
+                let start = prev_word(&writer.chars, writer.ch_ptr);
+                writer.delete_range(start, writer.ch_ptr);
+            },
+            ('d', '$') => writer.delete_range(writer.ch_ptr, writer.chars.len()),
+            ('d', '0') => writer.delete_range(0, writer.ch_ptr),
+            _ => {},
+        }
+        stay_on_last_char(writer);
+        return true;
+    }
+
+    match ch {
+        'h' => writer.move_cursor(-1),
+        'l' | ' ' => move_right(writer),
+        'w' => {
+            let pos = next_word(&writer.chars, writer.ch_ptr);
+            if pos < writer.chars.len() {
+                move_cursor_to(writer, pos);
+            }
+        },
+        'b' => {
+            let pos = prev_word(&writer.chars, writer.ch_ptr);
+            move_cursor_to(writer, pos);
+        },
+        '0' | '^' => move_cursor_to(writer, 0),
+        '$' => move_cursor_to(writer, writer.chars.len()),
+        'i' => state.normal = false,
+        'I' => {
+            move_cursor_to(writer, 0);
+            state.normal = false;
+        },
+        'a' => {
+            writer.move_cursor(1);
+            state.normal = false;
+        },
+        'A' => {
+            move_cursor_to(writer, writer.chars.len());
+            state.normal = false;
+        },
+        'x' => delete_char(writer),
+        'D' => writer.delete_range(writer.ch_ptr, writer.chars.len()),
+        'd' => state.pending = Some('d'),
+        'u' => writer.undo(),
+        'k' => writer.call_history(-1, &core.history),
+        'j' => writer.call_history(1, &core.history),
+        _ => {},
+    }
+
+    if state.normal {
+        stay_on_last_char(writer);
+    }
+    true
+}
@@ -0,0 +1,134 @@
+//SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+/* a word of a command line. Its text is kept as written. */
+#[derive(Debug, Clone)]
+pub struct LineWord {
+    pub text: String,
+    pub quote: Option<char>, // the quote which is not closed at the end of the line
+    pub is_operator: bool,   // ; & | ( ) < > and their combinations
+}
+
+impl LineWord {
+    fn new() -> LineWord {
+        LineWord {
+            text: String::new(),
+            quote: None,
+            is_operator: false,
+        }
+    }
+
+    /* the text without the quotes and the backslashes for escaping */
+    pub fn unquoted(&self) -> String {
+        let mut ans = String::new();
+        let mut quote = None;
+        let mut escaped = false;
+        for ch in self.text.chars() {
+            if escaped {
+                escaped = false;
+                if quote == Some('"') && ! "$`\"\\\n".contains(ch) {
+                    ans.push('\\');
+                }
+                ans.push(ch);
+            }else if ch == '\\' && quote != Some('\'') {
+                escaped = true;
+            }else if quote.is_none() && (ch == '\'' || ch == '"') {
+                quote = Some(ch);
+            }else if Some(ch) == quote {
+                quote = None;
+            }else{
+                ans.push(ch);
+            }
+        }
+        ans
+    }
+}
+
+/* splits a line into words at blanks and control/redirection operators
+   outside of quotes. The last word is empty when the line ends with a blank. */
+pub fn split_words(line: &str) -> Vec<LineWord> {
+    let mut ans = vec![];
+    let mut word = LineWord::new();
+    let mut escaped = false;
+
+    for ch in line.chars() {
+        if escaped {
+            escaped = false;
+            word.text.push(ch);
+            continue;
+        }
+
+        if let Some(q) = word.quote {
+            if ch == '\\' && q == '"' {
+                escaped = true;
+            }else if ch == q {
+                word.quote = None;
+            }
+            word.text.push(ch);
+            continue;
+        }
+
+        let is_blank = " \t\n".contains(ch);
+        let is_op = ";&|()<>".contains(ch);
+        if is_blank || is_op != word.is_operator {
+            if ! word.text.is_empty() {
+                ans.push(word);
+            }
+            word = LineWord::new();
+            if is_blank {
+                continue;
+            }
+        }
+
+        if is_op {
+            word.is_operator = true;
+        }else if ch == '\\' {
+            escaped = true;
+        }else if ch == '\'' || ch == '"' {
+            word.quote = Some(ch);
+        }
+        word.text.push(ch);
+    }
+
+    ans.push(word);
+    ans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(line: &str) -> Vec<String> {
+        split_words(line).iter().map(|w| w.text.clone()).collect()
+    }
+
+    #[test]
+    fn split_at_blanks_and_operators() {
+        assert_eq!(texts("echo a  b"), vec!["echo", "a", "b"]);
+        assert_eq!(texts("echo a "), vec!["echo", "a", ""]);
+        assert_eq!(texts("cat>a&&ls|wc"), vec!["cat", ">", "a", "&&", "ls", "|", "wc"]);
+        assert_eq!(texts("cat 2>> a"), vec!["cat", "2", ">>", "a"]);
+
+        let words = split_words("a || b");
+        assert!(! words[0].is_operator);
+        assert!(words[1].is_operator);
+    }
+
+    #[test]
+    fn split_with_quotes() {
+        assert_eq!(texts("echo 'a b' \"c;d\" e\\ f"), vec!["echo", "'a b'", "\"c;d\"", "e\\ f"]);
+
+        let last = split_words("echo \"a b").pop().unwrap();
+        assert_eq!(last.text, "\"a b");
+        assert_eq!(last.quote, Some('"'));
+        assert_eq!(last.unquoted(), "a b");
+    }
+
+    #[test]
+    fn unquoted_text() {
+        let words = split_words("'a\\b' \"c\\d\\$\" e\\ f");
+        assert_eq!(words[0].unquoted(), "a\\b");
+        assert_eq!(words[1].unquoted(), "c\\d$");
+        assert_eq!(words[2].unquoted(), "e f");
+    }
+}
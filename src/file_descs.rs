@@ -1,14 +1,22 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use nix::unistd::{close, dup2};
+use nix::unistd::{close, dup2, pipe, write};
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg, FdFlag};
 use std::os::unix::prelude::RawFd;
 use crate::elements::redirect::Redirect;
 use crate::operators::RedirectOp;
-use std::fs::OpenOptions;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{Seek, SeekFrom, Write};
+use std::{env, process};
 use std::os::unix::io::IntoRawFd;
 use crate::ShellCore;
 
+const PIPE_BUF_SIZE: usize = 4096; // PIPE_BUF. A pipe may hold only a page when the user has many pipes
+
 #[derive(Debug)]
 pub struct FileDescs {
     pub redirects: Vec<Box<Redirect>>,
@@ -34,6 +42,10 @@ impl FileDescs {
             self.prevpipein == -1
     }
 
+    pub fn no_pipe(&self) -> bool {
+        self.pipein == -1 && self.pipeout == -1 && self.prevpipein == -1
+    }
+
     pub fn set_child_io(&mut self, conf: &mut ShellCore) -> Result<(), String> {
         if self.pipein != -1 {
             close(self.pipein).expect("Cannot close in-pipe");
@@ -56,16 +68,39 @@ impl FileDescs {
     }
     
     fn set_redirect(r: &mut Box<Redirect>, conf: &mut ShellCore) -> Result<(), String> {
-        let path = r.eval(conf);
-        if r.redirect_type == RedirectOp::Output /*">"*/ {
-            if let Ok(file) = OpenOptions::new().truncate(true).write(true).create(true).open(&path){
-                Self::dup_and_close(file.into_raw_fd(), r.left_fd);
+        if r.redirect_type == RedirectOp::HereDoc || r.redirect_type == RedirectOp::HereStr {
+            let contents = r.eval_here(conf);
+            let fd = if contents.len() <= PIPE_BUF_SIZE {
+                Self::pipe_contents(&contents)?
             }else{
-                panic!("Cannot open the file: {}", path);
+                Self::temp_file_contents(&contents)?
             };
-        }else if r.redirect_type == RedirectOp::OutputAnd  {
-            if let Ok(n) = path.parse::<i32>() {
-                dup2(n, r.left_fd).expect("Invalid fd");
+            Self::dup_and_close(fd, r.left_fd);
+            return Ok(());
+        }
+
+        let path = r.eval(conf);
+        if r.redirect_type == RedirectOp::Output /*">"*/ || r.redirect_type == RedirectOp::Clobber /*">|"*/ {
+            let noclobber = r.redirect_type == RedirectOp::Output && conf.has_flag('C');
+            match Self::open_output(&path, noclobber) {
+                Ok(file) => Self::dup_and_close(file.into_raw_fd(), r.left_fd),
+                Err(e) => return Err(e),
+            };
+        }else if r.redirect_type == RedirectOp::Append /*">>"*/ {
+            match Self::open_append(&path) {
+                Ok(file) => Self::dup_and_close(file.into_raw_fd(), r.left_fd),
+                Err(e) => return Err(e),
+            };
+        }else if r.redirect_type == RedirectOp::OutputAnd || r.redirect_type == RedirectOp::InputAnd {
+            if path == "-" { // n>&- or n<&-
+                let _ = close(r.left_fd);
+            }else if let Some(n) = Self::dup_source(&path) {
+                if n != r.left_fd && dup2(n, r.left_fd).is_err() {
+                    return Err(format!("bash: {}: Bad file descriptor", n));
+                }
+                if path.ends_with('-') && n != r.left_fd { // n>&m- or n<&m-
+                    let _ = close(n);
+                }
             }else{
                 conf.set_var("?", "1");
                 if let Some(a) = &r.right_word {
@@ -75,25 +110,153 @@ impl FileDescs {
                 }
             }
         }else if r.redirect_type == RedirectOp::AndOutput {
-            if let Ok(file) = OpenOptions::new().truncate(true).write(true).create(true).open(&path){
-                Self::dup_and_close(file.into_raw_fd(), 1);
-                dup2(1, 2).expect("Redirection error on &>");
-            }else{
-                panic!("Cannot open the file: {}", path);
+            match Self::open_output(&path, conf.has_flag('C')) {
+                Ok(file) => {
+                    Self::dup_and_close(file.into_raw_fd(), 1);
+                    dup2(1, 2).expect("Redirection error on &>");
+                },
+                Err(e) => return Err(e),
+            };
+        }else if r.redirect_type == RedirectOp::AndAppend {
+            match Self::open_append(&path) {
+                Ok(file) => {
+                    Self::dup_and_close(file.into_raw_fd(), 1);
+                    dup2(1, 2).expect("Redirection error on &>>");
+                },
+                Err(e) => return Err(e),
+            };
+        }else if r.redirect_type == RedirectOp::InOut /*"<>"*/ {
+            match OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path){
+                Ok(file) => Self::dup_and_close(file.into_raw_fd(), r.left_fd),
+                Err(e) => return Err(Self::open_error(&path, e)),
             };
         }else if r.redirect_type == RedirectOp::Input /*"<"*/ {
-            if let Ok(file) = OpenOptions::new().read(true).open(&path){
-                Self::dup_and_close(file.into_raw_fd(), r.left_fd);
-            }else{
-                panic!("Cannot open the file: {}", path);
+            match OpenOptions::new().read(true).open(&path){
+                Ok(file) => Self::dup_and_close(file.into_raw_fd(), r.left_fd),
+                Err(e) => return Err(Self::open_error(&path, e)),
             };
         }
     
         Ok(())
     }
 
+    /* m of n>&m, n>&m-, n<&m or n<&m- */
+    fn dup_source(path: &str) -> Option<RawFd> {
+        path.strip_suffix('-').unwrap_or(path).parse::<RawFd>().ok()
+    }
+
+    /* with noclobber, an existing regular file is not truncated */
+    fn open_output(path: &str, noclobber: bool) -> Result<File, String> {
+        if ! noclobber {
+            return OpenOptions::new().truncate(true).write(true).create(true).open(path)
+                   .map_err(|e| Self::open_error(path, e));
+        }
+
+        let clobber_error = format!("bash: {}: cannot overwrite existing file", path);
+        match fs::metadata(path) {
+            Ok(m) if m.is_file() => Err(clobber_error),
+            Ok(_) => OpenOptions::new().write(true).open(path).map_err(|e| Self::open_error(path, e)),
+            Err(_) => match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(file) => Ok(file),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(clobber_error),
+                Err(e) => Err(Self::open_error(path, e)),
+            },
+        }
+    }
+
+    /* O_APPEND makes every write go to the end, even with other writers */
+    fn open_append(path: &str) -> Result<File, String> {
+        OpenOptions::new().append(true).create(true).open(path)
+            .map_err(|e| Self::open_error(path, e))
+    }
+
+    /* returns the read end of a pipe that holds the contents.
+       The contents must fit in the pipe because no one reads it yet. */
+    fn pipe_contents(contents: &str) -> Result<RawFd, String> {
+        let (recv, send) = pipe().map_err(|e| format!("bash: cannot make pipe for here-document: {}", e))?;
+
+        let mut data = contents.as_bytes();
+        while ! data.is_empty() {
+            match write(send, data) {
+                Ok(n) => data = &data[n..],
+                Err(Errno::EINTR) => continue,
+                Err(_) => break,
+            }
+        }
+        close(send).expect("Cannot close the pipe");
+        Ok(recv)
+    }
+
+    /* a long document is written to a temporary file, which is removed
+       right after it is opened like bash */
+    fn temp_file_contents(contents: &str) -> Result<RawFd, String> {
+        let err = |e: io::Error| format!("bash: cannot create temp file for here-document: {}", e);
+        let dir = env::var("TMPDIR").unwrap_or("/tmp".to_string());
+        let mut n = 0;
+        let (mut file, path) = loop {
+            let path = format!("{}/sh-thd.{}.{}", dir, process::id(), n);
+            match OpenOptions::new().read(true).write(true).create_new(true).open(&path) {
+                Ok(file) => break (file, path),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => n += 1,
+                Err(e) => return Err(err(e)),
+            }
+        };
+        let _ = fs::remove_file(&path);
+
+        file.write_all(contents.as_bytes()).map_err(err)?;
+        file.seek(SeekFrom::Start(0)).map_err(err)?;
+        Ok(file.into_raw_fd())
+    }
+
+    fn open_error(path: &str, e: io::Error) -> String {
+        let msg = match e.kind() {
+            io::ErrorKind::NotFound => "No such file or directory".to_string(),
+            io::ErrorKind::PermissionDenied => "Permission denied".to_string(),
+            _ => e.to_string(),
+        };
+        format!("bash: {}: {}", path, msg)
+    }
+
+    /* for builtin commands with redirects, which run on the shell process */
+    pub fn backup_fds(&self) -> Vec<(RawFd, Option<RawFd>)> {
+        let mut fds = vec![];
+        for r in &self.redirects {
+            fds.push(r.left_fd);
+            if r.redirect_type == RedirectOp::AndOutput || r.redirect_type == RedirectOp::AndAppend {
+                fds.append(&mut vec![1, 2]);
+            }
+            if r.redirect_type == RedirectOp::OutputAnd || r.redirect_type == RedirectOp::InputAnd {
+                if let Some(w) = r.right_word.as_ref().filter(|w| w.text.ends_with('-')) {
+                    if let Some(n) = Self::dup_source(&w.text) { // closed by n>&m- or n<&m-
+                        fds.push(n);
+                    }
+                }
+            }
+        }
+        fds.sort();
+        fds.dedup();
+
+        fds.iter()
+            .filter(|fd| **fd >= 0)
+            .map(|fd| (*fd, fcntl(*fd, FcntlArg::F_DUPFD_CLOEXEC(10)).ok()))
+            .collect()
+    }
+
+    pub fn restore_fds(backup: Vec<(RawFd, Option<RawFd>)>) {
+        for (fd, saved) in backup {
+            match saved {
+                Some(s) => Self::dup_and_close(s, fd),
+                None => {let _ = close(fd);},
+            }
+        }
+    }
+
     pub fn dup_and_close(from: RawFd, to: RawFd){
-        close(to).expect(&("Can't close fd: ".to_owned() + &to.to_string()));
+        if from == to { // keep the fd open over exec
+            let _ = fcntl(to, FcntlArg::F_SETFD(FdFlag::empty()));
+            return;
+        }
+        let _ = close(to); // it may not be opened yet (e.g. exec 3> file)
         dup2(from, to).expect("Can't copy file descriptors");
         close(from).expect(&("Can't close fd: ".to_owned() + &from.to_string()));
     }
@@ -116,13 +116,12 @@ fn main() {
     }
 
     let pid = process::id();
-    core.set_var("$", &pid.to_string());
     core.set_var("IFS", " \t\n");
     core.set_var("HOSTNAME", &get_hostname());
     core.set_var("SHELL", "rustybash");
     core.set_var("BASH", &core.args[0].to_string());
     if is_interactive(pid) {
-        core.flags += "i";
+        core.flags += "iH";
     }
 
     read_bashrc(&mut core);
@@ -132,6 +131,7 @@ fn main() {
 fn main_loop(core: &mut ShellCore) {
     let mut feeder = Feeder::new();
     loop {
+        core.run_traps();
         if !feeder.feed_line(core) {
             if core.has_flag('i') {
                 continue;
@@ -147,15 +147,15 @@ fn main_loop(core: &mut ShellCore) {
                 core.set_var("?", "2");
                 break;
             }
-            e.exec(core);
+            if ! core.interrupted {
+                e.exec(core);
+            }
+            core.run_traps();
         }
+        core.interrupted = false;
         core.check_jobs();
     }
 
-    if let Ok(status) = core.get_var("?").to_string().parse::<i32>(){
-        process::exit(status);
-    }else{
-        eprintln!("Shell internal error");
-        process::exit(1);
-    }
+    let f = core.builtins["exit"];
+    f(core, &mut vec!("exit".to_string()));
 }
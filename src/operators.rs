@@ -21,10 +21,12 @@ pub enum ControlOperator {
 #[derive(PartialEq, Debug)]
 pub enum RedirectOp {
     Output, /* > */ 
+    Clobber, /* >| */
     Input, /* < */
     InOut, /* <> */
     AndOutput, /* &> */ 
     OutputAnd, /* >& */ 
+    InputAnd, /* <& */
     Append, /* >> */ 
     HereDoc, /* << */ 
     AndAppend, /* &>> */ 
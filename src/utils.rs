@@ -1,61 +1,111 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-use glob::glob;
 use crate::env;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::fs::OpenOptions;
 use crate::ShellCore;
+use nix::unistd;
+use nix::errno::Errno;
+use nix::libc;
+use std::ffi::CString;
+use crate::bash_glob::{is_glob, expand_paths, glob_expand, sort_paths};
 
 pub fn chars_to_string(chars: &Vec<char>) -> String {
     chars.iter().collect::<String>()
 }
 
-fn is_glob(s: &String) -> bool {
-    let mut escaped = false;
+pub fn eval_glob(globstr: &String) -> Vec<String> {
+    if ! is_glob(globstr) {
+        return vec!(globstr.clone());
+    }
 
-    for ch in s.chars() {
-        if escaped {
-            continue;
-        }else if ! escaped && ch == '\\' {
-            escaped = true;
-            continue;
-        }
+    let ans = glob_expand(globstr);
+    if ans.is_empty() {
+        return vec!(globstr.clone());
+    }
 
-        if ch == '*' || ch == '[' || ch == '?' {
-            return true;
-        }
+    ans
+}
+
+/* glob expansion of an argument with nullglob and failglob. Err is returned for failglob. */
+pub fn expand_glob(globstr: &String, core: &ShellCore) -> Result<Vec<String>, String> {
+    if ! is_glob(globstr) {
+        return Ok(vec!(globstr.clone()));
+    }
+
+    let mut ans = expand_paths(globstr, core.shopts.get("dotglob"), core.shopts.get("globstar"),
+                               core.shopts.get("extglob"));
+    sort_paths(&mut ans, &core.get_var("GLOBSORT"));
+    if ! ans.is_empty() {
+        Ok(ans.iter().map(|p| p.replace("\\", "\\\\")).collect())
+    }else if core.shopts.get("failglob") {
+        Err(format!("bash: no match: {}", globstr))
+    }else if core.shopts.get("nullglob") {
+        Ok(vec![])
+    }else{
+        Ok(vec!(globstr.clone()))
     }
-    return false;
 }
 
-pub fn eval_glob(globstr: &String) -> Vec<String> {
-    if ! is_glob(&globstr) {
-        return vec!(globstr.clone());
+/* a field made by an expansion. None is the place where an unquoted expansion
+   gives no field. It vanishes when it is joined with a string, and is removed
+   if it remains alone. */
+pub type Field = Option<String>;
+
+pub fn to_fields(strings: Vec<Vec<String>>) -> Vec<Vec<Field>> {
+    strings.into_iter().map(|ss| ss.into_iter().map(Some).collect()).collect()
+}
+
+/* word splitting of an unquoted expansion with IFS. None is put at the head
+   or the tail if the value starts or ends with a delimiter so that the value is
+   not joined with the neighboring strings. Empty strings are empty fields made
+   by delimiters like :: with IFS=: */
+pub fn split_fields(value: &str, core: &ShellCore) -> Vec<Field> {
+    let ifs = if core.has_var("IFS") { core.get_var("IFS") }else{ " \t\n".to_string() };
+    if value.is_empty() {
+        return vec!(None);
+    }
+    if ifs.is_empty() {
+        return vec!(Some(value.to_string()));
     }
 
+    let is_ws = |c: char| " \t\n".contains(c) && ifs.contains(c);
+    let is_delim = |c: char| ifs.contains(c);
+    let chars = value.chars().collect::<Vec<char>>();
+
     let mut ans = vec![];
-    let g = globstr.replace("\\ ", " ").to_string();
-
-    //TODO: too ugly
-    if let Ok(path) = glob(&g) {
-        for dir in path {
-            if let Ok(d) = dir {
-                if let Some(s) = d.to_str() {
-                    if let Some('/') = g.chars().last() {
-                        // the library omits the last / 
-                        ans.push(s.to_string() + "/");
-                    }else{
-                        ans.push(s.to_string());
-                    }
-                };
-            };
-        };
-    };
+    let mut pos = 0;
+    while pos < chars.len() && is_ws(chars[pos]) {
+        pos += 1;
+    }
+    if pos > 0 {
+        ans.push(None);
+    }
+    if pos == chars.len() { // only blanks
+        ans.push(None);
+    }
 
-    if ans.len() == 0 {
-        return vec!(globstr.clone());
+    while pos < chars.len() {
+        let start = pos;
+        while pos < chars.len() && ! is_delim(chars[pos]) {
+            pos += 1;
+        }
+        ans.push(Some(chars[start..pos].iter().collect()));
+
+        while pos < chars.len() && is_ws(chars[pos]) {
+            pos += 1;
+        }
+        if pos < chars.len() && is_delim(chars[pos]) {
+            pos += 1;
+            while pos < chars.len() && is_ws(chars[pos]) {
+                pos += 1;
+            }
+        }
+        if pos == chars.len() && is_delim(chars[pos-1]) {
+            ans.push(None);
+        }
     }
 
     ans
@@ -70,15 +120,7 @@ pub fn search_commands(globstr: &String) -> Vec<String> {
 
     let mut ans: Vec<String> = vec![];
     for d in dirs {
-        if let Ok(path) = glob(&(d + "/" + globstr)) {
-            for dir in path {
-                if let Ok(d) = dir {
-                    if let Some(s) = d.to_str() {
-                        ans.push(s.to_string());
-                    };
-                };
-            };
-        };
+        ans.extend(glob_expand(&(d + "/" + globstr)));
     };
 
     ans
@@ -127,7 +169,15 @@ pub fn combine_with(left: &Vec<String>, right: &Vec<String>, ch: &str) -> Vec<St
     ans
 }
 
-pub fn combine(left: &mut Vec<Vec<String>>, right: Vec<Vec<String>>) -> Vec<Vec<String>> {
+fn join_fields(left: &Field, right: &Field) -> Field {
+    match (left, right) {
+        (None, _) => right.clone(),
+        (_, None) => left.clone(),
+        (Some(l), Some(r)) => Some(l.to_string() + r),
+    }
+}
+
+pub fn combine(left: &mut Vec<Vec<Field>>, right: Vec<Vec<Field>>) -> Vec<Vec<Field>> {
     if left.len() == 0 {
         return right;
     };
@@ -138,9 +188,8 @@ pub fn combine(left: &mut Vec<Vec<String>>, right: Vec<Vec<String>>) -> Vec<Vec<
         for rv in &right {
             let mut clv = lv.clone();
             clv.append(&mut rv.clone());
-            let n = clv[lv_len].clone();
-            clv[lv_len-1] += &n;
-            clv.remove(lv_len);
+            let n = clv.remove(lv_len);
+            clv[lv_len-1] = join_fields(&clv[lv_len-1], &n);
             ans.push(clv);
         }
     }
@@ -275,6 +324,14 @@ pub fn align_elems_on_term(list: &Vec<String>, width: u32) -> String {
 }
 
 
+/* directories and files without the permission are skipped in the search of PATH */
+pub fn is_executable_file(path: &str) -> bool {
+    match fs::metadata(path) {
+        Ok(m) => m.is_file() && unistd::access(path, unistd::AccessFlags::X_OK).is_ok(),
+        Err(_) => false,
+    }
+}
+
 pub fn get_fullpath(com: &String) -> String {
     let dirs = if let Ok(p) = env::var("PATH") {
         p.split(':').map(|s| s.to_string()).collect()
@@ -284,10 +341,52 @@ pub fn get_fullpath(com: &String) -> String {
 
     for d in dirs {
         let path = d + "/" + com;
-        if fs::metadata(&path).is_ok() {
+        if is_executable_file(&path) {
             return path;
         }
     }
 
     "".to_string()
 }
+
+/* reads fd 0 directly since the buffer of stdin may hold a part of the script.
+   The newline is left at the end of the line. */
+pub fn read_line_fd0() -> Option<String> {
+    let mut line = vec![];
+    let mut ch = [0; 1];
+    loop {
+        match unistd::read(0, &mut ch) {
+            Ok(1) => {
+                line.push(ch[0]);
+                if ch[0] == b'\n' {
+                    break;
+                }
+            },
+            Err(Errno::EINTR) => continue,
+            _ if line.is_empty() => return None,
+            _ => break,
+        }
+    }
+    Some(String::from_utf8_lossy(&line).to_string())
+}
+
+/* "#<unix time>" lines in a history file */
+pub fn is_history_timestamp(line: &str) -> bool {
+    line.len() > 1 && line.starts_with('#') && line[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/* formats a unix time with strftime(3) in the local time zone */
+pub fn format_time(time: i64, format: &str) -> String {
+    let format = match CString::new(format) {
+        Ok(f) => f,
+        Err(_) => return "".to_string(),
+    };
+
+    let mut buf = [0u8; 256];
+    let len = unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&(time as libc::time_t), &mut tm);
+        libc::strftime(buf.as_mut_ptr() as *mut libc::c_char, buf.len(), format.as_ptr(), &tm)
+    };
+    String::from_utf8_lossy(&buf[..len]).to_string()
+}
@@ -10,6 +10,7 @@ err () {
 cd $(dirname $0)
 
 com=../target/release/rusty_bash
+tmp=/tmp/$$
 
 ### BUILTIN COMMAND ###
 
@@ -19,6 +20,21 @@ EOF
 res=$($com <<< 'source /tmp/.rusty_bash ; echo $A')
 [ "$res" = "B" ] || err $LINENO
 
+cat << 'EOF' > /tmp/.rusty_bash
+f () { echo f $1; }
+echo $# $1 $2
+return 3
+echo never
+EOF
+res=$($com <<< 'set a; . /tmp/.rusty_bash x y; echo $? $1; f z')
+[ "$res" = "2 x y
+3 a
+f z" ] || err $LINENO
+
+res=$($com <<< 'source /tmp/nonexistent_file; echo $?' 2>&1)
+[ "$res" = "bash: /tmp/nonexistent_file: No such file or directory
+1" ] || err $LINENO
+
 res=$($com <<< 'set a b c ; shift; echo $1')
 [ "$res" = "b" ] || err $LINENO
 
@@ -29,6 +45,23 @@ res=$($com <<< 'set a b c ; shift 4; echo $? ; echo $1')
 [ "$res" = "1
 a" ] || err $LINENO
 
+res=$($com <<< 'f(){ while [ $# -gt 0 ]; do echo $1; shift; done; shift; echo $?; }; f a b c')
+[ "$res" = "a
+b
+c
+1" ] || err $LINENO
+
+res=$($com <<< 'f(){ shift 2; echo $1 $#; shift 3; echo $? $#; shift 0; echo $? $1; }; f x y z')
+[ "$res" = "z 1
+1 1
+0 z" ] || err $LINENO
+
+res=$($com <<< 'shift -1; echo $?; shift x; echo $?' 2>&1)
+[ "$res" = "bash: shift: -1: shift count out of range
+1
+bash: shift: x: numeric argument required
+1" ] || err $LINENO
+
 # export
 
 res=$($com <<< 'HOGE=A;export HOGE;printenv HOGE')
@@ -37,6 +70,21 @@ res=$($com <<< 'HOGE=A;export HOGE;printenv HOGE')
 res=$($com <<< 'export HOGE=A;printenv HOGE')
 [ "$res" = "A" ] || err $LINENO
 
+res=$($com <<< 'HOGE=A; export HOGE=B; printenv HOGE; echo $HOGE')
+[ "$res" = "B
+B" ] || err $LINENO
+
+res=$($com <<< 'export HOGE=A HUGA="x=y"; printenv HOGE HUGA')
+[ "$res" = "A
+x=y" ] || err $LINENO
+
+res=$($com <<< 'export HOGE=A; export | grep HOGE')
+[ "$res" = 'declare -x HOGE="A"' ] || err $LINENO
+
+res=$($com <<< 'export 1HOGE=A; echo $?' 2>&1)
+[ "$res" = "bash: export: \`1HOGE=A': not a valid identifier
+1" ] || err $LINENO
+
 # eval
 
 res=$($com <<< 'eval echo hello')
@@ -45,8 +93,8 @@ res=$($com <<< 'eval echo hello')
 res=$($com <<< "eval 'echo $(echo a b c)'")
 [ "$res" = "a b c" ] || err $LINENO
 
-res=$($com <<< "eval 'echo $(seq 3)'")
-[ "$res" = "1 2 3" ] || err $LINENO
+res=$($com <<< "eval 'echo $(seq 3)'" 2> /dev/null)
+[ "$res" = "1" ] || err $LINENO
 
 res=$($com <<< 'eval "echo $(seq 3)"')
 [ "$?" = "127" ] || err $LINENO
@@ -58,5 +106,667 @@ res=$($com <<< 'eval "echo $(echo a b c);" "echo $(echo a b c)"' )
 [ "$res" = "a b c
 a b c" ] || err $LINENO
 
+# cd
+
+res=$($com <<< 'cd /tmp; cd /; cd -; echo $PWD $OLDPWD')
+[ "$res" = "/tmp
+/tmp /" ] || err $LINENO
+
+res=$($com <<< 'HOME=/tmp; cd; echo $PWD')
+[ "$res" = "/tmp" ] || err $LINENO
+
+res=$($com <<< 'cd /tmp; cd ./../tmp/..; echo $PWD')
+[ "$res" = "/" ] || err $LINENO
+
+mkdir -p $tmp-cdpath/{proj/sub,here/dir}
+res=$($com <<< "cd $tmp-cdpath/here; CDPATH=/nonexistent_dir:$tmp-cdpath/proj/
+cd sub; pwd; cd ../../here; cd dir; pwd; cd ./sub; echo \$?" 2>&1)
+[ "$res" = "$tmp-cdpath/proj/sub
+$tmp-cdpath/proj/sub
+$tmp-cdpath/here/dir
+bash: cd: ./sub: No such file or directory
+1" ] || err $LINENO
+rm -rf $tmp-cdpath
+
+res=$($com <<< 'cd /nonexistent_dir; echo $?' 2>&1)
+[ "$res" = "bash: cd: /nonexistent_dir: No such file or directory
+1" ] || err $LINENO
+
+
+# pwd
+
+mkdir -p $tmp-pwd/real
+ln -s $tmp-pwd/real $tmp-pwd/link
+res=$($com <<< "cd $tmp-pwd/link; pwd; pwd -P; pwd -L")
+[ "$res" = "$tmp-pwd/link
+$tmp-pwd/real
+$tmp-pwd/link" ] || err $LINENO
+rm -rf $tmp-pwd
+
+
+# pushd, popd, dirs
+
+mkdir -p $tmp-dirs/{a,b,c}
+res=$($com <<< "HOME=$tmp-dirs; cd; pushd a; pushd ../b; pushd ~/c; dirs -v; pushd; pushd +2; popd; popd +1; popd; pwd; popd; echo \$?" 2>&1)
+[ "$res" = "~/a ~
+~/b ~/a ~
+~/c ~/b ~/a ~
+ 0  ~/c
+ 1  ~/b
+ 2  ~/a
+ 3  ~
+~/b ~/c ~/a ~
+~/a ~ ~/b ~/c
+~ ~/b ~/c
+~ ~/c
+~/c
+$tmp-dirs/c
+bash: popd: directory stack empty
+1" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-dirs; pushd; pushd nodir; pushd a >/dev/null; dirs -l -p; pushd +3; dirs -c; dirs" 2>&1)
+[ "$res" = "bash: pushd: no other directory
+bash: pushd: nodir: No such file or directory
+$tmp-dirs/a
+$tmp-dirs
+bash: pushd: +3: directory stack index out of range
+$tmp-dirs/a" ] || err $LINENO
+rm -rf $tmp-dirs
+
+
+# echo
+
+res=$($com <<< 'echo a b; echo -n c; echo d')
+[ "$res" = "a b
+cd" ] || err $LINENO
+
+res=$($com <<< 'echo hi > /dev/full; echo $?; echo next' 2>&1)
+[ "$res" = "bash: echo: write error: No space left on device
+1
+next" ] || err $LINENO
+
+res=$($com <<< 'echo -e "a\tb\\\\c"; echo -ne "x\ny\cz"; echo')
+[ "$res" = "a	b\c
+x
+y" ] || err $LINENO
+
+res=$($com <<< 'echo -n -x -e; echo "a\tb" -n')
+[ "$res" = "-x -ea\tb -n" ] || err $LINENO
+
+
+# exit
+
+res=$($com <<< 'exit 3; echo no')
+[ "$?" = "3" ] || err $LINENO
+[ "$res" = "" ] || err $LINENO
+
+res=$($com <<< 'false; exit')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'exit 257')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'exit abc' 2>&1)
+[ "$?" = "2" ] || err $LINENO
+[ "$res" = "bash: exit: abc: numeric argument required" ] || err $LINENO
+
+res=$($com <<< '(exit 4); echo $?')
+[ "$res" = "4" ] || err $LINENO
+
+
+# unset
+
+res=$($com <<< 'A=1; B=2; unset A B NOPE; echo [$A$B] ${A-unset} $?')
+[ "$res" = "[] unset 0" ] || err $LINENO
+
+res=$($com <<< 'export A=1; unset A; printenv A; echo $?')
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< 'f () { echo f; }; unset -f f; f' 2>/dev/null)
+[ "$res" = "" ] || err $LINENO
+
+res=$($com <<< 'f () { echo f; }; f=1; unset f; f; unset f; f' 2>/dev/null)
+[ "$res" = "f" ] || err $LINENO
+
+
+# exec
+
+res=$($com <<< 'exec echo a; echo b')
+[ "$res" = "a" ] || err $LINENO
+
+res=$($com <<< 'exec nonexistent_command; echo b' 2>&1)
+[ "$?" = "127" ] || err $LINENO
+[ "$res" = "bash: exec: nonexistent_command: not found" ] || err $LINENO
+
+res=$($com <<< 'exec > /tmp/$$-exec; echo a; echo b >&2; cat /tmp/$$-exec >&2; rm /tmp/$$-exec' 2>&1)
+[ "$res" = "b
+a" ] || err $LINENO
+
+res=$($com <<< 'exec 3> /tmp/$$-exec; echo a >&3; cat /tmp/$$-exec; rm /tmp/$$-exec')
+[ "$res" = "a" ] || err $LINENO
+
+
+# read
+
+res=$($com <<< 'echo "  a  b  c  " | { read x y; echo "[$x][$y]"; }')
+[ "$res" = "[a][b  c]" ] || err $LINENO
+
+res=$($com <<< 'echo "a b" > /tmp/$$-read; read x y z < /tmp/$$-read; echo "[$x][$y][$z]"; rm /tmp/$$-read')
+[ "$res" = "[a][b][]" ] || err $LINENO
+
+res=$($com <<< 'echo "x\\ y z" > /tmp/$$-read; read a b < /tmp/$$-read; echo "[$a][$b]"; read -r a b < /tmp/$$-read; echo "[$a][$b]"; rm /tmp/$$-read')
+[ "$res" = "[x y][z]
+[x\][y z]" ] || err $LINENO
+
+res=$($com <<< 'echo "1:2:" > /tmp/$$-read; IFS=:; read a b < /tmp/$$-read; echo "[$a][$b]"; rm /tmp/$$-read')
+[ "$res" = "[1][2]" ] || err $LINENO
+
+res=$($com <<< 'echo "  a b  " | { read; echo "[$REPLY]"; }')
+[ "$res" = "[  a b  ]" ] || err $LINENO
+
+res=$($com <<< 'printf abc | { read x; echo $? $x; }')
+[ "$res" = "1 abc" ] || err $LINENO
+
+
+# test
+
+res=$($com <<< '[ -f /etc/passwd ] && [ -d /tmp ] && [ ! -e /nonexistent ] && echo ok')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< 'test -z "" -a -n a; echo $?; [ a = b ]; echo $?; [ a != b ]; echo $?')
+[ "$res" = "0
+1
+0" ] || err $LINENO
+
+res=$($com <<< '[ 3 -lt 10 ]; echo $?; [ 3 -ge 10 ]; echo $?; [ \( 1 -eq 2 \) -o 2 -ne 3 ]; echo $?')
+[ "$res" = "0
+1
+0" ] || err $LINENO
+
+res=$($com <<< '[ a -eq 1 ]; echo $?; [ a; echo $?' 2>&1)
+[ "$res" = "bash: [: a: integer expression expected
+2
+bash: [: missing \`]'
+2" ] || err $LINENO
+
+
+# true and false
+
+res=$($com <<< 'false; echo $?; true; echo $?; true | false; echo $?')
+[ "$res" = "1
+0
+1" ] || err $LINENO
+
+res=$($com <<< 'PATH=; true && ! false && echo ok')
+[ "$res" = "ok" ] || err $LINENO
+
+# :
+
+res=$($com <<< 'false; : ${x:=5}; echo $? $x; PATH=; : a b; echo $?')
+[ "$res" = "0 5
+0" ] || err $LINENO
+
+
+# set -e
+
+res=$($com <<< 'set -e; echo a; false; echo b')
+[ "$?" = "1" ] || err $LINENO
+[ "$res" = "a" ] || err $LINENO
+
+res=$($com <<< 'set -e; true && false; echo b')
+[ "$res" = "" ] || err $LINENO
+
+res=$($com <<< 'set -e; if false; then :; fi; while false; do :; done; false || true; false && true; ! true; echo ok')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< 'set -e; ! false; echo $?; ! grep -q x <<< y; echo ok; ! ! false; echo never')
+[ "$?" = "1" ] || err $LINENO
+[ "$res" = "0
+ok" ] || err $LINENO
+
+res=$($com <<< 'set -o errexit; set +e; false; echo ok')
+[ "$res" = "ok" ] || err $LINENO
+
+res=$($com <<< 'set -e a b; echo $1 $#; set --; echo $#')
+[ "$res" = "a 2
+0" ] || err $LINENO
+
+
+# set -x
+
+res=$($com <<< 'set -x; a=1; echo $a "b c"; set +x; echo d' 2>&1)
+[ "$res" = "+ a=1
++ echo 1 'b c'
+1 b c
++ set +x
+d" ] || err $LINENO
+
+res=$($com <<< 'PS4="> "; set -o xtrace; echo a' 2>&1)
+[ "$res" = "> echo a
+a" ] || err $LINENO
+
+
+# set -u
+
+res=$($com <<< 'set -u; echo a; echo $nonexistent; echo b' 2>&1)
+[ "$?" = "1" ] || err $LINENO
+[ "$res" = "a
+bash: nonexistent: unbound variable" ] || err $LINENO
+
+res=$($com <<< 'set -u; echo $1' 2>&1)
+[ "$res" = "bash: \$1: unbound variable" ] || err $LINENO
+
+res=$($com <<< 'set -o nounset; a=; echo [$a] ${b-x} ${b:-y} [${b+z}] $# $? $@ $*; echo ok')
+[ "$res" = "[] x y [] 0 0
+ok" ] || err $LINENO
+
+res=$($com <<< 'set -o vi; echo $?; set -o emacs; echo $?; set +o vi; echo $? $-; set -o vim' 2>&1)
+[ "$res" = "0
+0
+0
+bash: set: vim: invalid option name" ] || err $LINENO
+
+# complete
+
+res=$($com <<< "complete -W '--help --version' mytool; complete -W 'a b' x y; complete; complete -r x; complete -p x; echo \$?" 2>&1)
+[ "$res" = "complete -W '--help --version' mytool
+complete -W 'a b' x
+complete -W 'a b' y
+bash: complete: x: no completion specification
+1" ] || err $LINENO
+
+res=$($com <<< 'complete -W; echo $?; complete -r; complete' 2>&1)
+[ "$res" = "bash: complete: -W: option requires an argument
+complete: usage: complete [-pr] [-W wordlist] [name ...]
+2" ] || err $LINENO
+
+# alias
+
+res=$($com <<< 'shopt -s expand_aliases
+alias a="echo A" b=a
+b x; a y; alias')
+[ "$res" = "A x
+A y
+alias a='echo A'
+alias b='a'" ] || err $LINENO
+
+res=$($com <<< 'shopt -s expand_aliases
+alias x=y y=x ls="ls -d"
+ls /; unalias ls x; alias; alias x' 2>&1)
+[ "$res" = "/
+alias y='x'
+bash: alias: x: not found" ] || err $LINENO
+
+res=$($com <<< 'alias a=b; unalias -a; unalias a; echo $?' 2>&1)
+[ "$res" = "bash: unalias: a: not found
+1" ] || err $LINENO
+
+# trap
+
+res=$($com <<< 'trap "echo hi" USR1; kill -USR1 $$; echo after')
+[ "$res" = "hi
+after" ] || err $LINENO
+
+res=$($com <<< 'trap "echo bye \$?" EXIT; echo a; exit 3')
+[ "$?" = "3" ] || err $LINENO
+[ "$res" = "a
+bye 3" ] || err $LINENO
+
+res=$($com <<< 'trap "echo bye \$?" EXIT; echo ${zz:?unset}; echo no' 2>&1)
+[ "$?" = "1" ] || err $LINENO
+[ "$res" = "bash: zz: unset
+bye 1" ] || err $LINENO
+
+res=$($com <<< 'set -u; trap "echo bye" EXIT; echo $zz; echo no' 2>&1)
+[ "$res" = "bash: zz: unbound variable
+bye" ] || err $LINENO
+
+res=$($com <<< 'trap "echo bye" EXIT; x="a b"; echo ${!x}; echo no' 2>&1)
+[ "$res" = "bash: a b: invalid variable name
+bye" ] || err $LINENO
+
+res=$($com <<< 'trap "echo x" INT SIGTERM; trap "it'"'"'s" 0; trap; trap - INT; trap TERM; trap -p')
+[ "$res" = "trap -- 'it'\''s' EXIT
+trap -- 'echo x' SIGINT
+trap -- 'echo x' SIGTERM
+trap -- 'it'\''s' EXIT" ] || err $LINENO
+
+res=$($com <<< 'trap "" USR2; kill -USR2 $$; echo ok; trap foo BAR; echo $?' 2>&1)
+[ "$res" = "ok
+bash: trap: BAR: invalid signal specification
+1" ] || err $LINENO
+
+res=$($com <<< 'trap -l' | head -1)
+[ "$res" = " 1) SIGHUP	 2) SIGINT	 3) SIGQUIT	 4) SIGILL	 5) SIGTRAP" ] || err $LINENO
+
+res=$($com <<< 'trap "echo \"dbg: \$BASH_COMMAND\"" DEBUG; echo a; x=1
+f() { echo b; echo c; }; f; trap - DEBUG; echo d')
+[ "$res" = "dbg: echo a
+a
+dbg: x=1
+dbg: f
+b
+c
+dbg: trap - DEBUG
+d" ] || err $LINENO
+
+res=$($com <<< 'trap "echo err \$?" ERR; false; false || true; ! false; if false; then :; fi; true && false; ls /nonexist 2> /dev/null')
+[ "$res" = "err 1
+err 1
+err 2" ] || err $LINENO
+
+res=$($com <<< 'trap "echo err" ERR; f() { false; echo f; return 3; }; f; echo $?; trap -p ERR; set -e; false; echo never')
+[ "$res" = "f
+err
+3
+trap -- 'echo err' ERR
+err" ] || err $LINENO
+
+res=$($com <<< 'f() { trap "echo ret \$?" RETURN; echo body; return 4; }; f; echo $?; trap -p RETURN')
+[ "$res" = "body
+ret 4
+4
+trap -- 'echo ret \$?' RETURN" ] || err $LINENO
+
+res=$($com <<< 'trap "echo ret" RETURN; f() { echo f; }; f; echo "echo src; false" > /tmp/$$-ret; source /tmp/$$-ret; echo $?; rm /tmp/$$-ret')
+[ "$res" = "f
+src
+ret
+1" ] || err $LINENO
+
+# kill
+
+res=$($com <<< 'trap "echo t" TERM; kill $$; trap "echo u" USR1; kill -USR1 $$; kill -s usr1 $$; kill -n 10 $$; kill -SIGUSR1 $$')
+[ "$res" = "t
+u
+u
+u
+u" ] || err $LINENO
+
+res=$($com <<< 'kill -l 2 130 TERM SIGHUP; kill -l FOO; echo $?' 2>&1)
+[ "$res" = "INT
+INT
+15
+1
+bash: kill: FOO: invalid signal specification
+1" ] || err $LINENO
+
+res=$($com <<< 'kill abc; echo $?; kill -0 $$; echo $?; kill %1; echo $?; kill; echo $?' 2>&1)
+[ "$res" = "bash: kill: abc: arguments must be process or job IDs
+1
+0
+bash: kill: %1: no such job
+1
+kill: usage: kill [-s sigspec | -n signum | -sigspec] pid | jobspec ... or kill -l [sigspec]
+2" ] || err $LINENO
+
+# getopts
+
+res=$($com <<< 'f(){ while getopts ab:c o; do echo "$o[$OPTARG]$OPTIND"; done; echo $? $OPTIND; }; f -ac -bx -b y -ca -- z w')
+[ "$res" = "a[]1
+c[]2
+b[x]3
+b[y]5
+c[]5
+a[]6
+0 7" ] || err $LINENO
+
+res=$($com <<< 'getopts :ab: o -x; echo $o $OPTARG $OPTIND; OPTIND=1; getopts :ab: o -b; echo $o $OPTARG $OPTIND')
+[ "$res" = "? x 2
+: b 2" ] || err $LINENO
+
+res=$($com <<< 'getopts ab: o -x; echo $o $OPTARG; OPTIND=1; getopts ab: o -b; echo $o $OPTARG' 2>&1)
+[ "$res" = "bash: illegal option -- x
+?
+bash: option requires an argument -- b
+?" ] || err $LINENO
+
+res=$($com <<< 'set -- -ab c; getopts ab o; getopts ab o; echo $o; getopts ab o; echo $? $OPTIND; getopts a 1x; echo $?' 2>&1)
+[ "$res" = "b
+1 2
+bash: getopts: \`1x': not a valid identifier
+1" ] || err $LINENO
+
+res=$($com <<< 'getopts ab o -ab; echo $o; OPTIND=1; getopts ab o -x; echo $? $o $OPTIND; OPTIND=1; set -- -ab; getopts ab o; set -- -c; getopts abc o; echo $? $o' 2>&1)
+[ "$res" = "a
+bash: illegal option -- x
+0 ? 2
+0 c" ] || err $LINENO
+
+# type
+
+res=$($com <<< 'f(){ echo a; }; alias ll="ls -l"; type -t f if cd ls ll nope; echo $?')
+[ "$res" = "function
+keyword
+builtin
+file
+alias
+1" ] || err $LINENO
+
+res=$($com <<< 'alias ll="ls -l"; type cd if ll; type /bin/ls; type ./nonexistent; echo $?' 2>&1)
+[ "$res" = "cd is a shell builtin
+if is a shell keyword
+ll is aliased to \`ls -l'
+/bin/ls is /bin/ls
+bash: type: ./nonexistent: not found
+1" ] || err $LINENO
+
+res=$($com <<< 'f(){ echo a; }; type f')
+[ "$res" = "f is a function
+f () 
+{ echo a; }" ] || err $LINENO
+
+# command
+
+res=$($com <<< 'ls() { echo func; }; ls -d /; command ls -d /; command -p ls -d / | cat')
+[ "$res" = "func
+/
+/" ] || err $LINENO
+
+res=$($com <<< 'echo() { builtin echo f "$@"; }; echo a; command echo b; command command echo c')
+[ "$res" = "f a
+b
+c" ] || err $LINENO
+
+res=$($com <<< 'f(){ :; }; command -v f echo if nosuch; echo $?; command -v nosuch; echo $?')
+[ "$res" = "f
+echo
+if
+0
+1" ] || err $LINENO
+
+res=$($com <<< 'command -v ls | grep -q "/ls$" && command -V echo')
+[ "$res" = "echo is a shell builtin" ] || err $LINENO
+
+# hash
+
+mkdir -p /tmp/$$-hash/a /tmp/$$-hash/b
+printf '#!/bin/sh\necho b\n' > /tmp/$$-hash/b/hashtest
+chmod +x /tmp/$$-hash/b/hashtest
+
+res=$($com <<< "PATH=/tmp/$$-hash/a:/tmp/$$-hash/b:\$PATH; hash; hashtest; hashtest; hash -t hashtest
+cp /tmp/$$-hash/b/hashtest /tmp/$$-hash/a/; sed -i s/b$/a/ /tmp/$$-hash/a/hashtest; hashtest; hash -r; hashtest")
+[ "$res" = "hash: hash table empty
+b
+b
+/tmp/$$-hash/b/hashtest
+b
+a" ] || err $LINENO
+
+res=$($com <<< "ls > /dev/null; ls > /dev/null; hash | grep -c '^   2.*/ls$'; hash -d ls; hash -t ls; echo \$?")
+[ "$res" = "1
+1" ] || err $LINENO
+
+res=$($com <<< "PATH=/tmp/$$-hash/b:\$PATH; hashtest; PATH=/tmp/$$-hash/a:\$PATH; hashtest")
+[ "$res" = "b
+a" ] || err $LINENO
+
+rm -rf /tmp/$$-hash
+
+# history
+
+res=$($com <<< 'history -s echo a; history -s ls; history -s pwd; history; history 2')
+[ "$res" = "    1  echo a
+    2  ls
+    3  pwd
+    2  ls
+    3  pwd" ] || err $LINENO
+
+res=$($com <<< 'history -s a; history -s b; history -s c; history -d 2; history; history -d -1; history')
+[ "$res" = "    1  a
+    2  c
+    1  a" ] || err $LINENO
+
+res=$($com <<< 'history -s a; history -s b; history -c; history; echo $?')
+[ "$res" = "0" ] || err $LINENO
+
+res=$($com <<< 'history -s a; history -d 2; echo $?; history x; echo $?' 2>&1)
+[ "$res" = "bash: history: 2: history position out of range
+1
+bash: history: x: numeric argument required
+1" ] || err $LINENO
+
+printf "echo a\nls\n" > $tmp-histfile
+res=$($com <<< "HISTFILE=$tmp-histfile; history -r; history -s pwd; history; history -w $tmp-histfile2; history -r /nonexistent/file; echo \$?")
+[ "$res" = "    1  echo a
+    2  ls
+    3  pwd
+1" ] || err $LINENO
+[ "$(cat $tmp-histfile2)" = "echo a
+ls
+pwd" ] || err $LINENO
+rm -f $tmp-histfile $tmp-histfile2
+
+printf "#1000000000\nx\ny\n" > $tmp-histfile
+res=$($com <<< "export TZ=UTC; HISTTIMEFORMAT='%F %T '; history -r $tmp-histfile; history 2 | head -n 1; unset HISTTIMEFORMAT; history 1")
+[ "$res" = "    1  2001-09-09 01:46:40 x
+    2  y" ] || err $LINENO
+
+res=$($com <<< "HISTTIMEFORMAT='%Y '; history -s a; history; history -w $tmp-histfile")
+[ "$res" = "    1  $(date +%Y) a" ] || err $LINENO
+head -n 1 $tmp-histfile | grep -qE '^#[0-9]+$' || err $LINENO
+rm -f $tmp-histfile
+
+# umask
+
+res=$($com <<< 'umask 027; umask; umask -S; umask -p')
+[ "$res" = "0027
+u=rwx,g=rx,o=
+umask 0027" ] || err $LINENO
+
+res=$($com <<< 'umask -S 0; umask 0777; umask; umask -pS')
+[ "$res" = "u=rwx,g=rwx,o=rwx
+0777
+umask -S u=,g=,o=" ] || err $LINENO
+
+res=$($com <<< "umask 077; rm -f /tmp/$$-umask; echo > /tmp/$$-umask; stat -c %a /tmp/$$-umask; sh -c umask")
+[ "$res" = "600
+0077" ] || err $LINENO
+rm -f /tmp/$$-umask
+
+res=$($com <<< 'umask 8; echo $?; umask -x; echo $?' 2>&1)
+[ "$res" = "bash: umask: 8: octal number out of range
+1
+bash: umask: -x: invalid option
+umask: usage: umask [-p] [-S] [mode]
+2" ] || err $LINENO
+
+# declare
+
+res=$($com <<< 'declare -i n=3+4; declare -r r=1; declare -x X=5; typeset -ir q=2; declare -p n r X q; printenv X')
+[ "$res" = 'declare -i n="7"
+declare -r r="1"
+declare -x X="5"
+declare -ir q="2"
+5' ] || err $LINENO
+
+res=$($com <<< 'declare -i m; m=2*3; echo $m; declare +i m; m=2*3; echo $m; declare -i k=abc; declare -p m k' 2>&1)
+[ "$res" = '6
+2*3
+declare -- m="2*3"
+declare -i k="0"' ] || err $LINENO
+
+res=$($com <<< 'declare -r r=1; declare r=3; echo $?; unset r; echo $?; declare -p nope; echo $?; r=2; echo never' 2>&1)
+[ "$?" = "1" ] || err $LINENO
+[ "$res" = "bash: declare: r: readonly variable
+1
+bash: unset: r: cannot unset: readonly variable
+1
+bash: declare: nope: not found
+1
+bash: r: readonly variable" ] || err $LINENO
+
+res=$($com <<< 'declare -r r=1; read r <<< 5; echo $? $r; getopts a r -a; echo $? $r; export r=5; echo $? $r' 2>&1)
+[ "$res" = "bash: r: readonly variable
+1 1
+bash: r: readonly variable
+1 1
+bash: r: readonly variable
+1 1" ] || err $LINENO
+
+res=$($com <<< 'declare -i n; read n <<< 2+3; echo $n; export n=3*4; echo $n; env | grep ^n=')
+[ "$res" = "5
+12
+n=12" ] || err $LINENO
+
+res=$($com <<< 'aa=1; bb="x y"; declare | grep -E "^(aa|bb)="')
+[ "$res" = "aa=1
+bb='x y'" ] || err $LINENO
+
+# local
+
+res=$($com <<< 'x=5; f(){ local x; echo ${x-unset}; x=2; g; }; g(){ echo in g $x; }; f; echo $x')
+[ "$res" = "unset
+in g 2
+5" ] || err $LINENO
+
+res=$($com <<< 'g(){ local a=1 b; local -i n=2+3; local; }; g; r(){ local x=9; return 3; }; x=5; r; echo $? $x')
+[ "$res" = 'declare -- a="1"
+declare -- b
+declare -i n="5"
+3 5' ] || err $LINENO
+
+res=$($com <<< 'k(){ local HOME=/tmp; printenv HOME; }; k; [ "$HOME" != /tmp ] && echo ok; local y; echo $?' 2>&1)
+[ "$res" = "/tmp
+ok
+bash: local: can only be used in a function
+1" ] || err $LINENO
+
+res=$($com <<< 'f(){ local -A m; m[a]=1; declare -p m; }; f; declare -p m; echo $?' 2>&1)
+[ "$res" = 'declare -A m=([a]="1" )
+bash: declare: m: not found
+1' ] || err $LINENO
+
+res=$($com <<< 'a=(x y); g(){ local -a a=(1 2); echo ${a[1]}; local -a b; b[3]=c; }; g; declare -p a; echo ${b[3]}end')
+[ "$res" = '2
+declare -a a=([0]="x" [1]="y")
+end' ] || err $LINENO
+
+
+# shopt
+
+res=$($com <<< 'shopt -s nullglob; shopt nullglob; shopt -p nullglob; shopt -u nullglob; shopt nullglob; echo $?')
+[ "$res" = "nullglob       	on
+shopt -s nullglob
+nullglob       	off
+1" ] || err $LINENO
+
+res=$($com <<< 'shopt -q dotglob; echo $?; shopt -s dotglob globstar; shopt -q dotglob globstar; echo $?')
+[ "$res" = "1
+0" ] || err $LINENO
+
+res=$($com <<< 'shopt -s nope; echo $?' 2>&1)
+[ "$res" = "bash: shopt: nope: invalid shell option name
+1" ] || err $LINENO
+
+res=$($com <<< 'shopt -s extglob; shopt -s | grep -c extglob; shopt -u | grep -c extglob')
+[ "$res" = "1
+0" ] || err $LINENO
+
+mkdir $tmp-dotglob
+res=$($com <<< "cd $tmp-dotglob; touch a .b; echo *; shopt -s dotglob; echo *")
+[ "$res" = "a
+.b a" ] || err $LINENO
+rm -rf $tmp-dotglob
 
 echo OK $0
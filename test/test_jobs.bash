@@ -9,6 +9,7 @@ err () {
 
 cd $(dirname $0)
 com=../target/release/rusty_bash
+tmp=/tmp/$$
 
 ### BASIC BEHAVIOR ###
 
@@ -31,13 +32,22 @@ echo $res | grep -E '^\[1\] [0-9]+$' || err $LINENO
 res="$($com <<< '( sleep 1 & wait ) 2>&1')"
 echo $res | grep Done || err $LINENO
 
+res="$($com << 'EOF'
+sleep 0.1 &
+sleep 0.1 | sleep 0.1 &
+sleep 0.5
+ps -o stat= --ppid $$ | grep -c Z
+EOF
+)"
+echo $res | grep -E '^\[1\]- Done sleep 0.1 & \[2\]\+ Done sleep 0.1 \| sleep 0.1 & 0$' || err $LINENO
+
 res="$($com <<< 'sleep 1 & sleep 1 & jobs')"
 echo $res | grep '\[1\].*Running sleep 1 &.*\[2\].*Running sleep 1 &' || err $LINENO
 
 ### bg COMMAND ###
 
 res="$($com <<< '(sleep 1 ; killall -SIGSTOP sleep ) & sleep 2 ; fg ')"
-echo $res | grep -F '[2]+ Stopped sleep 2 [2]+ Done sleep 2'  || err $LINENO
+echo $res | grep -F '[2]+ Stopped sleep 2 [1]- Done (sleep 1 ; killall -SIGSTOP sleep ) & [2]+ Done sleep 2'  || err $LINENO
 
 res="$($com <<< 'sleep 1 || sleep 1 & jobs')"
 echo $res | grep -F 'sleep 1 || sleep 1 &'  || err $LINENO
@@ -51,4 +61,84 @@ echo $res | grep -F '[1]- Stopped sleep 1 & [2]+ Stopped sleep 2 &' || err $LINE
 res="$($com <<< 'sleep 1 & sleep 2 & killall -SIGSTOP sleep ; sleep 3 & jobs ; killall -SIGCONT sleep')"
 echo $res | grep -F '[1] Stopped sleep 1 & [2]- Stopped sleep 2 & [3]+ Running sleep' || err $LINENO
 
+### fg COMMAND ###
+
+res="$($com <<< '(sleep 1; exit 5) & fg %?exit ; echo $?' 2>&1)"
+echo $res | grep -E '^\[1\] [0-9]+ 5' || err $LINENO
+
+res="$($com <<< 'sleep 1 & sleep 2 & fg %1 ; jobs ; fg %3 ; echo $?' 2>&1)"
+echo $res | grep -F '[1]- Done sleep 1 & [2]+ Running sleep 2 & bash: fg: %3: no such job 1' || err $LINENO
+
+res="$($com <<< 'fg ; bg %1' 2>&1)"
+[ "$res" = "bash: fg: current: no such job
+bash: bg: %1: no such job" ] || err $LINENO
+
+res="$($com <<< 'sleep 1 & jobs -l ; jobs -p %1 ; jobs -x' 2>&1)"
+echo $res | grep -E '^\[1\] ([0-9]+) \[1\]\+ \1 Running sleep 1 & \1 bash: jobs: -x: invalid option' || err $LINENO
+
+### wait COMMAND ###
+
+res="$($com <<< '(sleep 1; exit 3) & p=$! ; wait $p ; echo $?' 2>/dev/null)"
+[ "$res" = "3" ] || err $LINENO
+
+res="$($com <<< '(exit 3) & p=$!
+sleep 1
+wait $p ; echo $?' 2>/dev/null)"
+[ "$res" = "[1]+ Done		(exit 3) &
+3" ] || err $LINENO
+
+res="$($com <<< '(exit 3) & p=$!; (exit 4) & q=$!
+sleep 0.5
+wait $q ; echo $? ; wait $q ; echo $? ; wait ; wait $p ; echo $?' 2>&1 | grep -v Done | sed 's/\] [0-9]*$/] N/; s/pid [0-9]*/pid N/')"
+[ "$res" = "[1] N
+[2] N
+4
+bash: wait: pid N is not a child of this shell
+127
+bash: wait: pid N is not a child of this shell
+127" ] || err $LINENO
+
+res="$($com <<< '(sleep 1; exit 6) & (exit 7) & wait %+ %- ; echo $? ; (exit 4) & wait ; echo $?' 2>/dev/null)"
+[ "$res" = "6
+0" ] || err $LINENO
+
+res="$($com <<< 'wait 99999 ; echo $? ; wait %2 ; echo $? ; wait abc ; echo $?' 2>&1)"
+[ "$res" = "bash: wait: pid 99999 is not a child of this shell
+127
+bash: wait: %2: no such job
+127
+bash: wait: \`abc': not a pid or valid job spec
+1" ] || err $LINENO
+
+### disown COMMAND ###
+
+res="$($com <<< 'sleep 1 & sleep 1 & disown %1 ; jobs ; disown -h ; jobs ; disown 99999 ; disown -a ; jobs ; echo $?' 2>&1)"
+echo $res | grep -E '^\[1\] [0-9]+ \[2\] [0-9]+ \[2\]\+ Running sleep 1 & \[2\]\+ Running sleep 1 & bash: disown: 99999: no such job 0$' || err $LINENO
+
+# SIGHUP is sent to the jobs at exit with huponexit except for disown -h
+$com <<< "shopt -s huponexit; sleep 5 & echo \$! > $tmp-hup_1
+sleep 5 & echo \$! > $tmp-hup_2; disown -h %2" 2>/dev/null
+sleep 0.2
+alive () { ps -o stat= -p $(cat $tmp-hup_$1) | grep -qv Z ; }
+! alive 1 || err $LINENO
+alive 2 || err $LINENO
+kill $(cat $tmp-hup_2)
+rm -f $tmp-hup_*
+
+### SIGNALS ###
+
+# SIGINT to a foreground command doesn't stop the shell. An interactive
+# shell also skips the rest of the line after Ctrl-C.
+res="$($com <<< 'sh -c "kill -INT \$\$" ; echo $? ; echo after' 2>&1)"
+[ "$res" = "130
+after" ] || err $LINENO
+
+# the exit status of a killed command is 128+signal
+res="$($com <<< 'sh -c "kill -KILL \$\$" ; echo $?
+true | sh -c "kill -TERM \$\$" ; echo $?
+a=$(sh -c "kill -QUIT \$\$") ; echo $?' 2>&1)"
+[ "$res" = "137
+143
+131" ] || err $LINENO
+
 echo OK $0
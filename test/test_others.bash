@@ -39,7 +39,14 @@ EOF
 [ "$res" = '" \ a  bc' ] || err $LINENO
 
 res=$($com <<< 'echo "\a\n\$\`\{\}"')
-[ "$res" = '\a\n$`{}' ] || err $LINENO
+[ "$res" = '\a\n$`\{\}' ] || err $LINENO
+
+res=$($com <<< 'x=1; echo "a\tb" "\$x" "\\$x" "\q\*" $ "a$"')
+[ "$res" = 'a\tb $x \1 \q\* $ a$' ] || err $LINENO
+
+res=$($com <<< 'a="\q\$\\"; echo "$a"; case "\q" in "\q") echo m ;; esac')
+[ "$res" = '\q$\
+m' ] || err $LINENO
 
 res=$($com << 'EOF'
 echo "a'b'c"
@@ -53,6 +60,66 @@ res=$($com <<< 'echo hoge"hoge";')
 res=$($com <<< "echo '\*'")
 [ "$res" = "\*" ] || err $LINENO
 
+# backslash escapes in unquoted words
+res=$($com << 'EOF'
+printf '<%s>\n' a\ b c a\	b
+echo \$HOME "\$x" a\\b a\|b \&c \#d
+echo one \
+two
+EOF
+)
+[ "$res" = "<a b>
+<c>
+<a	b>
+\$HOME \$x a\b a|b &c #d
+one two" ] || err $LINENO
+
+res=$($com << 'EOF'
+echo \q "\q" '\q' $'\q' \\\q
+x='\q'; echo $x "$x" $(echo '\q') "$(echo '\r')"
+echo ${z:-\q} "${z:-\q}" "${z:-\$}"
+EOF
+)
+[ "$res" = "q \\q \\q \\q \\q
+\\q \\q \\q \\r
+q \\q \$" ] || err $LINENO
+
+# ANSI-C quoting
+
+res=$($com <<< "cd glob_test; printf '<%s>' \$'?0' \$'[a]0' '?0' '[a]0' \"?0\" \"[a]0\" ?0; shopt -s extglob; printf '<%s>' \$'@(a0)' '+(b1)' \"!(c2)\"")
+[ "$res" = "<?0><[a]0><?0><[a]0><?0><[a]0><a0><@(a0)><+(b1)><!(c2)>" ] || err $LINENO
+
+res=$($com <<< "echo \$'a\\nb'")
+[ "$res" = "a
+b" ] || err $LINENO
+
+res=$($com <<< "echo \$'\\x41\\x42c' x\$'\\t'y")
+[ "$res" = "ABc x	y" ] || err $LINENO
+
+res=$($com <<< "/bin/echo \$'a\\0b' \$'c\\x00d' \$'e\\u0000f' \$'g\\c@h' x; echo \$?")
+[ "$res" = "a c e g x
+0" ] || err $LINENO
+
+res=$($com <<< "printf '<%s>' \$'\\ca' \$'\\cあ'" | od -An -c | tr -s ' ')
+[ "$res" = " < 001 > < \\ c 343 201 202 >" ] || err $LINENO
+
+res=$($com <<< "echo \$'\\u3042\\U0001F600'")
+[ "$res" = "あ😀" ] || err $LINENO
+
+res=$($com <<< "echo \$'it\\'s \\\\ \\101' \$'\\q' \$'*'")
+[ "$res" = "it's \\ A \\q *" ] || err $LINENO
+
+res=$($com <<< "a=\$'\\cA'; echo -n \"\$a\" | od -An -tx1")
+[ "$res" = " 01" ] || err $LINENO
+
+# locale translation
+
+res=$($com <<< 'a=x; echo $"hello $a" $"$(echo b)"c')
+[ "$res" = "hello x bc" ] || err $LINENO
+
+res=$($com <<< 'a=3; b=$"v  $a"; echo "$b"')
+[ "$res" = "v  3" ] || err $LINENO
+
 res=$($com <<< 'echo )' || true)
 [ "$res" = "" ] || err $LINENO
 
@@ -121,6 +188,50 @@ res=$($com <<< 'echo a{b{}')
 res=$($com <<< 'echo }')
 [ "$res" = '}' ] || err $LINENO
 
+res=$($com <<< 'echo a{b,c,d}e')
+[ "$res" = 'abe ace ade' ] || err $LINENO
+
+res=$($com <<< 'echo {a,b{1,2}}')
+[ "$res" = 'a b1 b2' ] || err $LINENO
+
+res=$($com <<< 'echo {a{b,c}}')
+[ "$res" = '{ab} {ac}' ] || err $LINENO
+
+res=$($com <<< 'echo a{b}c')
+[ "$res" = 'a{b}c' ] || err $LINENO
+
+res=$($com <<< 'printf "[%s]" {a,}')
+[ "$res" = '[a]' ] || err $LINENO
+
+res=$($com <<< 'printf "[%s]" {a,""}')
+[ "$res" = '[a][]' ] || err $LINENO
+
+res=$($com <<< 'echo {1..5} {5..1}')
+[ "$res" = '1 2 3 4 5 5 4 3 2 1' ] || err $LINENO
+
+res=$($com <<< 'echo {01..10}')
+[ "$res" = '01 02 03 04 05 06 07 08 09 10' ] || err $LINENO
+
+res=$($com <<< 'echo {-03..3}')
+[ "$res" = '-03 -02 -01 000 001 002 003' ] || err $LINENO
+
+res=$($com <<< 'echo {1..10..2} {10..1..3} {1..5..-2}')
+[ "$res" = '1 3 5 7 9 10 7 4 1 1 3 5' ] || err $LINENO
+
+res=$(timeout 5 $com <<< 'echo {9223372036854775806..9223372036854775807} {1..3..9223372036854775807}
+echo {-9223372036854775807..-9223372036854775808} {1..3..-9223372036854775808}')
+[ "$res" = '9223372036854775806 9223372036854775807 1
+-9223372036854775807 -9223372036854775808 {1..3..-9223372036854775808}' ] || err $LINENO
+
+res=$($com <<< 'echo {a..e} {e..a..2}')
+[ "$res" = 'a b c d e e c a' ] || err $LINENO
+
+res=$($com <<< 'echo x{1..3}y')
+[ "$res" = 'x1y x2y x3y' ] || err $LINENO
+
+res=$($com <<< 'echo {a..5} {1..} {aa..c} {1.5..3}')
+[ "$res" = '{a..5} {1..} {aa..c} {1.5..3}' ] || err $LINENO
+
 # glob test
 
 res=$($com << 'EOF'
@@ -135,12 +246,84 @@ res=$($com <<< 'echo "*"')
 res=$($com <<< 'echo /')
 [ "$res" = "/" ] || err $LINENO
 
+mkdir -p $tmp-glob/dir
+touch $tmp-glob/b $tmp-glob/a $tmp-glob/ab $tmp-glob/.hidden $tmp-glob/dir/c
+
+res=$($com <<< "cd $tmp-glob; echo * ; echo .* ; echo ? [!a]* ; echo */ */c ./a*")
+[ "$res" = "a ab b dir
+.hidden
+a b b dir
+dir/ dir/c ./a ./ab" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob; echo z* \"a\"* 'a*' a\\*")
+[ "$res" = "z* a ab a* a*" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob; echo a z*; shopt -s nullglob; echo a z* b; echo [z*")
+[ "$res" = "a z*
+a b" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob; shopt -s failglob; echo z*; echo \$?; echo a*" 2>&1)
+[ "$res" = "bash: no match: z*" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob; shopt -s failglob; echo z*; echo \$?
+echo \$?; f(){ echo y*; echo in f; }; f; echo after f
+echo a*" 2>&1)
+[ "$res" = "bash: no match: z*
+1
+bash: no match: y*
+a ab" ] || err $LINENO
+
+mkdir -p $tmp-glob/dir/sub/deep
+touch $tmp-glob/dir/sub/x.txt $tmp-glob/dir/sub/deep/y.txt $tmp-glob/z.txt
+ln -s .. $tmp-glob/dir/sub/loop
+
+res=$($com <<< "cd $tmp-glob; shopt -s globstar; echo **/*.txt; echo dir/**/; echo **/deep")
+[ "$res" = "dir/sub/deep/y.txt dir/sub/x.txt z.txt
+dir/ dir/sub/ dir/sub/deep/ dir/sub/loop/
+dir/sub/deep" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob; echo **/x.txt; shopt -s globstar; echo dir/**")
+[ "$res" = "**/x.txt
+dir/ dir/c dir/sub dir/sub/deep dir/sub/deep/y.txt dir/sub/loop dir/sub/x.txt" ] || err $LINENO
+
+res=$($com <<< "cd $tmp-glob; shopt -s extglob
+echo !(*.txt|dir); echo @(ab|z.txt) +(a|b) ?(a)b*")
+[ "$res" = "a ab b
+ab z.txt a ab b ab b" ] || err $LINENO
+
+mkdir -p $tmp-glob/num
+touch $tmp-glob/num/{10,9,100,x}
+touch -d 2020-01-01 $tmp-glob/num/10
+res=$($com <<< "cd $tmp-glob/num; echo *; GLOBSORT=numeric; echo *; GLOBSORT=-name; echo *
+GLOBSORT=-numeric; echo *; GLOBSORT=mtime; echo 1*")
+[ "$res" = "10 100 9 x
+9 10 100 x
+x 9 100 10
+x 100 10 9
+10 100" ] || err $LINENO
+
+rm -rf $tmp-glob
+
 #The following checks trivial difference between bash and this.
 #$com <<< 'echo //*' | grep -F '//' 
 #$com <<< 'echo /*////' | grep -Fv '//'
 
 # command substitution
 
+res=$($com <<< 'printf "[%s]" "$(printf "a\nb\n\n")" $(printf "a\nb\n")')
+[ "$res" = "[a
+b][a][b]" ] || err $LINENO
+
+res=$($com <<< 'a=$(printf "\na\n\nb\n\n"); printf "[%s]" "$a" "x$(printf "\n\n")y"')
+[ "$res" = "[
+a
+
+b][xy]" ] || err $LINENO
+
+res=$($com <<< 'a="$(seq 2000)"; echo "$a" | tail -n 1; b=$(exit 3); echo $?')
+[ "$res" = "2000
+3" ] || err $LINENO
+
 res=$($com <<< 'echo $(echo hoge)hoge')
 [ "$res" = "hogehoge" ] || err $LINENO
 
@@ -240,6 +423,20 @@ EOF
  2
   3' ] || err $LINENO
 
+# nested substitutions
+echo 41 > $tmp-n.txt
+res=$($com <<< 'echo $(echo $(echo ")") "(")
+echo $(( $(cat '$tmp'-n.txt) + 1 ))
+echo "a$((1+2))b" "$(( $(echo 5) - (1) ))"
+a=3; echo "$(( a + $(echo "$(( a * 2 ))") ))"
+echo "$(echo $(( $(echo 7) % 4 )))"')
+[ "$res" = ') (
+42
+a3b 4
+9
+3' ] || err $LINENO
+rm -f $tmp-n.txt
+
 # expansion of tilde
 
 res=$($com <<< 'echo ~')
@@ -266,6 +463,15 @@ res=$($com <<< "echo ~$user")
 res=$($com <<< "echo {~$user,a}")
 [ "$res" = "$home a" ] || err $LINENO
 
+res=$($com <<< "echo ~$user/x ~nosuchuser/x")
+[ "$res" = "$home/x ~nosuchuser/x" ] || err $LINENO
+
+res=$($com <<< 'cd /tmp; cd /; echo ~+ ~- ~+/x')
+[ "$res" = "/ /tmp //x" ] || err $LINENO
+
+res=$($com <<< 'a=x:~/b:~; echo $a ~"/x"')
+[ "$res" = "x:$HOME/b:$HOME ~/x" ] || err $LINENO
+
 ### DIRECTORY TEST ###
 
 res=$($com << 'EOF'
@@ -293,6 +499,55 @@ world' ] || err $LINENO
 
 ### VARIABLE TEST ###
 
+res=$($com <<< 'a=1 b=2; echo $a $b; c=$(printf "hi  there") d=$((a+b)) e=$a$b"x"; echo "$c" $d $e')
+[ "$res" = "1 2
+hi  there 3 12x" ] || err $LINENO
+
+# append
+
+res=$($com <<< 'a=x; a+=y; a+="$a"; u+=z; echo $a $u; export E=a; E+=b; printenv E; x=1 x+=2 sh -c "echo \$x"')
+[ "$res" = "xyxy z
+ab
+12" ] || err $LINENO
+
+res=$($com <<< 'declare -i n=3; n+=4; echo $n; declare -i m; m+=5; m+=m; echo $m')
+[ "$res" = "7
+10" ] || err $LINENO
+
+res=$($com <<< 'b[0]=p; b+=q; b[1]+=r; b[1]+=s; echo ${b[0]} ${b[1]}; declare -A h; h[k]=v; h[k]+=w; echo ${h[k]}')
+[ "$res" = "pq rs
+vw" ] || err $LINENO
+
+res=$($com <<< 'a=(1); a+=(2 "3 4"); echo ${!a[@]}; echo ${a[2]}; b=x; b+=(y z); echo ${b[@]}; c[5]=five; c+=(six); echo ${!c[@]} ${c[@]}')
+[ "$res" = "0 1 2
+3 4
+x y z
+5 6 five six" ] || err $LINENO
+
+res=$($com << 'EOF'
+d=(a
+  b  c
+)
+d+=($(echo 1 2) {x,y})
+echo ${d[@]}
+EOF
+)
+[ "$res" = "a b c 1 2 x y" ] || err $LINENO
+
+res=$($com <<< 'i=$(echo $HOME) j=$i k=${i}/x; [ "$k" = "$HOME/x" ] && echo ok; m=$(exit 3); echo $?; n=$(exit 2) o=1; echo $? $o')
+[ "$res" = "ok
+3
+2 1" ] || err $LINENO
+
+res=$($com << 'EOF'
+if true; then
+  s=$(echo six)
+fi
+echo $s
+EOF
+)
+[ "$res" = "six" ] || err $LINENO
+
 res=$($com << 'EOF'
 abc=あいうえお
 echo $abc
@@ -336,6 +591,111 @@ res=$($com <<< 'a=c;echo ${a:?b}')
 res=$($com <<< '(echo ${a:?b}) 2>&1')
 [ "$res" = "bash: a: b" ] || err $LINENO
 
+res=$($com <<< 'echo ${a:?b}; echo c')
+[ "$?" = "1" ] || err $LINENO
+[ "$res" = "" ] || err $LINENO
+
+res=$($com <<< 'a=; echo ${a-b}; echo ${a:-b}')
+[ "$res" = "
+b" ] || err $LINENO
+
+res=$($com <<< 'a=; echo ${a=b}; echo ${a:=c}; echo $a')
+[ "$res" = "
+c
+c" ] || err $LINENO
+
+res=$($com <<< 'a=; echo [${a+b}] [${a:+b}] [${x+b}]')
+[ "$res" = "[b] [] []" ] || err $LINENO
+
+res=$($com <<< 'a=; (echo ${a?b}; echo ${a:?}) 2>&1')
+[ "$res" = "
+bash: a: parameter null or not set" ] || err $LINENO
+
+res=$($com <<< 'b=x; echo ${a:-$b ${c:-y} $(echo z)}')
+[ "$res" = "x y z" ] || err $LINENO
+
+res=$($com <<< 'file=a.txt.txt; echo ${file%.txt} ${file%%.*} ${file#*.} ${file##*.}')
+[ "$res" = "a.txt a txt.txt txt" ] || err $LINENO
+
+res=$($com <<< 'path=/usr/local/bin; echo ${path##*/} ${path%/*}')
+[ "$res" = "bin /usr/local" ] || err $LINENO
+
+res=$($com <<< 'a="x*y"; echo "$a" ${a#"x*"} ${a#x\*}')
+[ "$res" = "x*y y y" ] || err $LINENO
+
+res=$($com <<< "a='\\\\'; echo \"\$a\"")
+[ "$res" = '\\' ] || err $LINENO
+
+res=$($com <<< 'a=aXbXc; echo ${a/X/-} ${a//X/-} ${a//X}')
+[ "$res" = "a-bXc a-b-c abc" ] || err $LINENO
+
+res=$($com <<< 'a=abcabc; echo ${a/#ab/Z} ${a/%bc/Z} ${a/#bc/Z} ${a/b*/Z}')
+[ "$res" = "Zcabc abcaZ abcabc aZ" ] || err $LINENO
+
+res=$($com <<< 'a=x/y/z; echo ${a//\//:} "${a//"/"/ }"')
+[ "$res" = "x:y:z x y z" ] || err $LINENO
+
+res=$($com <<< 'a=abcdef; echo ${a:2} ${a:2:3} ${a: -2} ${a: -4:2} ${a:1:-2}')
+[ "$res" = "cdef cde ef cd bcd" ] || err $LINENO
+
+res=$($com <<< 'n=2; a=abcdef; echo ${a:1:100} ${a:$n:$n*2} [${a:10}] [${a: -10}]')
+[ "$res" = "bcdef cdef [] []" ] || err $LINENO
+
+# indirect expansion
+
+res=$($com <<< 'x=y; y=hello; echo ${!x} ${!x#h} ${!x:-def}
+a[0]=a; a[1]=b; p="a[1]"; echo ${!p}
+set -- q w; n=2; echo ${!n}
+z=1a; echo ${!z}' 2>&1)
+[ "$res" = "hello ello hello
+b
+w
+bash: 1a: invalid variable name" ] || err $LINENO
+
+res=$($com <<< 'y=1; yy=2; echo ${!y*}; set -- "${!y@}"; echo $# "$1"; echo "${!nomatch*}" end')
+[ "$res" = "y yy
+2 y
+ end" ] || err $LINENO
+
+# case modification
+
+res=$($com <<< 'v="hello wORLD"; echo ${v^} ${v^^} ${v,} ${v,,}
+echo ${v^^[lo]} ${v,,[A-O]} ${v^[x]}
+u="éclair straße"; echo ${u^} ${u^^}
+a[0]=ab; a[1]=cd; echo ${a[@]^^} ${a[1]^}')
+[ "$res" = "Hello wORLD HELLO WORLD hello wORLD hello world
+heLLO wORLD hello woRld hello wORLD
+Éclair straße ÉCLAIR STRAßE
+AB CD Cd" ] || err $LINENO
+
+# word splitting
+
+res=$($com <<< 'a="x  y"; printf "[%s]" $a "$a" p$a-q')
+[ "$res" = "[x][y][x  y][px][y-q]" ] || err $LINENO
+
+res=$($com <<< 'IFS=:; a="a:b : c:"; printf "[%s]" $a "$a"')
+[ "$res" = "[a][b ][ c][a:b : c:]" ] || err $LINENO
+
+res=$($com <<< 'IFS=; a="a b"; printf "[%s]" $a $(echo c d)')
+[ "$res" = "[a b][c d]" ] || err $LINENO
+
+res=$($com <<< 'IFS=:; set x:y z; printf "[%s]" $@ $(echo 1:2)')
+[ "$res" = "[x][y][z][1][2]" ] || err $LINENO
+
+res=$($com <<< 'a=" "; b=$a$a; printf "[%s]" $a "$b"')
+[ "$res" = "[  ]" ] || err $LINENO
+
+res=$($com <<< 'IFS=:; y=p::r; set -- $y; echo $#; y=:a; printf "<%s>" $y; y=::; printf "<%s>" $y')
+[ "$res" = "3
+<><a><><>" ] || err $LINENO
+
+res=$($com <<< "x=\$'\\uF8FF'; printf '<%s>' \"\$x\" \$x a")
+u=$'\xef\xa3\xbf' # U+F8FF in UTF-8
+[ "$res" = "<$u><$u><a>" ] || err $LINENO
+
+res=$($com <<< 'x=" "; e=; printf "<%s>" x$x"y" $e {,} ""$e a{b,}')
+[ "$res" = "<x><y><><ab><a>" ] || err $LINENO
+
 res=$($com <<< 'LANG=C TZ= date -d 2000-01-01')
 [ "$res" = "Sat Jan  1 00:00:00 UTC 2000" ] || err $LINENO
 
@@ -346,6 +706,29 @@ EOF
 )
 [ "$res" = "Sat Jan  1 00:00:00 UTC 2000" ] || err $LINENO
 
+# associative array (keys are listed in sorted order)
+
+res=$($com <<< 'declare -A m; m[b]=2; m[a]="x y"; k=c; m[$k]=3; m[1+1]=four; echo ${m[a]} ${m[c]} ${m[1+1]} [${m[zz]}] ${m[zz]-def}')
+[ "$res" = "x y 3 four [] def" ] || err $LINENO
+
+res=$($com <<< 'declare -A m; m[b]=2; m[a]=1; m[c]=3; f(){ echo $#; }; f "${!m[@]}"; echo "${!m[@]}"; echo "${m[@]}"; declare -p m')
+[ "$res" = '3
+a b c
+1 2 3
+declare -A m=([a]="1" [b]="2" [c]="3" )' ] || err $LINENO
+
+res=$($com <<< 'declare -A m; m[b]=2; m[a]=1; unset "m[a]"; echo ${!m[*]}; unset m; echo [${m[b]}]')
+[ "$res" = "b
+[]" ] || err $LINENO
+
+res=$($com <<< 'declare -A m; m=([x]=1 [y]="p q"); m+=([j]=c); declare -p m; declare -A n=([$HOME]=h [w]=); echo ${n[$HOME]} [${n[w]}]')
+[ "$res" = 'declare -A m=([j]="c" [x]="1" [y]="p q" )
+h []' ] || err $LINENO
+
+res=$($com <<< 'a=([3]=c d); i=1; a+=([i+5]=e f); declare -p a; f(){ local -A l=([k]=v); echo ${l[k]}; }; f' 2>&1)
+[ "$res" = 'declare -a a=([3]="c" [4]="d" [6]="e" [7]="f")
+v' ] || err $LINENO
+
 # special variable
 
 res=$($com <<< 'ls aaaaaaa; echo $?')
@@ -354,6 +737,15 @@ res=$($com <<< 'ls aaaaaaa; echo $?')
 res=$($com <<< 'echo $$')
 [ "$res" -gt 1 ] || err $LINENO
 
+res=$($com <<< 'echo $$ > /tmp/$$; (cat /tmp/$$; echo $$); rm /tmp/$$' | uniq | wc -l)
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< 'echo $PPID; cut -d" " -f4 /proc/$$/stat' | uniq | wc -l)
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< 'sleep 0 & echo $!; wait' 2>&1 | head -2 | awk '{print $NF}' | uniq | wc -l)
+[ "$res" = "1" ] || err $LINENO
+
 cat << 'EOF' > $tmp 
 echo $@
 echo $*
@@ -460,6 +852,158 @@ EOF
 )
 [ "$?" = "1" ] || err $LINENO
 
+# redirections with pipes
+
+res=$($com <<< 'printf "b\na\nc\n" > /tmp/$$-in; sort < /tmp/$$-in | tr a-z A-Z > /tmp/$$-out; cat /tmp/$$-out; rm /tmp/$$-in /tmp/$$-out')
+[ "$res" = "A
+B
+C" ] || err $LINENO
+
+res=$($com <<< 'echo x | cat < /dev/null; echo y | cat <<< z')
+[ "$res" = "z" ] || err $LINENO
+
+# noclobber
+
+res=$($com <<< "echo a > $tmp-nc; set -C; echo b > $tmp-nc; echo \$?; cat $tmp-nc; rm $tmp-nc" 2>&1)
+[ "$res" = "bash: $tmp-nc: cannot overwrite existing file
+1
+a" ] || err $LINENO
+
+res=$($com <<< 'echo a > /tmp/$$-nc; set -o noclobber; echo b >| /tmp/$$-nc; cat /tmp/$$-nc; echo c > /dev/null; echo $?; rm /tmp/$$-nc')
+[ "$res" = "b
+0" ] || err $LINENO
+
+res=$($com <<< 'rm -f /tmp/$$-nc; set -C; echo a > /tmp/$$-nc; set +C; echo b > /tmp/$$-nc; cat /tmp/$$-nc; rm /tmp/$$-nc')
+[ "$res" = "b" ] || err $LINENO
+
+# append redirection
+
+res=$($com <<< 'echo a > /tmp/$$-ap; echo b >> /tmp/$$-ap; echo c >> /tmp/$$-ap; cat /tmp/$$-ap; rm /tmp/$$-ap')
+[ "$res" = "a
+b
+c" ] || err $LINENO
+
+res=$($com <<< 'rm -f /tmp/$$-ap; ls -d / &>> /tmp/$$-ap; ls /nonexist &>> /tmp/$$-ap; wc -l < /tmp/$$-ap; rm /tmp/$$-ap')
+[ "$res" = "2" ] || err $LINENO
+
+res=$($com <<< 'rm -f /tmp/$$-ap; echo a >> /tmp/$$-ap & echo b >> /tmp/$$-ap & wait; sort /tmp/$$-ap; rm /tmp/$$-ap' 2> /dev/null)
+[ "$res" = "a
+b" ] || err $LINENO
+
+# duplication and closing of file descriptors
+
+res=$($com <<< 'ls /nonexist 2>&-; echo $?' 2>&1)
+[ "$res" = "2" ] || err $LINENO
+
+res=$($com <<< 'ls /nonexist 2>&1 | wc -l; ls /nonexist 2>&1 1>/dev/null | wc -l; ls /nonexist 1>/dev/null 2>&1 | wc -l')
+[ "$res" = "1
+1
+0" ] || err $LINENO
+
+res=$($com <<< '{ echo out; echo err >&2; } 3>&1 1>&2 2>&3- | tr a-z A-Z' 2> /dev/null)
+[ "$res" = "ERR" ] || err $LINENO
+
+res=$($com <<< 'exec 4> /tmp/$$-fd; echo a >&4; exec 4>&-; echo b >&4; echo $?; cat /tmp/$$-fd; rm /tmp/$$-fd' 2>&1)
+[ "$res" = "bash: 4: Bad file descriptor
+1
+a" ] || err $LINENO
+
+res=$($com <<< 'printf "l1\nl2\nl3\n" > /tmp/$$-in; exec 3</tmp/$$-in; read a <&3; echo $a; cat <&3; echo after; exec 4<>/tmp/$$-in; read l <&4; echo got $l; rm /tmp/$$-in' 2>&1)
+[ "$res" = "l1
+l2
+l3
+after
+got l1" ] || err $LINENO
+
+res=$($com <<< 'exec 5</etc/passwd; exec 5<&-; cat <&5; echo $?; exec 6</etc/passwd; exec 7<&6-; read x <&6; echo $?' 2>&1)
+[ "$res" = "bash: 5: Bad file descriptor
+1
+bash: 6: Bad file descriptor
+1" ] || err $LINENO
+
+res=$($com <<< 'echo a >; echo b
+echo $?
+echo c; cat <& ; echo d' 2>&1)
+[ "$res" = "bash: syntax error near unexpected token \`;'
+2
+bash: syntax error near unexpected token \`;'" ] || err $LINENO
+
+# opening a file for reading and writing
+
+res=$($com <<< 'printf "abc\nxyz\n" > /tmp/$$-rw; { read l; echo "got $l"; echo def >&0; } <> /tmp/$$-rw; cat /tmp/$$-rw; rm /tmp/$$-rw')
+[ "$res" = "got abc
+abc
+def" ] || err $LINENO
+
+res=$($com <<< 'printf "12345\n" > /tmp/$$-rw; echo ab 1<> /tmp/$$-rw; cat /tmp/$$-rw; rm -f /tmp/$$-rw; echo c 3<> /tmp/$$-rw; ls /tmp/$$-rw | wc -l; rm /tmp/$$-rw')
+[ "$res" = "ab
+45
+c
+1" ] || err $LINENO
+
+# here documents and here strings
+
+res=$(timeout 10 $com <<< 'cat <<< "$(head -c 4095 /dev/zero | tr "\0" y)" | wc -c; cat <<< "$(head -c 4096 /dev/zero | tr "\0" y)" | wc -c')
+[ "$res" = "4096
+4097" ] || err $LINENO
+
+res=$(timeout 10 $com <<< 'cat <<< "$(head -c 1100000 /dev/zero | tr "\0" y)" | wc -c')
+[ "$res" = "1100001" ] || err $LINENO
+
+res=$(timeout 10 $com << 'EOF'
+cat << END | wc -c
+$(head -c 1100000 /dev/zero | tr "\0" y)
+END
+EOF
+)
+[ "$res" = "1100001" ] || err $LINENO
+
+res=$($com << 'EOF'
+a=world
+cat <<END | tr a-z A-Z > /tmp/$$-out
+hello $a "q" \$a
+END
+cat /tmp/$$-out; rm /tmp/$$-out
+cat <<'END'
+hello $a
+END
+cat <<-END
+		tabbed $a
+	END
+EOF
+)
+[ "$res" = 'HELLO WORLD "Q" $A
+hello $a
+tabbed world' ] || err $LINENO
+
+res=$($com << 'EOF'
+f() {
+  cat <<END | rev
+abc $1
+END
+}
+f x; f y
+cat <<A <<B
+from a
+A
+from b
+B
+while read l; do echo "<$l>"; done <<END
+1
+2
+END
+EOF
+)
+[ "$res" = "x cba
+y cba
+from b
+<1>
+<2>" ] || err $LINENO
+
+res=$($com <<< 'a="x  y"; cat <<< "$a"; read v w <<< "read me"; echo $w')
+[ "$res" = "x  y
+me" ] || err $LINENO
+
 ### PIPELINE ###
 
 res=$($com <<< 'echo abc | rev')
@@ -474,6 +1018,33 @@ res=$($com <<< '! echo abc | rev | tr abc def')
 res=$($com <<< '! echo abc | rev | false')
 [ "$?" = "0" ] || err $LINENO
 
+res=$($com <<< '! true; echo $?; ! false; echo $?; ! ! true; echo $?; ! echo b | grep -q b; echo $?')
+[ "$res" = "1
+0
+0
+1" ] || err $LINENO
+
+res=$($com <<< '!true; echo $?' 2>/dev/null)
+[ "$res" = "127" ] || err $LINENO
+
+# time
+
+res=$($com <<< 'time echo abc | rev; echo $?; time -p false; echo $?; time ! false; echo $?' 2> /dev/null)
+[ "$res" = "cba
+0
+1
+0" ] || err $LINENO
+
+res=$($com <<< 'time sleep 0.1' 2>&1 >/dev/null | grep -E '^(real|user|sys)	0m0\.[0-9]{3}s$' | wc -l)
+[ "$res" = "3" ] || err $LINENO
+
+res=$($com <<< 'time -p true 2> /dev/null' 2>&1 | grep -E '^(real|user|sys) [0-9]+\.[0-9]{2}$' | wc -l)
+[ "$res" = "3" ] || err $LINENO
+
+res=$($com <<< 'time=3; echo $time; type time')
+[ "$res" = "3
+time is a shell keyword" ] || err $LINENO
+
 ### JOB ###
 
 res=$($com <<< '(sleep 1; echo a) & echo b')
@@ -520,6 +1091,37 @@ res=$($com <<< '(A=B);echo $A')
 res=$($com <<< '{A=B ; };echo $A')
 [ "$res" = "B" ] || err $LINENO
 
+res=$($com <<< '{ a=1; cd /; }; ( b=2; cd /tmp ); echo "$a[$b]"; pwd')
+[ "$res" = "1[]
+/" ] || err $LINENO
+
+res=$($com <<< '{ a=1; echo x; } > /dev/null; echo $a; { b=2; } | cat; echo "[$b]"; { read c; } <<< hi; echo $c')
+[ "$res" = "1
+[]
+hi" ] || err $LINENO
+
+res=$($com <<< '{ a=1; } < /nonexistent; echo "[$a]" $?; { echo err >&2; } 2> /dev/null; { exit 3; }; echo no' 2>/dev/null)
+[ $? -eq 3 ] || err $LINENO
+[ "$res" = "[] 1" ] || err $LINENO
+
+# subshell
+
+res=$($com <<< 'cd /; ( a=1; cd /tmp; set -e; alias q=echo; f(){ :; }; exit 4 ); echo $? "[$a]"; pwd; alias q; type f' 2>/dev/null)
+[ "$res" = "4 []
+/" ] || err $LINENO
+
+res=$($com <<< '( false ); echo $?; ( true ) && echo ok; f(){ return 3; }; f | cat; echo $?; ! f | true; echo $?')
+[ "$res" = "1
+ok
+0
+1" ] || err $LINENO
+
+res=$($com <<< 'trap "echo t" EXIT; ( exit 1 ); ( trap "echo in" EXIT; exit 5 ); echo $?; { trap "echo b" EXIT; } | cat')
+[ "$res" = "in
+5
+b
+t" ] || err $LINENO
+
 res=$($com <<< 'echo abc | (rev)')
 [ "$res" = "cba" ] || err $LINENO
 
@@ -544,6 +1146,23 @@ res=$($com <<< '((echo hoge))')
 [ "$?" != "0" ] || err $LINENO
 [ "$res" = "" ] || err $LINENO
 
+# select
+
+res=$($com <<< 'printf "2\n\n9\n1\n" | select x in a "b c" d; do echo "[$x] $REPLY"; done' 2>/dev/null)
+[ "$res" = "[b c] 2
+[] 9
+[a] 1" ] || err $LINENO
+
+res=$($com <<< 'PS3="> "; echo 1 | select x in a b; do echo $x; done' 2>&1)
+[ "$res" = "1) a
+2) b
+> a
+> " ] || err $LINENO
+
+res=$($com <<< 'set -- p q; echo 2 | select y; do echo $y; done ; echo $?' 2>/dev/null)
+[ "$res" = "q
+1" ] || err $LINENO
+
 # compound and read
 
 res=$($com <<< 'echo あ い う | ( read b ; echo $b )')
@@ -566,6 +1185,21 @@ res=$($com <<< '((1));echo $?')
 res=$($com <<< 'echo $((1+2+3))')
 [ "$res" = "6" ] || err $LINENO
 
+res=$($com <<< 'x=9223372036854775807; ((x++)); echo $x; ((x--)); echo $x; ((--x, ++x, ++x)); echo $x')
+[ "$res" = "-9223372036854775808
+9223372036854775807
+-9223372036854775808" ] || err $LINENO
+
+res=$($com <<< 'a=a; echo $((a))' 2>&1)
+[ "$res" = "bash: a: expression recursion level exceeded (error token is \"a\")" ] || err $LINENO
+
+res=$($com <<< "echo \$(( $(printf '%.0s(' {1..20000})1$(printf '%.0s)' {1..20000}) )); echo ok" 2>&1 | grep -o 'expression recursion level exceeded\|^ok')
+[ "$res" = "expression recursion level exceeded
+ok" ] || err $LINENO
+
+res=$($com <<< 'echo $(( 3**4294967297 )) $(( 2**64 ))')
+[ "$res" = "7473929035676909571 0" ] || err $LINENO
+
 res=$($com <<< 'echo $((1-2+3))')
 [ "$res" = "2" ] || err $LINENO
 
@@ -581,6 +1215,38 @@ res=$($com <<< 'echo $((1+2/3))')
 res=$($com <<< 'echo $((-1+2/3))')
 [ "$res" = "-1" ] || err $LINENO
 
+res=$($com <<< 'i=0; ((i++)); echo $? $i; ((i++)); echo $? $i')
+[ "$res" = "1 1
+0 2" ] || err $LINENO
+
+res=$($com <<< 'i=1; ((i=i+1)); echo $i; (( i -= 5 )); echo $i $?')
+[ "$res" = "2
+-3 0" ] || err $LINENO
+
+res=$($com <<< 'a=3; b=a; echo $(( (b+1) * 2 )) $(( $a + 0x10 ))')
+[ "$res" = "8 19" ] || err $LINENO
+
+res=$($com <<< '((1/0)); echo $?')
+[ "$res" = "1" ] || err $LINENO
+
+res=$($com <<< 'echo $((1+2*3-4/2)) $((2**3**2)) $((-2**2)) $((1<<4|1)) $((6&3^1)) $((~5)) $((!0))')
+[ "$res" = "5 512 4 17 3 -6 1" ] || err $LINENO
+
+res=$($com <<< 'echo $((1<2)) $((2<=1)) $((3>2 && 2>3)) $((0 || 5)) $((1==1)) $((1!=1))')
+[ "$res" = "1 0 0 1 1 0" ] || err $LINENO
+
+res=$($com <<< 'echo $((1 ? 2 : 3)) $((0 ? 2 : 3)) $((0 ? 1 : 0 ? 4 : 5)) $(( 1 + 1 ? 10 : 20 ))')
+[ "$res" = "2 3 5 10" ] || err $LINENO
+
+res=$($com <<< 'i=0; echo $((0 && i++)) $((1 || i++)) $((1 ? i++ : i--)) $i $((0 && 1/0))')
+[ "$res" = "0 1 0 1 0" ] || err $LINENO
+
+res=$($com <<< 'x=5; ((x<<=2)); ((x|=1)); echo $x')
+[ "$res" = "21" ] || err $LINENO
+
+res=$($com <<< 'x=$((5/0)); echo $?')
+[ "$res" = "1" ] || err $LINENO
+
 ### MULTILINE INPUT ###
 
 res=$($com << 'EOF'
@@ -591,6 +1257,53 @@ EOF
 )
 [ "$res" = "a b c" ] || err $LINENO
 
+res=$($com << 'EOF'
+echo 'a
+b' 'c\
+d'
+EOF
+)
+[ "$res" = "a
+b c\\
+d" ] || err $LINENO
+
+res=$($com << 'EOF'
+echo "a
+$(echo b)
+c"
+EOF
+)
+[ "$res" = "a
+b
+c" ] || err $LINENO
+
+res=$($com << 'EOF'
+while false ; do
+echo x
+done ; echo y
+EOF
+)
+[ "$res" = "y" ] || err $LINENO
+
+res=$(timeout 5 $com <<< 'echo "abc' 2>&1)
+[ "$?" = "2" ] || err $LINENO
+[ "$res" = 'bash: unexpected EOF while looking for matching `"'"'" ] || err $LINENO
+
+res=$(timeout 5 $com <<< "echo a; echo hi 'abc" 2>/dev/null)
+[ "$?" = "2" ] || err $LINENO
+[ "$res" = "" ] || err $LINENO
+
+res=$(timeout 5 $com <<< "echo hi \$'abc" 2>&1)
+[ "$?" = "2" ] || err $LINENO
+[ "$res" = "bash: unexpected EOF while looking for matching \`''" ] || err $LINENO
+
+rm -f $tmp-created
+$com <<< "touch $tmp-created 'abc" 2>/dev/null
+[ -e $tmp-created ] && err $LINENO
+
+res=$($com <<< "eval \"echo 'a\"; echo \$?" 2>/dev/null)
+[ "$res" = "2" ] || err $LINENO
+
 res=$($com << 'EOF'
 ec\
 ho a\
@@ -600,6 +1313,27 @@ EOF
 )
 [ "$res" = "abc" ] || err $LINENO
 
+res=$($com << 'EOF'
+echo a\\
+echo "b\
+c" d\
+e
+EOF
+)
+[ "$res" = "a\\
+bc de" ] || err $LINENO
+
+res=$($com << 'EOF'
+echo a # c \
+echo b
+echo p#r \
+s
+EOF
+)
+[ "$res" = "a
+b
+p#r s" ] || err $LINENO
+
 res=$($com << 'EOF'
 (
 echo a
@@ -627,6 +1361,57 @@ EOF
 )
 [ "$res" = "cba" ] || err $LINENO
 
+# history expansion
+
+res=$($com << 'EOF' 2>&1
+set -H
+history -s echo a b c
+!!
+echo !$ !^ "!*"
+EOF
+)
+[ "$res" = "echo a b c
+a b c
+echo c a \"a b c\"
+c a a b c" ] || err $LINENO
+
+res=$($com << 'EOF' 2>&1
+set -H
+history -s 'echo "a b" c'\''d e'\'' f\ g;echo h'
+echo !:1 !:2 !:3 !$
+EOF
+)
+[ "$res" = "echo \"a b\" c'd e' f\\ g h
+a b cd e f g h" ] || err $LINENO
+
+res=$($com << 'EOF' 2>&1
+set -H
+history -s echo x
+history -s ls /
+!ec y
+!1:1
+EOF
+)
+[ "$res" = "echo x y
+x y
+x
+Command not found: \"x\"" ] || err $LINENO
+
+res=$($com << 'EOF' 2>&1
+set -H
+history -s echo x
+!nothing ; echo no
+!5
+echo '!!' \!! "a!" ! !=
+EOF
+)
+[ "$res" = "bash: !nothing: event not found
+bash: !5: event not found
+!! !! a! ! !=" ] || err $LINENO
+
+res=$($com <<< 'history -s echo x; echo !!')
+[ "$res" = "!!" ] || err $LINENO
+
 ### FUNCTION ###
 
 res=$($com << 'EOF'
@@ -749,6 +1534,18 @@ EOF
 res=$($com <<< 'a(){ echo x; return ; echo b ; } ; a')
 [ "$res" = "x" ] || err $LINENO
 
+res=$($com << 'EOF'
+f(){ if true; then return 3; fi; echo after; }; f; echo $?
+g(){ while true; do return 4; done; echo after; }; g; echo $?
+h(){ g; return 2; }; h; echo $?
+echo end
+EOF
+)
+[ "$res" = "3
+4
+2
+end" ] || err $LINENO
+
 ### IF COMPOUND ###
 
 res=$($com <<< 'if [ "a" == "a" ] ; then echo aa; fi')
@@ -758,7 +1555,7 @@ res=$($com <<< 'if [ "a" == "b" ] ; then echo aa; fi' )
 [ "$res" = "" ] || err $LINENO
 
 res=$($com <<< 'if [ "a" == "b" ] ; then echo aa; fi' || echo x)
-[ "$res" = "x" ] || err $LINENO
+[ "$res" = "" ] || err $LINENO
 
 res=$($com <<< 'if [ "a" == "b" ] ; then echo a ; fi ; if [ "b" == "b" ] ; then echo bb ; fi')
 [ "$res" = "bb" ] || err $LINENO
@@ -861,6 +1658,20 @@ EOF
 )
 [ "$res" = "a" ] || err $LINENO
 
+res=$($com << 'EOF'
+seq 2 | while read x
+do
+    if [ $x = 2 ]
+    then
+	echo "done $x"
+    fi
+done
+echo end
+EOF
+)
+[ "$res" = "done 2
+end" ] || err $LINENO
+
 res=$($com <<< 'if [ "a" == "b" ] ; then echo aa; elif [ "b" == "b" ] ; then X=Y ; fi; echo $X')
 [ "$res" = "Y" ] || err $LINENO
 
@@ -879,6 +1690,19 @@ rm -f $tmp
 
 ### GLOB FOR CASE ###
 
+res=$($com <<< 'shopt -s extglob
+case foo in @(foo|bar)) echo a;; esac; case baz in @(foo|bar)) echo b;; *) echo c;; esac
+case x.c in !(*.txt|*.log)) echo d;; esac; case x.txt in !(*.txt)) echo e;; *) echo f;; esac
+case abbb in a*(b)) echo g;; esac; case a in a+(b)) echo h;; *) echo i;; esac
+v=foofoobar; echo ${v#+(foo)} ${v##+(foo)} ${v%@(bar|obar)} ${v//@(o|b)/_}')
+[ "$res" = "a
+c
+d
+f
+g
+i
+foobar bar foofoo f__f___ar" ] || err $LINENO
+
 res=$($com <<< 'glob_test "a*" abcde')
 [ "$?" = "0" ] || err $LINENO
 
@@ -927,6 +1751,48 @@ res=$($com <<< 'seq 3 | while read x ; do echo $x🎂 ; done')
 2🎂
 3🎂" ] || err $LINENO
 
+# break and continue
+
+res=$($com <<< 'i=0; while [ $i -lt 5 ]; do i=$((i+1)); [ $i = 2 ] && continue; [ $i = 4 ] && break; echo $i; done; echo end $?')
+[ "$res" = "1
+3
+end 0" ] || err $LINENO
+
+res=$($com << 'EOF'
+i=0
+while true; do
+  i=$((i+1)); j=0
+  while true; do
+    j=$((j+1))
+    [ $j = 2 ] && continue 2
+    [ $i = 3 ] && break 2
+    echo $i$j
+  done
+  echo no
+done
+echo end
+EOF
+)
+[ "$res" = "11
+21
+end" ] || err $LINENO
+
+res=$($com <<< 'while true; do while true; do break 5; done; echo no; done; echo $?; break; echo $?' 2>&1)
+[ "$res" = "0
+bash: break: only meaningful in a \`for', \`while', or \`until' loop
+0" ] || err $LINENO
+
+res=$($com <<< 'while true; do break 0; echo no; done; echo $?' 2>&1)
+[ "$res" = "bash: break: 0: loop count out of range
+1" ] || err $LINENO
+
+res=$($com <<< 'i=0; k(){ break; }; while [ $i -lt 2 ]; do i=$((i+1)); k; echo no $?; done; echo ok' 2>&1)
+[ "$res" = "bash: break: only meaningful in a \`for', \`while', or \`until' loop
+no 0
+bash: break: only meaningful in a \`for', \`while', or \`until' loop
+no 0
+ok" ] || err $LINENO
+
 ### CASE ###
 
 res=$($com <<< 'case $- in *x*) echo x ;; *) echo no ;; esac')
@@ -938,9 +1804,26 @@ res=$($com <<< 'case $- in *x*) ;; *) echo no ;; esac')
 res=$($com -x <<< 'case $- in *x*) echo x ;; *) echo no ;; esac')
 [ "$res" = "x" ] || err $LINENO
 
+res=$($com <<< 'case d in [!a-c]) echo A;; esac; case b in [!a-c]) echo B;; *) echo C;; esac; v=a1b2; echo ${v#?[0-9]} ${v%[!0-9]?} ${v//[0-9]/_}; case "" in ?) echo Q;; *) echo E;; esac')
+[ "$res" = "A
+C
+b2 a1 a_b_
+E" ] || err $LINENO
+
 res=$($com <<< 'A=hoge ; case $A in *x*|*h*) echo aaa ;; *) echo no ;; esac')
 [ "$res" = "aaa" ] || err $LINENO
 
+res=$($com <<< 'x=b; case b in a | $x ) echo y ;; esac; case b in (a|b) echo z ;; esac')
+[ "$res" = "y
+z" ] || err $LINENO
+
+res=$($com <<< 'case "a b" in ""|"a b"|c) echo q ;; esac; case "" in ""|a) echo e ;; esac')
+[ "$res" = "q
+e" ] || err $LINENO
+
+res=$($com <<< 'case b in a||b) echo y ;; esac' 2>/dev/null)
+[ "$res" = "" ] || err $LINENO
+
 res=$($com << 'EOF'
 case xterm-color in
     xterm-color|*-256color) color_prompt=yes;;
@@ -959,6 +1842,34 @@ EOF
 )
 [ "$res" = "no" ] || err $LINENO
 
+res=$($com <<< 'f() { case $1 in a) echo A ;& b) echo B ;& c) echo C ;; d) echo D ;; esac; }; f a; f b; f d')
+[ "$res" = "A
+B
+C
+B
+C
+D" ] || err $LINENO
+
+res=$($com <<< 'f() { case $1 in a*) echo 1 ;;& *b*) echo 2 ;;& *c) echo 3 ;; *) echo 4 ;; esac; }; f abc; f ab')
+[ "$res" = "1
+2
+3
+1
+2
+4" ] || err $LINENO
+
+res=$($com << 'EOF'
+case x in
+  x) echo one
+     ;&
+  y) echo two
+     ;;
+esac
+EOF
+)
+[ "$res" = "one
+two" ] || err $LINENO
+
 cat << EOF > $tmp 
 echo hoge
 EOF
@@ -9,6 +9,7 @@ err () {
 
 cd $(dirname $0)
 com=../target/release/rusty_bash
+tmp=/tmp/$$
 
 ### SIMPLE COMMAND TEST ###
 
@@ -24,4 +25,55 @@ res=$($com <<< '	echo hoge')
 res=$($com <<< 'echo hoge;')
 [ "$res" = "hoge" ] || err $LINENO
 
+# assignments before a command
+
+res=$($com <<< 'A=1 B=2 printenv A B; echo "[$A][$B]"; V=1; V=2 sh -c "echo \$V"; echo $V')
+[ "$res" = "1
+2
+[][]
+2
+1" ] || err $LINENO
+
+res=$($com <<< 'f(){ echo "f:$Y"; Y=9; }; Y=3 f; echo "[$Y]"; IFS=: read a b <<< "1:2"; echo $a $b; HOME=/ cd; pwd')
+[ "$res" = "f:3
+[]
+1 2
+/" ] || err $LINENO
+
+res=$($com <<< 'PATH=/nonexistent ls; echo $?; export E=1; E=2 printenv E; printenv E' 2> /dev/null)
+[ "$res" = "127
+2
+1" ] || err $LINENO
+
+# command search
+
+mkdir -p $tmp-path/a $tmp-path/b $tmp-path/c/rusty_cmd
+printf 'echo A\n' > $tmp-path/a/rusty_cmd
+printf '#!/bin/sh\necho B\n' > $tmp-path/b/rusty_cmd
+chmod +x $tmp-path/b/rusty_cmd
+
+res=$($com <<< "PATH=$tmp-path/c:$tmp-path/a:$tmp-path/b:\$PATH; rusty_cmd; echo \$?; rusty_nonexistent; echo \$?" 2>&1)
+[ "$res" = "B
+0
+Command not found: \"rusty_nonexistent\"
+127" ] || err $LINENO
+
+res=$($com <<< "PATH=/nonexistent; $tmp-path/b/rusty_cmd; echo \$?; $tmp-path/a/rusty_cmd; echo \$?; $tmp-path/c; echo \$?; ./rusty_nonexistent; echo \$?" 2>&1)
+[ "$res" = "B
+0
+bash: $tmp-path/a/rusty_cmd: Permission denied
+126
+bash: $tmp-path/c: Is a directory
+126
+bash: ./rusty_nonexistent: No such file or directory
+127" ] || err $LINENO
+
+rm -rf $tmp-path
+
+res=$($com <<< 'command_not_found_handle() { echo "missing: $1 $# $2"; X=1; return 5; }; rusty_nonexistent a b; echo $? "[$X]"; unset -f command_not_found_handle; rusty_nonexistent; echo $?' 2>&1)
+[ "$res" = "missing: rusty_nonexistent 3 a
+5 []
+Command not found: \"rusty_nonexistent\"
+127" ] || err $LINENO
+
 echo OK $0